@@ -0,0 +1,40 @@
+use std::sync::{Arc, Mutex};
+
+use crate::errors::{MapFileException, Result};
+use crate::map_data::MapReadResult;
+use crate::map_file::MapFile;
+use crate::tile::Tile;
+
+/// A `MapFile` for async callers. Reads run the synchronous decoder on
+/// tokio's blocking pool, one at a time, so they never block the executor.
+#[derive(Clone)]
+pub struct AsyncMapFile {
+    map_file: Arc<Mutex<MapFile>>,
+}
+
+impl AsyncMapFile {
+    pub fn new(map_file: MapFile) -> Self {
+        Self {
+            map_file: Arc::new(Mutex::new(map_file)),
+        }
+    }
+
+    pub async fn read_map_data(&self, tile: &Tile) -> Result<MapReadResult> {
+        let map_file = Arc::clone(&self.map_file);
+        let tile = tile.clone();
+        tokio::task::spawn_blocking(move || {
+            map_file
+                .lock()
+                .map_err(|_| MapFileException::new("map file lock poisoned"))?
+                .read_map_data(&tile)
+        })
+        .await
+        .map_err(|e| MapFileException::new(format!("read task failed: {}", e)))?
+    }
+}
+
+impl From<MapFile> for AsyncMapFile {
+    fn from(map_file: MapFile) -> Self {
+        Self::new(map_file)
+    }
+}
@@ -0,0 +1,44 @@
+use crate::errors::MapFileException;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Decompresses a whole map file if it starts with a gzip or zstd magic
+/// prefix, otherwise returns the bytes unchanged.
+pub(crate) fn decompress(bytes: Vec<u8>) -> Result<Vec<u8>, MapFileException> {
+    if bytes.starts_with(&GZIP_MAGIC) {
+        gunzip(&bytes)
+    } else if bytes.starts_with(&ZSTD_MAGIC) {
+        unzstd(&bytes)
+    } else {
+        Ok(bytes)
+    }
+}
+
+#[cfg(feature = "gzip")]
+fn gunzip(bytes: &[u8]) -> Result<Vec<u8>, MapFileException> {
+    use std::io::Read;
+
+    let mut decompressed = Vec::new();
+    flate2::read::GzDecoder::new(bytes).read_to_end(&mut decompressed)?;
+    Ok(decompressed)
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gunzip(_bytes: &[u8]) -> Result<Vec<u8>, MapFileException> {
+    Err(MapFileException::new(
+        "gzip compressed map file, but the `gzip` feature is not enabled",
+    ))
+}
+
+#[cfg(feature = "zstd")]
+fn unzstd(bytes: &[u8]) -> Result<Vec<u8>, MapFileException> {
+    Ok(zstd::stream::decode_all(bytes)?)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn unzstd(_bytes: &[u8]) -> Result<Vec<u8>, MapFileException> {
+    Err(MapFileException::new(
+        "zstd compressed map file, but the `zstd` feature is not enabled",
+    ))
+}
@@ -2,17 +2,51 @@ use std::fmt;
 use std::io;
 use std::time::SystemTimeError;
 
+/// The category of a `MapFileException`, for callers that need to react to
+/// specific failures rather than just report them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MapFileErrorKind {
+    /// A read of `length` bytes was rejected because it exceeds `max`.
+    BufferTooLarge {
+        length: usize,
+        max: usize,
+    },
+    /// The source ended before the requested bytes could be read.
+    UnexpectedEof,
+    /// A strict query asked for `zoom_level`, above the map's maximum `max`.
+    NoCoverage {
+        zoom_level: u8,
+        max: u8,
+    },
+    /// The header names a projection other than Mercator.
+    UnsupportedProjection(String),
+    Other,
+}
+
+/// The result of the reader's fallible operations.
+pub type Result<T, E = MapFileException> = std::result::Result<T, E>;
+
 #[derive(Debug)]
 pub struct MapFileException {
+    kind: MapFileErrorKind,
     message: String,
 }
 
 impl MapFileException {
     pub fn new(message: impl Into<String>) -> Self {
+        Self::with_kind(MapFileErrorKind::Other, message)
+    }
+
+    pub fn with_kind(kind: MapFileErrorKind, message: impl Into<String>) -> Self {
         Self {
+            kind,
             message: message.into(),
         }
     }
+
+    pub fn kind(&self) -> &MapFileErrorKind {
+        &self.kind
+    }
 }
 
 impl fmt::Display for MapFileException {
@@ -26,7 +60,12 @@ impl std::error::Error for MapFileException {}
 // Add conversion from io::Error to MapFileException
 impl From<io::Error> for MapFileException {
     fn from(err: io::Error) -> Self {
-        MapFileException::new(format!("IO error: {}", err))
+        let kind = if err.kind() == io::ErrorKind::UnexpectedEof {
+            MapFileErrorKind::UnexpectedEof
+        } else {
+            MapFileErrorKind::Other
+        };
+        MapFileException::with_kind(kind, format!("IO error: {}", err))
     }
 }
 
@@ -0,0 +1,47 @@
+use geo_types::{Coord, Geometry, LineString, MultiLineString, Point, Polygon};
+
+use crate::map_data::{PointOfInterest, Way};
+use crate::types::LatLong;
+
+fn line_string(nodes: &[LatLong]) -> LineString<f64> {
+    nodes
+        .iter()
+        .map(|node| Coord {
+            x: node.longitude,
+            y: node.latitude,
+        })
+        .collect()
+}
+
+impl From<&PointOfInterest> for Point<f64> {
+    fn from(poi: &PointOfInterest) -> Self {
+        Point::new(poi.position.longitude, poi.position.latitude)
+    }
+}
+
+impl Way {
+    /// The way as a `geo` geometry with x as longitude and y as latitude.
+    /// Closed ways become a polygon whose first segment is the exterior and
+    /// the others are holes; open ways become a line string, or a multi line
+    /// string if they have several segments.
+    pub fn to_geo_geometry(&self) -> Geometry<f64> {
+        if self.is_closed() {
+            let exterior = line_string(&self.way_nodes[0]);
+            let interiors = self.way_nodes[1..]
+                .iter()
+                .map(|segment| line_string(segment))
+                .collect();
+            return Geometry::Polygon(Polygon::new(exterior, interiors));
+        }
+
+        match self.way_nodes.as_slice() {
+            [segment] => Geometry::LineString(line_string(segment)),
+            segments => Geometry::MultiLineString(MultiLineString::new(
+                segments
+                    .iter()
+                    .map(|segment| line_string(segment))
+                    .collect(),
+            )),
+        }
+    }
+}
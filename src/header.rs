@@ -1,9 +1,10 @@
 use std::io::{Read, Seek};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use tracing::{debug, error, info};
 
 use crate::{
-    errors::MapFileException,
+    errors::{MapFileException, Result},
     optional_field::OptionalFields,
     reader::ReadBuffer,
     required_field::RequiredFields,
@@ -34,6 +35,19 @@ pub struct SubFileParameter {
 impl SubFileParameter {
     pub const BYTES_PER_INDEX_ENTRY: u8 = 5;
 
+    /// The number of the block at `row` and `column`, counted row by row.
+    /// Fails instead of wrapping around for a corrupt `blocks_width`.
+    pub fn block_number(&self, row: i64, column: i64) -> Result<i64> {
+        row.checked_mul(self.blocks_width)
+            .and_then(|start| start.checked_add(column))
+            .ok_or_else(|| {
+                MapFileException::new(format!(
+                    "block number overflow at row {} column {} with {} blocks per row",
+                    row, column, self.blocks_width
+                ))
+            })
+    }
+
     pub fn hash_code(&self) -> i32 {
         let mut result = 7i32;
 
@@ -76,7 +90,7 @@ impl SubFileParameterBuilder {
     pub fn new() -> Self {
         Self::default()
     }
-    pub fn build(self) -> Result<SubFileParameter, MapFileException> {
+    pub fn build(self) -> Result<SubFileParameter> {
         info!("Building SubFileParameter with Java-compatible calculations");
 
         // Get bounding box
@@ -134,8 +148,9 @@ impl SubFileParameterBuilder {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MapFileInfo {
+    /// The bounding box with latitudes clamped to the Mercator range.
     pub bounding_box: BoundingBox,
     pub comment: Option<String>,
     pub created_by: Option<String>,
@@ -147,6 +162,8 @@ pub struct MapFileInfo {
     pub number_of_sub_files: u8,
     pub poi_tags: Vec<Tag>,
     pub projection_name: String,
+    /// The bounding box as stored in the header.
+    pub raw_bounding_box: BoundingBox,
     pub start_position: Option<LatLong>,
     pub start_zoom_level: Option<u8>,
     pub tile_pixel_size: i32,
@@ -155,6 +172,75 @@ pub struct MapFileInfo {
     pub zoom_level_max: u8,
 }
 
+impl MapFileInfo {
+    /// The map date, stored as milliseconds since the epoch. None if it lies
+    /// before the epoch.
+    pub fn map_date_as_system_time(&self) -> Option<SystemTime> {
+        let millis = u64::try_from(self.map_date).ok()?;
+        UNIX_EPOCH.checked_add(Duration::from_millis(millis))
+    }
+
+    /// Tool name and version parsed from `created_by`, e.g.
+    /// `mapsforge-map-writer-0.3.1-SNAPSHOT`. None if the field is missing or
+    /// does not end in a version.
+    pub fn writer_version(&self) -> Option<WriterInfo> {
+        WriterInfo::parse(self.created_by.as_deref()?)
+    }
+}
+
+/// Writer tool and version, see `MapFileInfo::writer_version`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriterInfo {
+    pub tool: String,
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+    /// Suffix after the version, e.g. `SNAPSHOT`.
+    pub pre_release: Option<String>,
+}
+
+impl WriterInfo {
+    fn parse(created_by: &str) -> Option<Self> {
+        // The version starts at the first dash followed by a digit
+        let split = created_by
+            .match_indices('-')
+            .map(|(index, _)| index)
+            .find(|&index| created_by[index + 1..].starts_with(|c: char| c.is_ascii_digit()))?;
+        let tool = &created_by[..split];
+        let (version, pre_release) = match created_by[split + 1..].split_once('-') {
+            Some((version, suffix)) => (version, Some(suffix)),
+            None => (&created_by[split + 1..], None),
+        };
+
+        let mut numbers = version.split('.').map(|part| part.parse::<u32>().ok());
+        let major = numbers.next()??;
+        let minor = numbers.next()??;
+        let patch = numbers.next().unwrap_or(Some(0))?;
+        if tool.is_empty() || numbers.next().is_some() {
+            return None;
+        }
+
+        Some(Self {
+            tool: tool.to_string(),
+            major,
+            minor,
+            patch,
+            pre_release: pre_release
+                .filter(|suffix| !suffix.is_empty())
+                .map(str::to_string),
+        })
+    }
+}
+
+/// Owned copy of a `MapFileInfo`, see `MapFile::info_snapshot`.
+pub type MapFileInfoSnapshot = MapFileInfo;
+
+// Snapshots are meant to cross threads; keep them Send + Sync
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync + Clone>() {}
+    assert_send_sync::<MapFileInfoSnapshot>();
+};
+
 #[derive(Default)]
 pub struct MapFileInfoBuilder {
     pub bounding_box: Option<BoundingBox>,
@@ -165,6 +251,7 @@ pub struct MapFileInfoBuilder {
     pub optional_fields: OptionalFields,
     pub poi_tags: Vec<Tag>,
     pub projection_name: String,
+    pub raw_bounding_box: Option<BoundingBox>,
     pub tile_pixel_size: i32,
     pub way_tags: Vec<Tag>,
     pub zoom_level_min: u8,
@@ -182,6 +269,7 @@ impl MapFileInfoBuilder {
             optional_fields: OptionalFields::default(),
             poi_tags: Vec::new(),
             projection_name: String::new(),
+            raw_bounding_box: None,
             tile_pixel_size: 0,
             way_tags: Vec::new(),
             zoom_level_min: 0,
@@ -189,10 +277,13 @@ impl MapFileInfoBuilder {
         }
     }
 
-    pub fn build(self) -> Result<MapFileInfo, MapFileException> {
+    pub fn build(self) -> Result<MapFileInfo> {
         let bounding_box = self
             .bounding_box
             .ok_or_else(|| MapFileException::new("bounding box is required"))?;
+        let raw_bounding_box = self
+            .raw_bounding_box
+            .unwrap_or_else(|| bounding_box.clone());
 
         Ok(MapFileInfo {
             bounding_box,
@@ -206,6 +297,7 @@ impl MapFileInfoBuilder {
             number_of_sub_files: self.number_of_sub_files,
             poi_tags: self.poi_tags,
             projection_name: self.projection_name,
+            raw_bounding_box,
             start_position: self.optional_fields.start_position,
             start_zoom_level: self.optional_fields.start_zoom_level,
             tile_pixel_size: self.tile_pixel_size,
@@ -219,6 +311,11 @@ impl MapFileInfoBuilder {
 pub struct MapFileHeader {
     map_file_info: Option<MapFileInfo>,
     sub_file_parameters: Option<Vec<SubFileParameter>>,
+    // One entry per sub-file, in file order
+    distinct_sub_file_parameters: Vec<SubFileParameter>,
+    lenient_file_size: bool,
+    // The header exactly as stored, magic bytes included
+    raw_bytes: Vec<u8>,
     zoom_level_maximum: u8,
     zoom_level_minimum: u8,
 }
@@ -233,6 +330,9 @@ impl MapFileHeader {
         Self {
             map_file_info: None,
             sub_file_parameters: None,
+            distinct_sub_file_parameters: Vec::new(),
+            lenient_file_size: false,
+            raw_bytes: Vec::new(),
             zoom_level_maximum: 0,
             zoom_level_minimum: u8::MAX,
         }
@@ -242,6 +342,11 @@ impl MapFileHeader {
         self.map_file_info.as_ref()
     }
 
+    /// The header bytes as read from the file, starting with the magic bytes.
+    pub fn raw_bytes(&self) -> &[u8] {
+        &self.raw_bytes
+    }
+
     pub fn get_query_zoom_level(&self, zoom_level: u8) -> u8 {
         if zoom_level > self.zoom_level_maximum {
             self.zoom_level_maximum
@@ -272,41 +377,104 @@ impl MapFileHeader {
         })
     }
 
+    /// Every sub-file of the map once, in the order they appear in the file.
+    pub fn sub_file_parameters(&self) -> &[SubFileParameter] {
+        &self.distinct_sub_file_parameters
+    }
+
+    /// The sub-file whose base zoom level is exactly `base_zoom_level`.
+    pub fn get_sub_file_parameter_by_base_zoom(
+        &self,
+        base_zoom_level: u8,
+    ) -> Option<&SubFileParameter> {
+        self.sub_file_parameters
+            .as_ref()
+            .and_then(|params| params.iter().find(|p| p.base_zoom_level == base_zoom_level))
+    }
+
+    /// Accept a header whose stored file size differs from the actual size,
+    /// logging a warning instead of failing. Must be set before reading.
+    pub fn set_lenient_file_size(&mut self, lenient: bool) {
+        self.lenient_file_size = lenient;
+    }
+
+    // Reads the magic bytes and the rest of the header into the buffer,
+    // keeping a copy of both
+    fn read_header_block<R: Read + Seek>(&mut self, read_buffer: &mut ReadBuffer<R>) -> Result<()> {
+        RequiredFields::read_magic_byte(read_buffer)?;
+        self.raw_bytes = read_buffer.get_buffer_data().to_vec();
+        RequiredFields::read_remaining_header(read_buffer)?;
+        self.raw_bytes
+            .extend_from_slice(read_buffer.get_buffer_data());
+        Ok(())
+    }
+
     pub fn read_header<R: Read + Seek>(
         &mut self,
         read_buffer: &mut ReadBuffer<R>,
         file_size: i64,
-    ) -> Result<(), MapFileException> {
-        RequiredFields::read_magic_byte(read_buffer)?;
-        RequiredFields::read_remaining_header(read_buffer)?;
+    ) -> Result<()> {
+        self.read_header_block(read_buffer)?;
 
         let mut map_file_info_builder = MapFileInfoBuilder::new();
 
-        RequiredFields::read_file_version(read_buffer, &mut map_file_info_builder)?;
-        RequiredFields::read_file_size(read_buffer, file_size, &mut map_file_info_builder)?;
-        RequiredFields::read_map_date(read_buffer, &mut map_file_info_builder)?;
-        RequiredFields::read_bounding_box(read_buffer, &mut map_file_info_builder)?;
-        RequiredFields::read_tile_pixel_size(read_buffer, &mut map_file_info_builder)?;
-        RequiredFields::read_projection_name(read_buffer, &mut map_file_info_builder)?;
+        self.read_info_fields(read_buffer, file_size, &mut map_file_info_builder)?;
+        self.read_sub_file_parameters(read_buffer, file_size, &mut map_file_info_builder)?;
 
-        let mut optional_fields = OptionalFields::new(read_buffer.read_byte()?);
-        optional_fields.read_optional_fields(read_buffer)?;
-        map_file_info_builder.optional_fields = optional_fields;
-        RequiredFields::read_poi_tags(read_buffer, &mut map_file_info_builder)?;
-        RequiredFields::read_way_tags(read_buffer, &mut map_file_info_builder)?;
+        self.map_file_info = Some(map_file_info_builder.build()?);
+        Ok(())
+    }
 
-        self.read_sub_file_parameters(read_buffer, file_size, &mut map_file_info_builder)?;
+    /// Reads the header up to and including the tag tables, without building
+    /// the sub-file parameters. The zoom range of the resulting info is left
+    /// at zero, as it is derived from the sub-files.
+    pub fn read_info_only<R: Read + Seek>(
+        &mut self,
+        read_buffer: &mut ReadBuffer<R>,
+        file_size: i64,
+    ) -> Result<()> {
+        self.read_header_block(read_buffer)?;
+
+        let mut map_file_info_builder = MapFileInfoBuilder::new();
+        self.read_info_fields(read_buffer, file_size, &mut map_file_info_builder)?;
+        map_file_info_builder.number_of_sub_files = read_buffer.read_byte()?;
 
         self.map_file_info = Some(map_file_info_builder.build()?);
         Ok(())
     }
 
+    fn read_info_fields<R: Read + Seek>(
+        &self,
+        read_buffer: &mut ReadBuffer<R>,
+        file_size: i64,
+        map_file_info_builder: &mut MapFileInfoBuilder,
+    ) -> Result<()> {
+        RequiredFields::read_file_version(read_buffer, map_file_info_builder)?;
+        RequiredFields::read_file_size(
+            read_buffer,
+            file_size,
+            self.lenient_file_size,
+            map_file_info_builder,
+        )?;
+        RequiredFields::read_map_date(read_buffer, map_file_info_builder)?;
+        RequiredFields::read_bounding_box(read_buffer, map_file_info_builder)?;
+        RequiredFields::read_tile_pixel_size(read_buffer, map_file_info_builder)?;
+        RequiredFields::read_projection_name(read_buffer, map_file_info_builder)?;
+
+        let mut optional_fields = OptionalFields::new(read_buffer.read_byte()?);
+        optional_fields.read_optional_fields(read_buffer)?;
+        map_file_info_builder.optional_fields = optional_fields;
+        RequiredFields::read_poi_tags(read_buffer, map_file_info_builder)?;
+        RequiredFields::read_way_tags(read_buffer, map_file_info_builder)?;
+        Ok(())
+    }
+
     fn read_sub_file_parameters<R: Read + Seek>(
         &mut self,
         read_buffer: &mut ReadBuffer<R>,
         file_size: i64,
         map_file_info_builder: &mut MapFileInfoBuilder,
-    ) -> Result<(), MapFileException> {
+    ) -> Result<()> {
         let number_of_sub_files = read_buffer.read_byte()?;
         if number_of_sub_files < 1 {
             return Err(MapFileException::new(format!(
@@ -423,6 +591,7 @@ impl MapFileHeader {
         }
 
         self.sub_file_parameters = Some(sub_file_parameters);
+        self.distinct_sub_file_parameters = temp_sub_file_parameters;
         Ok(())
     }
 }
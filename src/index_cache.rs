@@ -1,12 +1,12 @@
 use crate::deserializer::Deserializer;
 
 use crate::header::SubFileParameter;
-use crate::MapFileException;
+use crate::{MapFileException, Result};
 use lru::LruCache;
 use std::collections::HashMap;
 use std::io::{Read, Seek, SeekFrom};
 use std::num::NonZeroUsize;
-use tracing::{debug, error, info};
+use tracing::trace;
 
 const INDEX_ENTRIES_PER_BLOCK: usize = 128;
 const SIZE_OF_INDEX_BLOCK: usize =
@@ -58,29 +58,143 @@ impl std::hash::Hash for IndexCacheEntryKey {
     }
 }
 
+/// Hit and miss counts of the index cache, for tuning its capacity.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct IndexCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub len: usize,
+    pub capacity: usize,
+}
+
 pub struct IndexCache<R: Read + Seek> {
-    map: LruCache<IndexCacheEntryKey, Vec<u8>>,
+    // None when caching is disabled, every lookup then reads the file
+    map: Option<LruCache<IndexCacheEntryKey, Vec<u8>>>,
     file_channel: R,
+    hits: u64,
+    misses: u64,
 }
 
 impl<R: Read + Seek> IndexCache<R> {
+    /// A cache of `capacity` index blocks. A capacity of 0 disables caching.
     pub fn new(file_channel: R, capacity: usize) -> Self {
-        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::new(1).unwrap());
         Self {
-            map: LruCache::new(capacity),
+            map: NonZeroUsize::new(capacity).map(LruCache::new),
             file_channel,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.as_ref().map_or(0, LruCache::len)
+    }
+
+    pub fn stats(&self) -> IndexCacheStats {
+        IndexCacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            len: self.len(),
+            capacity: self.map.as_ref().map_or(0, |map| map.cap().get()),
         }
     }
 
     pub fn destroy(&mut self) {
-        self.map.clear();
+        if let Some(map) = self.map.as_mut() {
+            map.clear();
+        }
+    }
+
+    /// Loads the index blocks covering `from_block..=to_block` that are not
+    /// cached yet, with one sequential read per run of missing index blocks.
+    /// At most `capacity` index blocks are loaded, so the prefetch does not
+    /// evict its own entries. Each index block read counts as a miss.
+    pub fn prefetch(
+        &mut self,
+        sub_file_parameter: &SubFileParameter,
+        from_block: i64,
+        to_block: i64,
+    ) -> Result<()> {
+        let Some(capacity) = self.map.as_ref().map(|map| map.cap().get()) else {
+            return Ok(());
+        };
+        let to_block = to_block.min(sub_file_parameter.number_of_blocks - 1);
+        if from_block < 0 || from_block > to_block {
+            return Ok(());
+        }
+
+        let first = from_block / INDEX_ENTRIES_PER_BLOCK as i64;
+        let last = (to_block / INDEX_ENTRIES_PER_BLOCK as i64).min(first + capacity as i64 - 1);
+
+        let mut index_block_number = first;
+        while index_block_number <= last {
+            if self.is_cached(sub_file_parameter, index_block_number) {
+                index_block_number += 1;
+                continue;
+            }
+
+            // Extend the run over all following index blocks that are missing
+            let run_start = index_block_number;
+            while index_block_number < last
+                && !self.is_cached(sub_file_parameter, index_block_number + 1)
+            {
+                index_block_number += 1;
+            }
+            self.read_index_blocks(sub_file_parameter, run_start, index_block_number)?;
+            index_block_number += 1;
+        }
+
+        Ok(())
+    }
+
+    fn is_cached(&self, sub_file_parameter: &SubFileParameter, index_block_number: i64) -> bool {
+        self.map.as_ref().is_some_and(|map| {
+            map.contains(&IndexCacheEntryKey::new(
+                sub_file_parameter.clone(),
+                index_block_number,
+            ))
+        })
+    }
+
+    fn read_index_blocks(
+        &mut self,
+        sub_file_parameter: &SubFileParameter,
+        first: i64,
+        last: i64,
+    ) -> Result<()> {
+        let position = sub_file_parameter.index_start_address + first * SIZE_OF_INDEX_BLOCK as i64;
+        let end = (sub_file_parameter.index_start_address
+            + (last + 1) * SIZE_OF_INDEX_BLOCK as i64)
+            .min(sub_file_parameter.index_end_address);
+        if end <= position {
+            return Err(MapFileException::new("invalid index block size"));
+        }
+
+        let mut bytes = vec![0u8; (end - position) as usize];
+        self.file_channel.seek(SeekFrom::Start(position as u64))?;
+        self.file_channel.read_exact(&mut bytes)?;
+        trace!(
+            "Prefetched index blocks {} to {} ({} bytes)",
+            first,
+            last,
+            bytes.len()
+        );
+
+        for (offset, chunk) in bytes.chunks(SIZE_OF_INDEX_BLOCK).enumerate() {
+            let key = IndexCacheEntryKey::new(sub_file_parameter.clone(), first + offset as i64);
+            if let Some(map) = self.map.as_mut() {
+                map.put(key, chunk.to_vec());
+            }
+            self.misses += 1;
+        }
+        Ok(())
     }
 
     pub fn get_index_entry(
         &mut self,
         sub_file_parameter: &SubFileParameter,
         block_number: i64,
-    ) -> Result<i64, MapFileException> {
+    ) -> Result<i64> {
         // Check if the block number is out of bounds (similar to Java)
         if block_number >= sub_file_parameter.number_of_blocks {
             return Err(MapFileException::new(format!(
@@ -95,10 +209,13 @@ impl<R: Read + Seek> IndexCache<R> {
 
         let key = IndexCacheEntryKey::new(sub_file_parameter.clone(), index_block_number);
 
-        let index_block = if let Some(block) = self.map.get(&key) {
-            block.clone()
+        let cached = self.map.as_mut().and_then(|map| map.get(&key).cloned());
+        let index_block = if let Some(block) = cached {
+            self.hits += 1;
+            block
         } else {
             // Cache miss, read from file
+            self.misses += 1;
             // Replicate Java's calculation logic without overflow checks
             let index_block_position = sub_file_parameter.index_start_address
                 + index_block_number * SIZE_OF_INDEX_BLOCK as i64;
@@ -133,7 +250,9 @@ impl<R: Read + Seek> IndexCache<R> {
                 }
             }
 
-            self.map.put(key, index_block.clone());
+            if let Some(map) = self.map.as_mut() {
+                map.put(key, index_block.clone());
+            }
             index_block
         };
 
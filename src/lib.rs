@@ -1,28 +1,60 @@
+#[cfg(feature = "async")]
+mod async_map_file;
+mod compression;
 mod deserializer;
 mod errors;
+#[cfg(feature = "geo")]
+mod geometry;
 mod header;
 mod index_cache;
 mod map_data;
 pub mod map_file;
 mod mercator;
+mod observer;
 mod optional_field;
 mod query_calculations;
 mod query_parameters;
+pub mod raster;
 mod reader;
 mod required_field;
+mod serializer;
+mod source;
+#[cfg(feature = "test-util")]
+mod test_util;
 mod tile;
 mod types;
 
 // Create a single, consistent public API
+#[cfg(feature = "async")]
+pub use async_map_file::AsyncMapFile;
 pub use deserializer::Deserializer;
-pub use errors::MapFileException;
-pub use header::{MapFileHeader, MapFileInfo, SubFileParameter};
-pub use map_file::MapFile;
+pub use errors::{MapFileErrorKind, MapFileException, Result};
+pub use header::{MapFileHeader, MapFileInfo, MapFileInfoSnapshot, SubFileParameter, WriterInfo};
+pub use index_cache::IndexCacheStats;
 pub use map_file::Selector;
+pub use map_file::{
+    decode_way_coordinates, BlockIterator, FeatureFlags, MapFile, MapFileBuilder, ReadOptions,
+};
 pub use mercator::MercatorProjection;
+pub use observer::{NoopObserver, ReadObserver, TracingObserver};
+pub use query_calculations::QueryCalculations;
 pub use query_parameters::QueryParameters;
+pub use reader::{ByteOrder, ReadBuffer};
+pub use serializer::Serializer;
+pub use source::BlockSource;
+#[cfg(feature = "test-util")]
+pub use test_util::{PoiBuilder, WayBuilder};
 pub use tile::Tile;
-pub use types::{BoundingBox, LatLong, Tag};
+pub use types::{
+    BoundingBox, CoordinatePrecision, Interner, LabelPosition, LatLong, LatLongE6, Layer, Tag,
+};
 
 // Re-export these types ONLY from map_data, not from multiple places
-pub use map_data::{MapReadResult, PoiWayBundle, PointOfInterest, Way};
+pub use map_data::{
+    centroid, clip_way_to_bbox, cluster_pois, densify, way_tiles, DrawItem, Feature, Geometry,
+    HighwayKind, LanduseKind, MapReadResult, MapStats, NamedItem, NaturalKind, PoiCluster,
+    PoiFilter, PoiWayBundle, PointOfInterest, ProjectedMapResult, ProjectedPoi, ProjectedWay,
+    WaterwayKind, Way, WayClass,
+};
+#[cfg(feature = "debug")]
+pub use map_data::{RawBlock, RawWay, ZoomRow};
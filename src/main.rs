@@ -1,13 +1,11 @@
 use minifb::{Key, Window, WindowOptions};
+use reader::raster::{draw_thick_line, fill_polygon, set_pixel};
 use reader::{
+    LatLong,
     MapFile,
     MapReadResult, // This should now consistently refer to one type
-    MercatorProjection,
-    PoiWayBundle, // Same here
-    Tile,
+    PoiWayBundle,  // Same here
 };
-use std::cmp::max;
-use std::cmp::min;
 use std::collections::HashMap;
 use std::path::Path;
 use std::time::{Duration, Instant};
@@ -15,16 +13,15 @@ use std::time::{Duration, Instant};
 const WINDOW_WIDTH: usize = 800;
 const WINDOW_HEIGHT: usize = 600;
 const INITIAL_ZOOM_LEVEL: u8 = 14;
-const TILE_SIZE: usize = 256;
 
 // Initial view center coordinates
 const INITIAL_LAT: f64 = 26.7428831;
 const INITIAL_LON: f64 = 93.9074701;
 
-// Cache structure for map data
+// Cache structure for map data, keyed by the tiles covering the window
 struct TileCache {
-    tile_x: i64,
-    tile_y: i64,
+    upper_left: (i64, i64),
+    lower_right: (i64, i64),
     zoom: u8,
     data: reader::MapReadResult,
 }
@@ -129,22 +126,26 @@ impl MapRenderer {
             *pixel = 0x00F0F0F0;
         }
 
-        // Calculate current tile
-        let tile_x = MercatorProjection::longitude_to_tile_x(self.center_lon, self.zoom);
-        let tile_y = MercatorProjection::latitude_to_tile_y(self.center_lat, self.zoom);
-
-        // Check if we have this tile cached
-        let map_data = if let Some(cache) = &self.tile_cache {
-            if cache.tile_x == tile_x && cache.tile_y == tile_y && cache.zoom == self.zoom {
+        // Calculate the tiles covering the window
+        let center = LatLong::new(self.center_lat, self.center_lon);
+        let (upper_left, lower_right) =
+            self.map_file
+                .viewport_tiles(&center, self.zoom, width as u32, height as u32);
+        let upper_left = (upper_left.tile_x, upper_left.tile_y);
+        let lower_right = (lower_right.tile_x, lower_right.tile_y);
+
+        // Check if we have these tiles cached
+        let map_data = match &self.tile_cache {
+            Some(cache)
+                if cache.upper_left == upper_left
+                    && cache.lower_right == lower_right
+                    && cache.zoom == self.zoom =>
+            {
                 // Use cached data
                 cache.data.clone()
-            } else {
-                // Need to load new data
-                self.load_new_tile(tile_x, tile_y)?
             }
-        } else {
-            // First tile load
-            self.load_new_tile(tile_x, tile_y)?
+            // Need to load new data
+            _ => self.load_viewport(&center, width, height, upper_left, lower_right)?,
         };
 
         // Create and return the render state
@@ -160,35 +161,32 @@ impl MapRenderer {
         })
     }
 
-    // Function to load a new tile and update cache
-    fn load_new_tile(&mut self, tile_x: i64, tile_y: i64) -> Result<reader::MapReadResult, String> {
+    // Function to load the tiles covering the window and update cache
+    fn load_viewport(
+        &mut self,
+        center: &LatLong,
+        width: usize,
+        height: usize,
+        upper_left: (i64, i64),
+        lower_right: (i64, i64),
+    ) -> Result<reader::MapReadResult, String> {
         println!(
-            "Loading new tile: x={}, y={}, zoom={}",
-            tile_x, tile_y, self.zoom
+            "Loading tiles: x={}..={}, y={}..={}, zoom={}",
+            upper_left.0, lower_right.0, upper_left.1, lower_right.1, self.zoom
         );
-        let tile = Tile::new(tile_x, tile_y, self.zoom, TILE_SIZE as i32);
 
-        match self.map_file.read_map_data(&tile) {
+        match self
+            .map_file
+            .read_viewport(center, self.zoom, width as u32, height as u32)
+        {
             Ok(data) => {
-                // We need to convert the return type to the expected type
-                // Create a new MapReadResult with the data from the original
-                let map_data = reader::MapReadResult {
-                    poi_way_bundles: data.poi_way_bundles.clone(),
-                    is_water: data.is_water,
-                };
-
-                // Update cache with the same converted data
                 self.tile_cache = Some(TileCache {
-                    tile_x,
-                    tile_y,
+                    upper_left,
+                    lower_right,
                     zoom: self.zoom,
-                    data: reader::MapReadResult {
-                        poi_way_bundles: data.poi_way_bundles,
-                        is_water: data.is_water,
-                    },
+                    data: data.clone(),
                 });
-
-                Ok(map_data)
+                Ok(data)
             }
             Err(e) => Err(format!("Error reading map data: {}", e)),
         }
@@ -237,69 +235,6 @@ impl MapRenderer {
             }
         }
     }
-    // Change the fill_polygon method to take a buffer directly instead of using self
-
-    fn fill_polygon(
-        points: &[(i32, i32)],
-        color: u32,
-        buffer: &mut [u32],
-        width: usize,
-        height: usize,
-    ) {
-        if points.len() < 3 {
-            return; // Need at least 3 points for a polygon
-        }
-
-        // Find the bounding box of the polygon
-        let mut min_y = i32::MAX;
-        let mut max_y = i32::MIN;
-
-        for &(_, y) in points {
-            min_y = min(min_y, y);
-            max_y = max(max_y, y);
-        }
-
-        // Clip to screen bounds
-        min_y = max(0, min_y);
-        max_y = min(height as i32 - 1, max_y);
-
-        // For each scanline
-        for y in min_y..=max_y {
-            let mut nodes = Vec::new();
-
-            // Find intersections with polygon edges
-            for i in 0..points.len() {
-                let j = (i + 1) % points.len();
-                let (x1, y1) = points[i];
-                let (x2, y2) = points[j];
-
-                // Check if the edge crosses this scanline
-                if (y1 <= y && y2 > y) || (y2 <= y && y1 > y) {
-                    // Calculate x-coordinate of intersection
-                    let x = x1 + ((y - y1) as f64 * (x2 - x1) as f64 / (y2 - y1) as f64) as i32;
-                    nodes.push(x);
-                }
-            }
-
-            // Sort intersections
-            nodes.sort();
-
-            // Fill pixel pairs
-            for i in (0..nodes.len()).step_by(2) {
-                if i + 1 < nodes.len() {
-                    let start_x = max(0, nodes[i]);
-                    let end_x = min(width as i32 - 1, nodes[i + 1]);
-
-                    for x in start_x..=end_x {
-                        if x >= 0 && x < width as i32 && y >= 0 && y < height as i32 {
-                            buffer[(y as usize) * width + (x as usize)] = color;
-                        }
-                    }
-                }
-            }
-        }
-    }
-
     fn darken_color(color: u32, factor: f64) -> u32 {
         let r = ((color >> 16) & 0xFF) as f64 * factor;
         let g = ((color >> 8) & 0xFF) as f64 * factor;
@@ -339,65 +274,6 @@ impl MapRenderer {
             (center_x + dx as i32, center_y + dy as i32)
         };
 
-        // Function to set a pixel if it's within bounds
-        let set_pixel = |x: i32, y: i32, color: u32, buffer: &mut [u32], width: usize| {
-            if x >= 0 && x < width as i32 && y >= 0 && y < buffer.len() as i32 / width as i32 {
-                buffer[(y as usize) * width + (x as usize)] = color;
-            }
-        };
-
-        // Function to draw a thick line
-        let draw_thick_line = |x0: i32,
-                               y0: i32,
-                               x1: i32,
-                               y1: i32,
-                               color: u32,
-                               width: u8,
-                               buffer: &mut [u32],
-                               buffer_width: usize| {
-            // Draw a basic line using Bresenham's algorithm
-            let dx = (x1 - x0).abs();
-            let dy = -(y1 - y0).abs();
-            let sx = if x0 < x1 { 1 } else { -1 };
-            let sy = if y0 < y1 { 1 } else { -1 };
-            let mut err = dx + dy;
-
-            let mut x = x0;
-            let mut y = y0;
-
-            // For thickness, draw pixels in a square pattern around each point
-            let thickness = width as i32 / 2;
-
-            loop {
-                // Draw a square around the current point for thickness
-                for dy in -thickness..=thickness {
-                    for dx in -thickness..=thickness {
-                        set_pixel(x + dx, y + dy, color, buffer, buffer_width);
-                    }
-                }
-
-                if x == x1 && y == y1 {
-                    break;
-                }
-
-                let e2 = 2 * err;
-                if e2 >= dy {
-                    if x == x1 {
-                        break;
-                    }
-                    err += dy;
-                    x += sx;
-                }
-                if e2 <= dx {
-                    if y == y1 {
-                        break;
-                    }
-                    err += dx;
-                    y += sy;
-                }
-            }
-        };
-
         // Clear the buffer (light gray background)
         for pixel in self.buffer.iter_mut() {
             *pixel = 0x00F0F0F0;
@@ -419,15 +295,15 @@ impl MapRenderer {
                 // Check tags to determine if it's an area and what color to use
                 for tag in &way.tags {
                     // Debug logging for features
-                    if tag.key == "natural" || tag.key == "landuse" {
+                    if tag.as_key() == "natural" || tag.as_key() == "landuse" {
                         has_natural_features = true;
                         println!("Found natural feature: {}={}", tag.key, tag.value);
                     }
-                    if tag.key == "waterway" {
+                    if tag.as_key() == "waterway" {
                         has_water_features = true;
                         println!("Found water feature: {}={}", tag.key, tag.value);
                     }
-                    if tag.key == "area" && tag.value == "yes" {
+                    if tag.as_key() == "area" && tag.as_value() == "yes" {
                         has_any_areas = true;
                         println!("Found area feature");
                         is_area = true;
@@ -441,11 +317,12 @@ impl MapRenderer {
                     }
 
                     // Some special cases for area detection
-                    if (tag.key == "natural" && (tag.value == "sea" || tag.value == "water"))
-                        || (tag.key == "landuse"
-                            && (tag.value == "forest"
-                                || tag.value == "industrial"
-                                || tag.value == "quarry"))
+                    if (tag.as_key() == "natural"
+                        && (tag.as_value() == "sea" || tag.as_value() == "water"))
+                        || (tag.as_key() == "landuse"
+                            && (tag.as_value() == "forest"
+                                || tag.as_value() == "industrial"
+                                || tag.as_value() == "quarry"))
                     {
                         is_area = true;
                         let tag_key = format!("{}={}", tag.key, tag.value);
@@ -469,32 +346,17 @@ impl MapRenderer {
                         }
 
                         // Fill the polygon
-                        Self::fill_polygon(
-                            &polygon_points,
-                            area_color,
-                            &mut self.buffer,
-                            width,
-                            height,
-                        );
+                        fill_polygon(&polygon_points, area_color, &mut self.buffer, width, height);
 
                         // Draw the outline
                         for i in 0..segment.len() {
                             let j = (i + 1) % segment.len();
-                            let (x0, y0) = to_screen(segment[i].latitude, segment[i].longitude);
-                            let (x1, y1) = to_screen(segment[j].latitude, segment[j].longitude);
+                            let from = to_screen(segment[i].latitude, segment[i].longitude);
+                            let to = to_screen(segment[j].latitude, segment[j].longitude);
 
                             // Draw a slightly darker outline
                             let outline_color = Self::darken_color(area_color, 0.8);
-                            draw_thick_line(
-                                x0,
-                                y0,
-                                x1,
-                                y1,
-                                outline_color,
-                                1,
-                                &mut self.buffer,
-                                width,
-                            );
+                            draw_thick_line(from, to, outline_color, 1, &mut self.buffer, width);
                         }
                     }
                 }
@@ -508,7 +370,7 @@ impl MapRenderer {
                 for tag in &way.tags {
                     let tag_key = format!("{}={}", tag.key, tag.value);
                     if area_styles.contains_key(&tag_key)
-                        || (tag.key == "area" && tag.value == "yes")
+                        || (tag.as_key() == "area" && tag.as_value() == "yes")
                     {
                         is_area = true;
                         break;
@@ -528,18 +390,22 @@ impl MapRenderer {
                     let tag_key = format!("{}={}", tag.key, tag.value);
 
                     // Check for hiking paths
-                    if tag.key == "highway"
-                        && (tag.value == "path" || tag.value == "footway" || tag.value == "track")
+                    if tag.as_key() == "highway"
+                        && (tag.as_value() == "path"
+                            || tag.as_value() == "footway"
+                            || tag.as_value() == "track")
                     {
                         is_hiking_path = true;
                         color = 0x00AA4400; // Brown
-                        line_width = if tag.value == "track" { 2 } else { 1 };
+                        line_width = if tag.as_value() == "track" { 2 } else { 1 };
                     }
 
                     // Check for waterways
-                    if tag.key == "waterway" && (tag.value == "river" || tag.value == "stream") {
+                    if tag.as_key() == "waterway"
+                        && (tag.as_value() == "river" || tag.as_value() == "stream")
+                    {
                         color = 0x0033AAFF; // Blue
-                        line_width = if tag.value == "river" { 3 } else { 2 };
+                        line_width = if tag.as_value() == "river" { 3 } else { 2 };
                     }
 
                     // Get standard way style
@@ -557,24 +423,15 @@ impl MapRenderer {
 
                     // Draw each segment
                     for i in 0..segment.len() - 1 {
-                        let (x0, y0) = to_screen(segment[i].latitude, segment[i].longitude);
-                        let (x1, y1) = to_screen(segment[i + 1].latitude, segment[i + 1].longitude);
+                        let from = to_screen(segment[i].latitude, segment[i].longitude);
+                        let to = to_screen(segment[i + 1].latitude, segment[i + 1].longitude);
 
                         // For hiking paths, use dashed pattern
                         if is_hiking_path {
                             // Draw dashed line code here
                         } else {
                             // Regular line for other ways
-                            draw_thick_line(
-                                x0,
-                                y0,
-                                x1,
-                                y1,
-                                color,
-                                line_width,
-                                &mut self.buffer,
-                                width,
-                            );
+                            draw_thick_line(from, to, color, line_width, &mut self.buffer, width);
                         }
                     }
                 }
@@ -590,14 +447,14 @@ impl MapRenderer {
 
                 // Determine POI style based on tags
                 for tag in &poi.tags {
-                    if tag.key == "name" {
-                        poi_name = tag.value.clone();
+                    if tag.as_key() == "name" {
+                        poi_name = tag.value.to_string();
                     }
 
                     // Set color based on POI type
-                    match tag.key.as_str() {
+                    match tag.as_key() {
                         "amenity" => {
-                            match tag.value.as_str() {
+                            match tag.as_value() {
                                 "restaurant" | "cafe" | "fast_food" => poi_color = 0x00FF8000, // Orange
                                 "bank" | "atm" => poi_color = 0x0000AAFF, // Blue
                                 "hospital" | "pharmacy" | "doctors" => poi_color = 0x00FF0000, // Red
@@ -606,7 +463,7 @@ impl MapRenderer {
                             }
                         }
                         "natural" => {
-                            match tag.value.as_str() {
+                            match tag.as_value() {
                                 "peak" => {
                                     poi_color = 0x00663300; // Brown for mountain peaks
                                     poi_radius = 4; // Make peaks more visible
@@ -621,7 +478,7 @@ impl MapRenderer {
                         }
                         "shop" => poi_color = 0x0000CC00, // Green
                         "tourism" => {
-                            match tag.value.as_str() {
+                            match tag.as_value() {
                                 "viewpoint" => {
                                     poi_color = 0x00FF3300; // Red for viewpoints
                                     poi_radius = 4;
@@ -634,7 +491,7 @@ impl MapRenderer {
                             }
                         }
                         "amenity" => {
-                            match tag.value.as_str() {
+                            match tag.as_value() {
                                 "shelter" => {
                                     poi_color = 0x00AA6600; // Dark orange for shelters
                                     poi_radius = 4;
@@ -643,7 +500,7 @@ impl MapRenderer {
                             }
                         }
                         "historic" => {
-                            match tag.value.as_str() {
+                            match tag.as_value() {
                                 "memorial" | "monument" => {
                                     poi_color = 0x00AA00AA; // Purple for memorials
                                     poi_radius = 4;
@@ -652,7 +509,7 @@ impl MapRenderer {
                             }
                         }
                         "emergency" => {
-                            match tag.value.as_str() {
+                            match tag.as_value() {
                                 "phone" => {
                                     poi_color = 0x00FF00FF; // Magenta for emergency phones
                                     poi_radius = 3;
@@ -661,7 +518,7 @@ impl MapRenderer {
                             }
                         }
                         "leisure" => {
-                            match tag.value.as_str() {
+                            match tag.as_value() {
                                 "park" => {
                                     poi_color = 0x0000AA00; // Dark green for parks
                                     poi_radius = 4;
@@ -670,7 +527,7 @@ impl MapRenderer {
                             }
                         }
                         "craft" => {
-                            match tag.value.as_str() {
+                            match tag.as_value() {
                                 "brewery" | "distillery" => {
                                     poi_color = 0x00FFAA00; // Yellow for breweries
                                     poi_radius = 4;
@@ -679,7 +536,7 @@ impl MapRenderer {
                             }
                         }
                         "office" => {
-                            match tag.value.as_str() {
+                            match tag.as_value() {
                                 "government" => {
                                     poi_color = 0x00FF00FF; // Magenta for government offices
                                     poi_radius = 4;
@@ -688,7 +545,7 @@ impl MapRenderer {
                             }
                         }
                         "power" => {
-                            match tag.value.as_str() {
+                            match tag.as_value() {
                                 "station" => {
                                     poi_color = 0x00FF00FF; // Magenta for power stations
                                     poi_radius = 4;
@@ -697,7 +554,7 @@ impl MapRenderer {
                             }
                         }
                         "public_transport" => {
-                            match tag.value.as_str() {
+                            match tag.as_value() {
                                 "station" => {
                                     poi_color = 0x0000FFFF; // Cyan for public transport stations
                                     poi_radius = 4;
@@ -706,7 +563,7 @@ impl MapRenderer {
                             }
                         }
 
-                        "railway" | "highway" if tag.value == "bus_station" => {
+                        "railway" | "highway" if tag.as_value() == "bus_station" => {
                             poi_color = 0x0000FFFF
                         } // Cyan
                         _ => {}
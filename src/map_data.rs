@@ -1,8 +1,13 @@
-use crate::types::{LatLong, Tag};
+use crate::mercator::MercatorProjection;
+use crate::tile::Tile;
+use crate::types::{BoundingBox, LatLong, LatLongE6, LatLongUtils, Layer, Tag};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 
 #[derive(Debug, Clone)]
 pub struct PointOfInterest {
-    pub layer: i8,
+    pub layer: Layer,
     pub tags: Vec<Tag>,
     pub position: LatLong,
 }
@@ -10,19 +15,67 @@ pub struct PointOfInterest {
 impl PointOfInterest {
     pub fn new(layer: i8, tags: Vec<Tag>, position: LatLong) -> Self {
         Self {
-            layer,
+            layer: Layer(layer),
             tags,
             position,
         }
     }
+
+    /// Elevation in meters from the `ele` tag, which may be negative for
+    /// points below sea level.
+    pub fn elevation(&self) -> Option<i32> {
+        self.tags
+            .iter()
+            .find(|tag| tag.as_key() == "ele")
+            .and_then(|tag| tag.as_value().parse().ok())
+    }
+}
+
+/// Selects POIs by a single tag, either by key alone (`place=*`) or by key
+/// and value.
+#[derive(Debug, Clone)]
+pub struct PoiFilter {
+    key: String,
+    value: Option<String>,
+}
+
+impl PoiFilter {
+    /// POIs carrying `key` with any value.
+    pub fn key(key: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            value: None,
+        }
+    }
+
+    /// POIs carrying exactly `key=value`.
+    pub fn tag(key: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            value: Some(value.into()),
+        }
+    }
+
+    pub fn matches(&self, poi: &PointOfInterest) -> bool {
+        poi.tags.iter().any(|tag| {
+            tag.as_key() == self.key
+                && self
+                    .value
+                    .as_deref()
+                    .is_none_or(|value| tag.as_value() == value)
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Way {
-    pub layer: i8,
+    pub layer: Layer,
     pub tags: Vec<Tag>,
     pub way_nodes: Vec<Vec<LatLong>>, // Equivalent to LatLong[][] in Java
     pub label_position: Option<LatLong>,
+    /// The envelope of all nodes, only filled when reading with
+    /// `include_bboxes`.
+    pub bbox: Option<BoundingBox>,
 }
 
 impl Way {
@@ -33,24 +86,759 @@ impl Way {
         label_position: Option<LatLong>,
     ) -> Self {
         Self {
-            layer,
+            layer: Layer(layer),
             tags,
             way_nodes,
             label_position,
+            bbox: None,
+        }
+    }
+
+    /// The envelope of all nodes, None for a way without nodes.
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        let nodes: Vec<LatLong> = self.way_nodes.iter().flatten().cloned().collect();
+        BoundingBox::from_lat_longs(&nodes).ok()
+    }
+
+    /// The node lists of the way, the outer one first.
+    pub fn segments(&self) -> &[Vec<LatLong>] {
+        &self.way_nodes
+    }
+
+    /// The first segment, which is the outer ring of a multipolygon.
+    pub fn outer_segment(&self) -> Option<&[LatLong]> {
+        self.way_nodes.first().map(Vec::as_slice)
+    }
+
+    /// The segment with the most nodes, the first of them on a tie. Useful
+    /// for placing a label.
+    pub fn longest_segment(&self) -> Option<&[LatLong]> {
+        self.way_nodes
+            .iter()
+            .rev()
+            .max_by_key(|segment| segment.len())
+            .map(Vec::as_slice)
+    }
+
+    // Recomputes a filled `bbox` after the nodes changed
+    fn refresh_bbox(&mut self) {
+        if self.bbox.is_some() {
+            self.bbox = self.bounding_box();
+        }
+    }
+
+    /// Whether the way describes an area rather than a line. An explicit
+    /// `area=yes`/`area=no` tag wins, otherwise closed rings are areas unless
+    /// they are tagged as highways or barriers.
+    pub fn is_area(&self) -> bool {
+        let mut linear = false;
+        for tag in &self.tags {
+            match (tag.as_key(), tag.as_value()) {
+                ("area", "yes") => return true,
+                ("area", "no") => return false,
+                ("highway", _) | ("barrier", _) => linear = true,
+                _ => {}
+            }
+        }
+
+        !linear && self.is_closed()
+    }
+
+    /// Whether `point` lies inside the polygon formed by the way's segments,
+    /// using the even-odd rule so that inner segments act as holes.
+    pub fn contains_point(&self, point: &LatLong) -> bool {
+        let mut inside = false;
+        for segment in &self.way_nodes {
+            for edge in segment.windows(2) {
+                let (a, b) = (&edge[0], &edge[1]);
+                if (a.latitude > point.latitude) != (b.latitude > point.latitude) {
+                    let crossing = a.longitude
+                        + (point.latitude - a.latitude) * (b.longitude - a.longitude)
+                            / (b.latitude - a.latitude);
+                    if point.longitude < crossing {
+                        inside = !inside;
+                    }
+                }
+            }
+        }
+        inside
+    }
+
+    /// Area of the outer ring in square degrees, zero for open ways.
+    pub fn area(&self) -> f64 {
+        if !self.is_closed() {
+            return 0.0;
+        }
+        ring_area(&self.way_nodes[0]).abs()
+    }
+
+    /// The segments with every node in integer microdegrees.
+    pub fn nodes_e6(&self) -> Vec<Vec<LatLongE6>> {
+        self.way_nodes
+            .iter()
+            .map(|segment| segment.iter().map(LatLongE6::from).collect())
+            .collect()
+    }
+
+    /// Splits the segments into outer and inner rings by winding order:
+    /// counter-clockwise segments are outer rings, clockwise ones are holes.
+    pub fn rings(&self) -> (Vec<&[LatLong]>, Vec<&[LatLong]>) {
+        self.way_nodes
+            .iter()
+            .map(Vec::as_slice)
+            .partition(|segment| ring_area(segment) >= 0.0)
+    }
+
+    /// Classifies the way by its first matching key, in the order highway,
+    /// waterway, building, natural, landuse.
+    pub fn classify(&self) -> WayClass {
+        let value = |key: &str| {
+            self.tags
+                .iter()
+                .find(|tag| tag.as_key() == key)
+                .map(|tag| tag.as_value())
+        };
+
+        if let Some(highway) = value("highway") {
+            WayClass::Highway(HighwayKind::from_value(highway))
+        } else if let Some(waterway) = value("waterway") {
+            WayClass::Waterway(WaterwayKind::from_value(waterway))
+        } else if value("building").is_some_and(|building| building != "no") {
+            WayClass::Building
+        } else if let Some(natural) = value("natural") {
+            WayClass::Natural(NaturalKind::from_value(natural))
+        } else if let Some(landuse) = value("landuse") {
+            WayClass::Landuse(LanduseKind::from_value(landuse))
+        } else {
+            WayClass::Other
+        }
+    }
+
+    /// A copy of the way with every segment densified, see `densify`.
+    pub fn densified(&self, max_segment_meters: f64) -> Way {
+        let mut way = Way {
+            way_nodes: self
+                .way_nodes
+                .iter()
+                .map(|segment| densify(segment, max_segment_meters))
+                .collect(),
+            ..self.clone()
+        };
+        way.refresh_bbox();
+        way
+    }
+
+    /// Whether the first segment starts and ends on the same node. Nodes are
+    /// compared at the microdegree precision of the map file, as decoding
+    /// deltas can leave float noise between identical nodes.
+    pub fn is_closed(&self) -> bool {
+        match self.way_nodes.first() {
+            Some(segment) if segment.len() > 2 => {
+                same_node(&segment[0], &segment[segment.len() - 1])
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The tiles at `zoom` covered by the bounding box of the way, row by row
+/// from the top-left. Empty for a way without nodes.
+pub fn way_tiles(way: &Way, zoom: u8, tile_size: i32) -> Vec<Tile> {
+    let Some(bounding_box) = way.bbox.clone().or_else(|| way.bounding_box()) else {
+        return Vec::new();
+    };
+
+    let left = MercatorProjection::longitude_to_tile_x(bounding_box.min_longitude, zoom);
+    let right = MercatorProjection::longitude_to_tile_x(bounding_box.max_longitude, zoom);
+    let top = MercatorProjection::latitude_to_tile_y(bounding_box.max_latitude, zoom);
+    let bottom = MercatorProjection::latitude_to_tile_y(bounding_box.min_latitude, zoom);
+
+    (top..=bottom)
+        .flat_map(|tile_y| {
+            (left..=right).map(move |tile_x| Tile::new(tile_x, tile_y, zoom, tile_size))
+        })
+        .collect()
+}
+
+/// Clips the way to `bbox`. Closed ways are clipped as polygons
+/// (Sutherland–Hodgman), open ways as polylines (Liang–Barsky), where a
+/// line leaving and re-entering the box yields one segment per visit. None
+/// if nothing of the way lies inside.
+pub fn clip_way_to_bbox(way: &Way, bbox: &BoundingBox) -> Option<Way> {
+    let way_nodes: Vec<Vec<LatLong>> = if way.is_closed() {
+        way.way_nodes
+            .iter()
+            .filter_map(|ring| clip_ring(ring, bbox))
+            .collect()
+    } else {
+        way.way_nodes
+            .iter()
+            .flat_map(|line| clip_line(line, bbox))
+            .collect()
+    };
+
+    if way_nodes.is_empty() {
+        return None;
+    }
+    let mut clipped = Way {
+        way_nodes,
+        ..way.clone()
+    };
+    clipped.refresh_bbox();
+    Some(clipped)
+}
+
+// Liang–Barsky: the parameter range of the edge inside the box, if any
+fn clip_edge(a: &LatLong, b: &LatLong, bbox: &BoundingBox) -> Option<(f64, f64)> {
+    let d_lon = b.longitude - a.longitude;
+    let d_lat = b.latitude - a.latitude;
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    for (p, q) in [
+        (-d_lon, a.longitude - bbox.min_longitude),
+        (d_lon, bbox.max_longitude - a.longitude),
+        (-d_lat, a.latitude - bbox.min_latitude),
+        (d_lat, bbox.max_latitude - a.latitude),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            t0 = t0.max(q / p);
+        } else {
+            t1 = t1.min(q / p);
+        }
+    }
+    (t0 <= t1).then_some((t0, t1))
+}
+
+fn interpolate(a: &LatLong, b: &LatLong, t: f64) -> LatLong {
+    LatLong::new(
+        a.latitude + (b.latitude - a.latitude) * t,
+        a.longitude + (b.longitude - a.longitude) * t,
+    )
+}
+
+fn clip_line(line: &[LatLong], bbox: &BoundingBox) -> Vec<Vec<LatLong>> {
+    let mut pieces = Vec::new();
+    let mut piece: Vec<LatLong> = Vec::new();
+    for edge in line.windows(2) {
+        let Some((t0, t1)) = clip_edge(&edge[0], &edge[1], bbox) else {
+            continue;
+        };
+        // Entering the box mid-edge starts a new piece
+        if t0 > 0.0 && piece.len() > 1 {
+            pieces.push(std::mem::take(&mut piece));
+        }
+        if piece.is_empty() {
+            piece.push(interpolate(&edge[0], &edge[1], t0));
+        }
+        piece.push(interpolate(&edge[0], &edge[1], t1));
+        // Leaving the box mid-edge ends it
+        if t1 < 1.0 {
+            pieces.push(std::mem::take(&mut piece));
+        }
+    }
+    if piece.len() > 1 {
+        pieces.push(piece);
+    }
+    pieces
+}
+
+fn clip_ring(ring: &[LatLong], bbox: &BoundingBox) -> Option<Vec<LatLong>> {
+    // Work on the open ring and close it again at the end
+    let mut polygon = ring.to_vec();
+    if polygon.len() > 1 && same_node(&polygon[0], &polygon[polygon.len() - 1]) {
+        polygon.pop();
+    }
+    let edges: [fn(&LatLong, &BoundingBox) -> f64; 4] = [
+        |node, bbox| node.longitude - bbox.min_longitude,
+        |node, bbox| bbox.max_longitude - node.longitude,
+        |node, bbox| node.latitude - bbox.min_latitude,
+        |node, bbox| bbox.max_latitude - node.latitude,
+    ];
+    for distance in edges {
+        let input = std::mem::take(&mut polygon);
+        for (index, current) in input.iter().enumerate() {
+            let previous = &input[(index + input.len() - 1) % input.len()];
+            let (d_previous, d_current) = (distance(previous, bbox), distance(current, bbox));
+            if (d_previous >= 0.0) != (d_current >= 0.0) {
+                let t = d_previous / (d_previous - d_current);
+                polygon.push(interpolate(previous, current, t));
+            }
+            if d_current >= 0.0 {
+                polygon.push(current.clone());
+            }
+        }
+    }
+
+    if polygon.len() < 3 {
+        return None;
+    }
+    polygon.push(polygon[0].clone());
+    Some(polygon)
+}
+
+/// Coarse rendering class of a way, derived from its standard OSM keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WayClass {
+    Highway(HighwayKind),
+    Waterway(WaterwayKind),
+    Landuse(LanduseKind),
+    Natural(NaturalKind),
+    Building,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HighwayKind {
+    Motorway,
+    Trunk,
+    Primary,
+    Secondary,
+    Tertiary,
+    Residential,
+    Service,
+    Track,
+    Path,
+    Footway,
+    Cycleway,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaterwayKind {
+    River,
+    Stream,
+    Canal,
+    Ditch,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LanduseKind {
+    Forest,
+    Residential,
+    Industrial,
+    Commercial,
+    Retail,
+    Farmland,
+    Farmyard,
+    Orchard,
+    Meadow,
+    Grass,
+    Quarry,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NaturalKind {
+    Water,
+    Sea,
+    Nosea,
+    Wood,
+    Other,
+}
+
+impl HighwayKind {
+    fn from_value(value: &str) -> Self {
+        match value {
+            "motorway" | "motorway_link" => HighwayKind::Motorway,
+            "trunk" | "trunk_link" => HighwayKind::Trunk,
+            "primary" | "primary_link" => HighwayKind::Primary,
+            "secondary" | "secondary_link" => HighwayKind::Secondary,
+            "tertiary" | "tertiary_link" => HighwayKind::Tertiary,
+            "residential" | "living_street" | "unclassified" => HighwayKind::Residential,
+            "service" => HighwayKind::Service,
+            "track" => HighwayKind::Track,
+            "path" => HighwayKind::Path,
+            "footway" | "pedestrian" | "steps" => HighwayKind::Footway,
+            "cycleway" => HighwayKind::Cycleway,
+            _ => HighwayKind::Other,
+        }
+    }
+}
+
+impl WaterwayKind {
+    fn from_value(value: &str) -> Self {
+        match value {
+            "river" => WaterwayKind::River,
+            "stream" => WaterwayKind::Stream,
+            "canal" => WaterwayKind::Canal,
+            "ditch" | "drain" => WaterwayKind::Ditch,
+            _ => WaterwayKind::Other,
+        }
+    }
+}
+
+impl LanduseKind {
+    fn from_value(value: &str) -> Self {
+        match value {
+            "forest" => LanduseKind::Forest,
+            "residential" => LanduseKind::Residential,
+            "industrial" => LanduseKind::Industrial,
+            "commercial" => LanduseKind::Commercial,
+            "retail" => LanduseKind::Retail,
+            "farmland" => LanduseKind::Farmland,
+            "farmyard" => LanduseKind::Farmyard,
+            "orchard" => LanduseKind::Orchard,
+            "meadow" => LanduseKind::Meadow,
+            "grass" => LanduseKind::Grass,
+            "quarry" => LanduseKind::Quarry,
+            _ => LanduseKind::Other,
+        }
+    }
+}
+
+impl NaturalKind {
+    fn from_value(value: &str) -> Self {
+        match value {
+            "water" => NaturalKind::Water,
+            "sea" => NaturalKind::Sea,
+            "nosea" => NaturalKind::Nosea,
+            "wood" => NaturalKind::Wood,
+            _ => NaturalKind::Other,
+        }
+    }
+}
+
+/// Inserts points along the great circle between consecutive nodes so that
+/// no segment is longer than `max_segment_meters`. Original nodes are kept.
+pub fn densify(nodes: &[LatLong], max_segment_meters: f64) -> Vec<LatLong> {
+    if max_segment_meters.is_nan() || max_segment_meters <= 0.0 || nodes.len() < 2 {
+        return nodes.to_vec();
+    }
+
+    let mut densified = Vec::with_capacity(nodes.len());
+    densified.push(nodes[0].clone());
+    for edge in nodes.windows(2) {
+        let distance = edge[0].distance_to(&edge[1]);
+        let parts = (distance / max_segment_meters).ceil().max(1.0) as usize;
+        for part in 1..parts {
+            densified.push(great_circle_point(
+                &edge[0],
+                &edge[1],
+                part as f64 / parts as f64,
+            ));
+        }
+        densified.push(edge[1].clone());
+    }
+    densified
+}
+
+// Point at `fraction` of the way from `a` to `b` along the great circle
+fn great_circle_point(a: &LatLong, b: &LatLong, fraction: f64) -> LatLong {
+    let to_vector = |node: &LatLong| {
+        let (lat, lon) = (node.latitude.to_radians(), node.longitude.to_radians());
+        [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+    };
+    let (u, v) = (to_vector(a), to_vector(b));
+    let angle = (u[0] * v[0] + u[1] * v[1] + u[2] * v[2])
+        .clamp(-1.0, 1.0)
+        .acos();
+    if angle < f64::EPSILON {
+        return a.clone();
+    }
+
+    let wa = ((1.0 - fraction) * angle).sin() / angle.sin();
+    let wb = (fraction * angle).sin() / angle.sin();
+    let p = [
+        wa * u[0] + wb * v[0],
+        wa * u[1] + wb * v[1],
+        wa * u[2] + wb * v[2],
+    ];
+    LatLong::new(
+        p[2].atan2((p[0] * p[0] + p[1] * p[1]).sqrt()).to_degrees(),
+        p[1].atan2(p[0]).to_degrees(),
+    )
+}
+
+// Nodes are equal at the microdegree precision of the map file
+fn same_node(a: &LatLong, b: &LatLong) -> bool {
+    LatLongUtils::degrees_to_microdegrees(a.latitude)
+        == LatLongUtils::degrees_to_microdegrees(b.latitude)
+        && LatLongUtils::degrees_to_microdegrees(a.longitude)
+            == LatLongUtils::degrees_to_microdegrees(b.longitude)
+}
+
+fn same_tags(a: &[Tag], b: &[Tag]) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .zip(b)
+            .all(|(a, b)| a.as_key() == b.as_key() && a.as_value() == b.as_value())
+}
+
+// Whether `next` continues `way` without closing it into a ring
+fn continues(way: &Way, next: &Way) -> bool {
+    let (Some(line), Some(next_line)) = (way.way_nodes.first(), next.way_nodes.first()) else {
+        return false;
+    };
+    way.way_nodes.len() == 1
+        && next.way_nodes.len() == 1
+        && line.len() > 1
+        && next_line.len() > 1
+        && way.layer == next.layer
+        && same_tags(&way.tags, &next.tags)
+        && same_node(&line[line.len() - 1], &next_line[0])
+        && !same_node(&line[0], &next_line[next_line.len() - 1])
+        && !way.is_closed()
+        && !next.is_closed()
+}
+
+/// A fallback label point for ways without an explicit label position.
+/// Closed ways use the area-weighted centroid of their outer ring, open ways
+/// the midpoint of their longest edge.
+pub fn centroid(way: &Way) -> Option<LatLong> {
+    if way.is_closed() {
+        if let Some(center) = polygon_centroid(&way.way_nodes[0]) {
+            return Some(center);
         }
     }
+
+    way.way_nodes
+        .iter()
+        .flat_map(|segment| segment.windows(2))
+        .map(|edge| {
+            let d_lat = edge[1].latitude - edge[0].latitude;
+            let d_lon = edge[1].longitude - edge[0].longitude;
+            (d_lat * d_lat + d_lon * d_lon, edge)
+        })
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, edge)| {
+            LatLong::new(
+                (edge[0].latitude + edge[1].latitude) / 2.0,
+                (edge[0].longitude + edge[1].longitude) / 2.0,
+            )
+        })
+        .or_else(|| way.way_nodes.iter().flatten().next().cloned())
+}
+
+fn cross(a: &LatLong, b: &LatLong) -> f64 {
+    a.longitude * b.latitude - b.longitude * a.latitude
+}
+
+// Signed shoelace area, positive for counter-clockwise rings
+fn ring_area(ring: &[LatLong]) -> f64 {
+    ring.windows(2)
+        .map(|edge| cross(&edge[0], &edge[1]))
+        .sum::<f64>()
+        / 2.0
+}
+
+// None for degenerate rings without area
+fn polygon_centroid(ring: &[LatLong]) -> Option<LatLong> {
+    let area = ring_area(ring);
+    if area.abs() < f64::EPSILON {
+        return None;
+    }
+
+    let mut latitude = 0.0;
+    let mut longitude = 0.0;
+    for edge in ring.windows(2) {
+        let cross = cross(&edge[0], &edge[1]);
+        longitude += (edge[0].longitude + edge[1].longitude) * cross;
+        latitude += (edge[0].latitude + edge[1].latitude) * cross;
+    }
+    Some(LatLong::new(
+        latitude / (6.0 * area),
+        longitude / (6.0 * area),
+    ))
+}
+
+/// POIs that fall into the same screen-grid cell.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoiCluster {
+    /// Mean position of the clustered POIs.
+    pub position: LatLong,
+    pub count: usize,
+}
+
+/// Buckets POIs into square cells of `cell_px` pixels at `zoom`, returning
+/// one cluster per non-empty cell in the order the cells were first hit.
+pub fn cluster_pois(pois: &[PointOfInterest], zoom: u8, cell_px: i32) -> Vec<PoiCluster> {
+    let cell_size = cell_px.max(1) as f64;
+    let mut cells: HashMap<(i64, i64), usize> = HashMap::new();
+    let mut sums: Vec<(f64, f64, usize)> = Vec::new();
+
+    for poi in pois {
+        let cell = (
+            (MercatorProjection::longitude_to_pixel_x(poi.position.longitude, zoom) / cell_size)
+                .floor() as i64,
+            (MercatorProjection::latitude_to_pixel_y(poi.position.latitude, zoom) / cell_size)
+                .floor() as i64,
+        );
+        let index = *cells.entry(cell).or_insert_with(|| {
+            sums.push((0.0, 0.0, 0));
+            sums.len() - 1
+        });
+        let sum = &mut sums[index];
+        sum.0 += poi.position.latitude;
+        sum.1 += poi.position.longitude;
+        sum.2 += 1;
+    }
+
+    sums.into_iter()
+        .map(|(latitude, longitude, count)| PoiCluster {
+            position: LatLong::new(latitude / count as f64, longitude / count as f64),
+            count,
+        })
+        .collect()
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct PoiWayBundle {
     pub pois: Vec<PointOfInterest>,
     pub ways: Vec<Way>,
+    /// Column and row within its sub-file of the block the bundle was read
+    /// from, if any.
+    pub(crate) block: Option<(i64, i64)>,
 }
 
 impl PoiWayBundle {
     pub fn new(pois: Vec<PointOfInterest>, ways: Vec<Way>) -> Self {
-        Self { pois, ways }
+        Self {
+            pois,
+            ways,
+            block: None,
+        }
+    }
+}
+
+/// A way as stored in a block, with the header fields the reader normally
+/// consumes. Holds one `Way` per way data block.
+#[cfg(feature = "debug")]
+#[derive(Debug, Clone)]
+pub struct RawWay {
+    pub tile_bitmask: u16,
+    pub way_data_size: u32,
+    pub ways: Vec<Way>,
+}
+
+/// Everything stored in one block, decoded without zoom, bitmask or
+/// bounding box filtering.
+#[cfg(feature = "debug")]
+#[derive(Debug, Default, Clone)]
+pub struct RawBlock {
+    pub zoom_table: Vec<[i32; 2]>,
+    pub pois: Vec<PointOfInterest>,
+    pub ways: Vec<RawWay>,
+}
+
+/// One row of a block's zoom table: the number of POIs and ways to read at
+/// `zoom`, including those of all lower zoom levels.
+#[cfg(feature = "debug")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZoomRow {
+    pub zoom: u8,
+    pub cumulative_pois: u32,
+    pub cumulative_ways: u32,
+}
+
+/// A feature borrowed from a `MapReadResult`, classified for rendering.
+#[derive(Debug, Clone, Copy)]
+pub enum DrawItem<'a> {
+    Area(&'a Way),
+    Line(&'a Way),
+    Poi(&'a PointOfInterest),
+}
+
+impl DrawItem<'_> {
+    pub fn layer(&self) -> Layer {
+        match self {
+            DrawItem::Area(way) | DrawItem::Line(way) => way.layer,
+            DrawItem::Poi(poi) => poi.layer,
+        }
+    }
+
+    // Within a layer, areas are drawn first, then lines, then POIs
+    fn type_rank(&self) -> u8 {
+        match self {
+            DrawItem::Area(_) => 0,
+            DrawItem::Line(_) => 1,
+            DrawItem::Poi(_) => 2,
+        }
+    }
+}
+
+/// A feature name together with the point its label is drawn at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedItem {
+    pub name: String,
+    pub anchor: LatLong,
+    pub layer: Layer,
+}
+
+fn name_of(tags: &[Tag]) -> Option<&str> {
+    tags.iter()
+        .find(|tag| tag.as_key() == "name")
+        .map(|tag| tag.as_value())
+        .filter(|name| !name.is_empty())
+}
+
+/// The shape of a `Feature`.
+#[derive(Debug, Clone)]
+pub enum Geometry {
+    Point(LatLong),
+    Line(Vec<LatLong>),
+    Polygon {
+        outer: Vec<LatLong>,
+        inners: Vec<Vec<LatLong>>,
+    },
+}
+
+/// A POI or way reduced to its geometry, tags and layer.
+#[derive(Debug, Clone)]
+pub struct Feature {
+    pub geometry: Geometry,
+    pub tags: Vec<Tag>,
+    pub layer: Layer,
+}
+
+// One polygon for an area, one line per segment otherwise
+fn push_way_features(way: Way, features: &mut Vec<Feature>) {
+    if way.way_nodes.is_empty() {
+        return;
     }
+    if !way.is_area() {
+        for segment in way.way_nodes {
+            features.push(Feature {
+                geometry: Geometry::Line(segment),
+                tags: way.tags.clone(),
+                layer: way.layer,
+            });
+        }
+        return;
+    }
+
+    let (outers, inners) = way.rings();
+    // Without exactly one counter-clockwise ring the winding order does not
+    // single out the outer ring, the first segment is then taken
+    let geometry = if outers.len() == 1 {
+        Geometry::Polygon {
+            outer: outers[0].to_vec(),
+            inners: inners.iter().map(|ring| ring.to_vec()).collect(),
+        }
+    } else {
+        Geometry::Polygon {
+            outer: way.way_nodes[0].clone(),
+            inners: way.way_nodes[1..].to_vec(),
+        }
+    };
+    features.push(Feature {
+        geometry,
+        tags: way.tags,
+        layer: way.layer,
+    });
+}
+
+/// Summary counts of a `MapReadResult`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MapStats {
+    pub poi_count: usize,
+    pub way_count: usize,
+    pub total_way_nodes: usize,
+    pub bundle_count: usize,
+    pub has_water: bool,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -67,4 +855,261 @@ impl MapReadResult {
     pub fn add(&mut self, bundle: PoiWayBundle) {
         self.poi_way_bundles.push(bundle);
     }
+
+    pub fn stats(&self) -> MapStats {
+        let mut stats = MapStats {
+            bundle_count: self.poi_way_bundles.len(),
+            has_water: self.is_water,
+            ..MapStats::default()
+        };
+
+        for bundle in &self.poi_way_bundles {
+            stats.poi_count += bundle.pois.len();
+            stats.way_count += bundle.ways.len();
+            stats.total_way_nodes += bundle
+                .ways
+                .iter()
+                .flat_map(|way| way.way_nodes.iter())
+                .map(|segment| segment.len())
+                .sum::<usize>();
+        }
+
+        stats
+    }
+
+    /// Returns all features in painter's order: sorted stably by layer, then
+    /// areas before lines before POIs.
+    pub fn drawing_order(&self) -> Vec<DrawItem<'_>> {
+        let mut items: Vec<DrawItem<'_>> = self
+            .ways()
+            .map(|way| {
+                if way.is_area() {
+                    DrawItem::Area(way)
+                } else {
+                    DrawItem::Line(way)
+                }
+            })
+            .chain(self.pois().map(DrawItem::Poi))
+            .collect();
+
+        items.sort_by_key(|item| (item.layer(), item.type_rank()));
+        items
+    }
+
+    /// Named features with their label anchors: the POI position, or for ways
+    /// the explicit label position, falling back to the way's centroid.
+    pub fn labels(&self) -> Vec<NamedItem> {
+        let pois = self.pois().filter_map(|poi| {
+            Some(NamedItem {
+                name: name_of(&poi.tags)?.to_string(),
+                anchor: poi.position.clone(),
+                layer: poi.layer,
+            })
+        });
+        let ways = self.ways().filter_map(|way| {
+            Some(NamedItem {
+                name: name_of(&way.tags)?.to_string(),
+                anchor: way.label_position.clone().or_else(|| centroid(way))?,
+                layer: way.layer,
+            })
+        });
+        pois.chain(ways).collect()
+    }
+
+    /// Iterates over the ways of all bundles.
+    pub fn ways(&self) -> impl Iterator<Item = &Way> {
+        self.poi_way_bundles
+            .iter()
+            .flat_map(|bundle| bundle.ways.iter())
+    }
+
+    /// Iterates over the POIs of all bundles.
+    pub fn pois(&self) -> impl Iterator<Item = &PointOfInterest> {
+        self.poi_way_bundles
+            .iter()
+            .flat_map(|bundle| bundle.pois.iter())
+    }
+
+    pub fn into_ways(self) -> impl Iterator<Item = Way> {
+        self.poi_way_bundles
+            .into_iter()
+            .flat_map(|bundle| bundle.ways.into_iter())
+    }
+
+    pub fn into_pois(self) -> impl Iterator<Item = PointOfInterest> {
+        self.poi_way_bundles
+            .into_iter()
+            .flat_map(|bundle| bundle.pois.into_iter())
+    }
+
+    /// All POIs as points, then all ways: areas as polygons split into outer
+    /// and inner rings with `Way::rings`, other ways as one line per segment.
+    pub fn into_features(self) -> Vec<Feature> {
+        let mut features = Vec::new();
+        let mut ways = Vec::new();
+        for bundle in self.poi_way_bundles {
+            features.extend(bundle.pois.into_iter().map(|poi| Feature {
+                geometry: Geometry::Point(poi.position),
+                tags: poi.tags,
+                layer: poi.layer,
+            }));
+            ways.extend(bundle.ways);
+        }
+        for way in ways {
+            push_way_features(way, &mut features);
+        }
+        features
+    }
+
+    /// Joins ways with the same layer and tags where one ends on the node the
+    /// next one starts with, as happens for ways split across base tiles. The
+    /// joined way stays in the bundle of its first part. Ways with several
+    /// segments are left alone, and no join closes a ring.
+    pub fn stitch_ways(&mut self) {
+        let mut ways: Vec<(usize, Way)> = self
+            .poi_way_bundles
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(index, bundle)| bundle.ways.drain(..).map(move |way| (index, way)))
+            .collect();
+
+        let mut i = 0;
+        while i < ways.len() {
+            let next = (0..ways.len()).find(|&j| j != i && continues(&ways[i].1, &ways[j].1));
+            match next {
+                Some(j) => {
+                    let (_, next) = ways.remove(j);
+                    if j < i {
+                        i -= 1;
+                    }
+                    let way = &mut ways[i].1;
+                    way.way_nodes[0].extend(next.way_nodes[0].iter().skip(1).cloned());
+                    way.refresh_bbox();
+                }
+                None => i += 1,
+            }
+        }
+
+        for (index, way) in ways {
+            self.poi_way_bundles[index].ways.push(way);
+        }
+    }
+
+    /// A hash of all geometry and tags for change detection. Bundles are
+    /// combined order-independently; features within a bundle are hashed in
+    /// order. Stable within a build of the crate, not across Rust versions.
+    pub fn content_hash(&self) -> u64 {
+        let bundles = self
+            .poi_way_bundles
+            .iter()
+            .map(|bundle| {
+                let mut hasher = DefaultHasher::new();
+                for poi in &bundle.pois {
+                    poi.layer.hash(&mut hasher);
+                    hash_tags(&poi.tags, &mut hasher);
+                    hash_lat_long(&poi.position, &mut hasher);
+                }
+                for way in &bundle.ways {
+                    way.layer.hash(&mut hasher);
+                    hash_tags(&way.tags, &mut hasher);
+                    for segment in &way.way_nodes {
+                        segment.len().hash(&mut hasher);
+                        segment
+                            .iter()
+                            .for_each(|node| hash_lat_long(node, &mut hasher));
+                    }
+                    if let Some(label_position) = &way.label_position {
+                        hash_lat_long(label_position, &mut hasher);
+                    }
+                }
+                hasher.finish()
+            })
+            .fold(0u64, u64::wrapping_add);
+
+        let mut hasher = DefaultHasher::new();
+        bundles.hash(&mut hasher);
+        self.is_water.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Appends the bundles of another result. The merged result is only
+    /// water if both parts are.
+    pub fn merge(&mut self, other: MapReadResult) {
+        self.is_water = self.is_water && other.is_water;
+        self.poi_way_bundles.extend(other.poi_way_bundles);
+    }
+}
+
+fn hash_tags(tags: &[Tag], hasher: &mut DefaultHasher) {
+    tags.len().hash(hasher);
+    for tag in tags {
+        tag.as_key().hash(hasher);
+        tag.as_value().hash(hasher);
+    }
+}
+
+fn hash_lat_long(lat_long: &LatLong, hasher: &mut DefaultHasher) {
+    lat_long.latitude.to_bits().hash(hasher);
+    lat_long.longitude.to_bits().hash(hasher);
+}
+
+/// A POI with its position in pixels relative to the top-left of a tile.
+#[derive(Debug, Clone)]
+pub struct ProjectedPoi {
+    pub layer: Layer,
+    pub tags: Vec<Tag>,
+    pub position: (f32, f32),
+}
+
+/// A way with its nodes in pixels relative to the top-left of a tile.
+#[derive(Debug, Clone)]
+pub struct ProjectedWay {
+    pub layer: Layer,
+    pub tags: Vec<Tag>,
+    pub way_nodes: Vec<Vec<(f32, f32)>>,
+    pub label_position: Option<(f32, f32)>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ProjectedMapResult {
+    pub pois: Vec<ProjectedPoi>,
+    pub ways: Vec<ProjectedWay>,
+    pub is_water: bool,
+}
+
+impl ProjectedMapResult {
+    /// Projects every coordinate of `result` into the pixel space of `tile`.
+    pub fn new(result: &MapReadResult, tile: &Tile) -> Self {
+        let project = |position: &LatLong| {
+            (
+                tile.longitude_to_pixel_x(position.longitude) as f32,
+                tile.latitude_to_pixel_y(position.latitude) as f32,
+            )
+        };
+
+        Self {
+            pois: result
+                .pois()
+                .map(|poi| ProjectedPoi {
+                    layer: poi.layer,
+                    tags: poi.tags.clone(),
+                    position: project(&poi.position),
+                })
+                .collect(),
+            ways: result
+                .ways()
+                .map(|way| ProjectedWay {
+                    layer: way.layer,
+                    tags: way.tags.clone(),
+                    way_nodes: way
+                        .way_nodes
+                        .iter()
+                        .map(|segment| segment.iter().map(project).collect())
+                        .collect(),
+                    label_position: way.label_position.as_ref().map(project),
+                })
+                .collect(),
+            is_water: result.is_water,
+        }
+    }
 }
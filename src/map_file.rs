@@ -1,30 +1,88 @@
-use tracing::{info, warn};
-
-use crate::errors::MapFileException;
-use crate::map_data::{MapReadResult, PoiWayBundle};
-
-use crate::header::{MapFileHeader, MapFileInfo};
-use crate::index_cache::IndexCache;
-use crate::map_data::{PointOfInterest, Way};
-use crate::mercator::MercatorProjection;
+use tracing::{debug, trace};
+
+use crate::compression;
+use crate::errors::{MapFileErrorKind, MapFileException, Result};
+use crate::map_data::{
+    clip_way_to_bbox, MapReadResult, NamedItem, PoiWayBundle, ProjectedMapResult,
+};
+
+use crate::header::{MapFileHeader, MapFileInfo, MapFileInfoSnapshot};
+use crate::index_cache::{IndexCache, IndexCacheStats};
+use crate::map_data::{PoiFilter, PointOfInterest, Way};
+#[cfg(feature = "debug")]
+use crate::map_data::{RawBlock, RawWay, ZoomRow};
+use crate::mercator::{MercatorProjection, EARTH_RADIUS};
+use crate::observer::{NoopObserver, ReadObserver};
 use crate::query_parameters::QueryParameters;
-use crate::reader::ReadBuffer;
+use crate::reader::{check_buffer_size, ReadBuffer, MAXIMUM_BUFFER_SIZE};
+use crate::source::{BlockSource, MapSource};
 use crate::tile::Tile;
-use crate::types::{BoundingBox, LatLong, LatLongUtils, Tag};
+use crate::types::{
+    BoundingBox, CoordinatePrecision, Interner, LabelPosition, LatLong, LatLongUtils, Tag,
+};
 use crate::SubFileParameter;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
-use std::io::{Read, Seek};
+use std::io::{Cursor, Read, Seek};
+use std::ops::RangeInclusive;
 use std::path::Path;
+use std::sync::Arc;
 
 pub const INDEX_CACHE_SIZE: usize = 64;
 pub const DEFAULT_START_ZOOM_LEVEL: u8 = 12;
+/// Which features a read returns.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Selector {
+    /// Every POI and way, including features without any tags.
     All,
+    /// Only the POIs.
     Pois,
+    /// Only the ways, every one of them. POIs are skipped undecoded.
+    Ways,
+    /// The POIs, plus the ways carrying a name, house number or ref. Ways
+    /// without tags are left out.
     Named,
 }
 
+/// The optional fields announced by a POI or way feature byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FeatureFlags {
+    pub has_name: bool,
+    pub has_house_number: bool,
+    pub has_ref: bool,
+    pub has_elevation: bool,
+    pub has_label_position: bool,
+    pub has_data_blocks_byte: bool,
+    pub double_delta: bool,
+}
+
+impl FeatureFlags {
+    /// Decodes a way feature byte.
+    pub fn from_byte(feature_byte: u8) -> Self {
+        FeatureFlags {
+            has_name: feature_byte & WAY_FEATURE_NAME != 0,
+            has_house_number: feature_byte & WAY_FEATURE_HOUSE_NUMBER != 0,
+            has_ref: feature_byte & WAY_FEATURE_REF != 0,
+            has_elevation: false,
+            has_label_position: feature_byte & WAY_FEATURE_LABEL_POSITION != 0,
+            has_data_blocks_byte: feature_byte & WAY_FEATURE_DATA_BLOCKS_BYTE != 0,
+            double_delta: feature_byte & WAY_FEATURE_DOUBLE_DELTA_ENCODING != 0,
+        }
+    }
+
+    /// Decodes a POI feature byte, where the bit a way uses for its ref
+    /// flags an elevation instead.
+    pub fn from_poi_byte(feature_byte: u8) -> Self {
+        FeatureFlags {
+            has_name: feature_byte & POI_FEATURE_NAME != 0,
+            has_house_number: feature_byte & POI_FEATURE_HOUSE_NUMBER != 0,
+            has_elevation: feature_byte & POI_FEATURE_ELEVATION != 0,
+            ..Default::default()
+        }
+    }
+}
+
 // POI constants
 const POI_FEATURE_ELEVATION: u8 = 0x20;
 const POI_FEATURE_HOUSE_NUMBER: u8 = 0x40;
@@ -61,43 +119,339 @@ const BITMASK_INDEX_WATER: i64 = 0x8000000000;
 
 const INVALID_FIRST_WAY_OFFSET: &str = "invalid first way offset: ";
 
+// A whole block read into memory
+type BlockBuffer = ReadBuffer<Cursor<Vec<u8>>>;
+
+// What a query reads, shared by all of its blocks
+struct QueryContext {
+    query_parameters: QueryParameters,
+    sub_file_parameter: SubFileParameter,
+    bounding_box: BoundingBox,
+    selector: Selector,
+    // Drops non-matching POIs as each block is decoded
+    poi_filter: Option<PoiFilter>,
+    // Strings read for the query share storage across all blocks
+    interner: Interner,
+}
+
+impl QueryContext {
+    fn new(
+        query_parameters: QueryParameters,
+        sub_file_parameter: SubFileParameter,
+        bounding_box: BoundingBox,
+        selector: Selector,
+    ) -> Self {
+        Self {
+            query_parameters,
+            sub_file_parameter,
+            bounding_box,
+            selector,
+            poi_filter: None,
+            interner: Interner::new(),
+        }
+    }
+}
+
+// Layers -5..10 are stored as 0..15
+const LAYER_BIAS: i8 = 5;
+
+// Longitudes overshooting the date line by less than this are clamped to it
+const DATE_LINE_EPSILON: f64 = 0.001;
+
 // Global settings with unsafe access
 static mut WAY_FILTER_ENABLED: bool = true;
 static mut WAY_FILTER_DISTANCE: i32 = 20;
+/// How a `MapFile` decodes its blocks. Held by both `MapFileBuilder` and
+/// `MapFile`, so options can be set when opening or changed later through
+/// `MapFile::options_mut`.
+#[derive(Debug, Clone)]
+pub struct ReadOptions {
+    /// Largest block in bytes that will be read, 10 MB by default.
+    pub maximum_buffer_size: usize,
+    pub coordinate_precision: CoordinatePrecision,
+    /// Thin way nodes to about one per pixel at the query zoom, off by default.
+    pub node_thinning: bool,
+    /// Skip only the ways of a block with a corrupt first way offset, keeping
+    /// its POIs, instead of dropping the whole block. Off by default.
+    pub lenient_way_offset: bool,
+    /// Fill `Way::bbox` while decoding, off by default.
+    pub include_bboxes: bool,
+    /// Return all POIs and ways of the blocks a query reads, without dropping
+    /// those outside the tile above the base zoom level. Off by default.
+    pub keep_all_geometry: bool,
+    /// Only return POIs and ways whose layer lies in the range. Ways on other
+    /// layers are skipped without decoding their geometry.
+    pub layer_filter: Option<RangeInclusive<i8>>,
+    /// Sort each feature's tags by key instead of keeping the stream order.
+    /// Off by default.
+    pub sort_tags: bool,
+    /// Fail queries above the map's maximum zoom level with a `NoCoverage`
+    /// error instead of reading the data of the maximum zoom level. Off by
+    /// default.
+    pub strict_zoom: bool,
+    /// Lowercase tag keys and trim whitespace around tag values, keeping the
+    /// case of values. Off by default.
+    pub normalize_tags: bool,
+    /// Clip the geometry of each way to the queried tiles, dropping the
+    /// nodes outside. Off by default.
+    pub clip_to_tile: bool,
+    /// Drop consecutive duplicate nodes within each way segment. Off by
+    /// default, keeping the nodes as stored.
+    pub dedup_nodes: bool,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self {
+            maximum_buffer_size: MAXIMUM_BUFFER_SIZE,
+            coordinate_precision: CoordinatePrecision::default(),
+            node_thinning: false,
+            lenient_way_offset: false,
+            include_bboxes: false,
+            keep_all_geometry: false,
+            layer_filter: None,
+            sort_tags: false,
+            strict_zoom: false,
+            normalize_tags: false,
+            clip_to_tile: false,
+            dedup_nodes: false,
+        }
+    }
+}
+
+/// Options for opening a `MapFile`.
+#[derive(Clone)]
+pub struct MapFileBuilder {
+    /// Decoding options handed on to the opened `MapFile`.
+    pub options: ReadOptions,
+    /// Number of index blocks kept in memory, 0 to read the index from the
+    /// file on every lookup.
+    pub index_cache_size: usize,
+    /// Open files whose header states a different file size, logging a
+    /// warning instead of failing. Off by default.
+    pub lenient_file_size: bool,
+    /// Receives the block events and recoverable warnings of the reads,
+    /// `NoopObserver` by default.
+    pub observer: Arc<dyn ReadObserver>,
+}
+
+impl std::fmt::Debug for MapFileBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MapFileBuilder")
+            .field("options", &self.options)
+            .field("index_cache_size", &self.index_cache_size)
+            .field("lenient_file_size", &self.lenient_file_size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for MapFileBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MapFileBuilder {
+    pub fn new() -> Self {
+        Self {
+            options: ReadOptions::default(),
+            index_cache_size: INDEX_CACHE_SIZE,
+            lenient_file_size: false,
+            observer: Arc::new(NoopObserver),
+        }
+    }
+
+    pub fn options(mut self, options: ReadOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn max_buffer_size(mut self, bytes: usize) -> Self {
+        self.options.maximum_buffer_size = bytes;
+        self
+    }
+
+    pub fn coordinate_precision(mut self, precision: CoordinatePrecision) -> Self {
+        self.options.coordinate_precision = precision;
+        self
+    }
+
+    pub fn node_thinning(mut self, enabled: bool) -> Self {
+        self.options.node_thinning = enabled;
+        self
+    }
+
+    pub fn index_cache_size(mut self, index_blocks: usize) -> Self {
+        self.index_cache_size = index_blocks;
+        self
+    }
+
+    pub fn lenient_file_size(mut self, lenient: bool) -> Self {
+        self.lenient_file_size = lenient;
+        self
+    }
+
+    pub fn lenient_way_offset(mut self, lenient: bool) -> Self {
+        self.options.lenient_way_offset = lenient;
+        self
+    }
+
+    pub fn include_bboxes(mut self, enabled: bool) -> Self {
+        self.options.include_bboxes = enabled;
+        self
+    }
+
+    pub fn keep_all_geometry(mut self, enabled: bool) -> Self {
+        self.options.keep_all_geometry = enabled;
+        self
+    }
+
+    pub fn layer_filter(mut self, layers: RangeInclusive<i8>) -> Self {
+        self.options.layer_filter = Some(layers);
+        self
+    }
+
+    pub fn sort_tags(mut self, enabled: bool) -> Self {
+        self.options.sort_tags = enabled;
+        self
+    }
+
+    pub fn strict_zoom(mut self, strict: bool) -> Self {
+        self.options.strict_zoom = strict;
+        self
+    }
+
+    pub fn normalize_tags(mut self, enabled: bool) -> Self {
+        self.options.normalize_tags = enabled;
+        self
+    }
+
+    pub fn clip_to_tile(mut self, enabled: bool) -> Self {
+        self.options.clip_to_tile = enabled;
+        self
+    }
+
+    pub fn dedup_nodes(mut self, enabled: bool) -> Self {
+        self.options.dedup_nodes = enabled;
+        self
+    }
+
+    pub fn observer(mut self, observer: Arc<dyn ReadObserver>) -> Self {
+        self.observer = observer;
+        self
+    }
+
+    pub fn open<P: AsRef<Path>>(self, path: P) -> Result<MapFile> {
+        let file = File::open(&path)?;
+        let file_size = file.metadata()?.len() as i64;
+
+        let mut map_file = MapFile::from_source(
+            Box::new(file),
+            file_size,
+            self.index_cache_size,
+            self.lenient_file_size,
+        )?;
+        self.apply(&mut map_file);
+        Ok(map_file)
+    }
+
+    /// Opens a map file held entirely in memory, e.g. for WASM targets or tests.
+    pub fn from_bytes(self, bytes: Vec<u8>) -> Result<MapFile> {
+        let file_size = bytes.len() as i64;
+        let source: Arc<[u8]> = bytes.into();
+        let mut map_file = MapFile::from_source(
+            Box::new(Cursor::new(source)),
+            file_size,
+            self.index_cache_size,
+            self.lenient_file_size,
+        )?;
+        self.apply(&mut map_file);
+        Ok(map_file)
+    }
+
+    /// Opens a map file that may be gzip or zstd compressed, decompressing it
+    /// into memory. Each codec needs its feature (`gzip`, `zstd`) enabled;
+    /// uncompressed files are opened from memory as they are.
+    pub fn open_compressed<P: AsRef<Path>>(self, path: P) -> Result<MapFile> {
+        let bytes = compression::decompress(std::fs::read(path)?)?;
+        self.from_bytes(bytes)
+    }
+
+    fn apply(self, map_file: &mut MapFile) {
+        map_file.options = self.options;
+        map_file.observer = self.observer;
+    }
+}
+
 pub struct MapFile {
-    file: File,
+    file: Box<dyn MapSource>,
     pub header: MapFileHeader,
-    database_index_cache: Option<IndexCache<File>>,
+    database_index_cache: Option<IndexCache<Box<dyn MapSource>>>,
     file_size: i64,
-    timestamp: i64,
     zoom_level_min: u8,
     zoom_level_max: u8,
+    options: ReadOptions,
+    observer: Arc<dyn ReadObserver>,
 }
 
 impl MapFile {
-    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, MapFileException> {
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        MapFileBuilder::new().open(path)
+    }
+
+    /// Opens a map file held entirely in memory, e.g. for WASM targets or tests.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self> {
+        MapFileBuilder::new().from_bytes(bytes)
+    }
+
+    /// Opens a gzip or zstd compressed map file, see
+    /// `MapFileBuilder::open_compressed`.
+    pub fn new_compressed<P: AsRef<Path>>(path: P) -> Result<Self> {
+        MapFileBuilder::new().open_compressed(path)
+    }
+
+    /// Reads only the metadata of the map at `path`, skipping the sub-file
+    /// parameters. Cheaper than a full open, e.g. for listing maps.
+    pub fn read_info_only<P: AsRef<Path>>(path: P) -> Result<MapFileInfo> {
         let file = File::open(&path)?;
         let file_size = file.metadata()?.len() as i64;
-        let timestamp = std::fs::metadata(&path)?
-            .modified()?
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_secs() as i64;
 
-        let mut read_buffer = ReadBuffer::new(file.try_clone()?);
+        let mut header = MapFileHeader::new();
+        header.read_info_only(&mut ReadBuffer::new(file), file_size)?;
+        header
+            .get_map_file_info()
+            .cloned()
+            .ok_or_else(|| MapFileException::new("Missing map file info"))
+    }
+
+    pub fn builder() -> MapFileBuilder {
+        MapFileBuilder::new()
+    }
+
+    fn from_source(
+        file: Box<dyn MapSource>,
+        file_size: i64,
+        index_cache_size: usize,
+        lenient_file_size: bool,
+    ) -> Result<Self> {
+        let mut read_buffer = ReadBuffer::new(file.try_clone_source()?);
 
         let mut header = MapFileHeader::new();
+        header.set_lenient_file_size(lenient_file_size);
         header.read_header(&mut read_buffer, file_size)?;
 
-        let database_index_cache = Some(IndexCache::new(file.try_clone()?, INDEX_CACHE_SIZE));
+        let database_index_cache =
+            Some(IndexCache::new(file.try_clone_source()?, index_cache_size));
 
         Ok(Self {
             file,
             header,
             database_index_cache,
             file_size,
-            timestamp,
             zoom_level_min: 0,
             zoom_level_max: u8::MAX,
+            options: ReadOptions::default(),
+            observer: Arc::new(NoopObserver),
         })
     }
 
@@ -105,8 +459,23 @@ impl MapFile {
         self.header.get_map_file_info()
     }
 
+    /// The verbatim header block, e.g. for diffing headers between map
+    /// versions.
+    pub fn raw_header_bytes(&self) -> &[u8] {
+        self.header.raw_bytes()
+    }
+
+    /// Owned copy of the map file info, for handing to other threads.
+    pub fn info_snapshot(&self) -> Option<MapFileInfoSnapshot> {
+        self.get_map_file_info().cloned()
+    }
+
+    /// Age of the map data: the map date from the header, in seconds since
+    /// the epoch. The same for every tile.
     pub fn get_data_timestamp(&self, _tile: &Tile) -> i64 {
-        self.timestamp
+        self.get_map_file_info()
+            .map(|info| info.map_date / 1000)
+            .unwrap_or_default()
     }
 
     pub fn get_map_languages(&self) -> Option<Vec<String>> {
@@ -117,11 +486,192 @@ impl MapFile {
         })
     }
 
+    /// Hit and miss counts of the index cache since the file was opened.
+    pub fn index_cache_stats(&self) -> IndexCacheStats {
+        self.database_index_cache
+            .as_ref()
+            .map(|cache| cache.stats())
+            .unwrap_or_default()
+    }
+
+    /// Loads the index entries of blocks `from_block..=to_block` of the
+    /// sub-file into the index cache, reading consecutive index blocks at once.
+    pub fn prefetch_index(
+        &mut self,
+        sub_file_parameter: &SubFileParameter,
+        from_block: i64,
+        to_block: i64,
+    ) -> Result<()> {
+        self.database_index_cache
+            .as_mut()
+            .ok_or_else(|| MapFileException::new("Missing index cache"))?
+            .prefetch(sub_file_parameter, from_block, to_block)
+    }
+
+    // Prefetches the index entries of every block of the query, a failed
+    // prefetch only costs the batching and is passed on as a warning
+    fn prefetch_query(
+        &mut self,
+        sub_file_parameter: &SubFileParameter,
+        query_parameters: &QueryParameters,
+    ) -> Result<()> {
+        let from_block = sub_file_parameter
+            .block_number(query_parameters.from_block_y, query_parameters.from_block_x)?;
+        let to_block = sub_file_parameter
+            .block_number(query_parameters.to_block_y, query_parameters.to_block_x)?;
+        if let Err(e) = self.prefetch_index(sub_file_parameter, from_block, to_block) {
+            self.observer
+                .on_warning(&format!("Error prefetching index: {}", e));
+        }
+        Ok(())
+    }
+
+    /// The distinct sub-files of the map, see
+    /// `MapFileHeader::sub_file_parameters`.
+    pub fn sub_files(&self) -> &[SubFileParameter] {
+        self.header.sub_file_parameters()
+    }
+
+    /// The options the reads decode with.
+    pub fn options(&self) -> &ReadOptions {
+        &self.options
+    }
+
+    /// Changes the options of the reads from now on.
+    pub fn options_mut(&mut self) -> &mut ReadOptions {
+        &mut self.options
+    }
+
+    pub fn set_coordinate_precision(&mut self, precision: CoordinatePrecision) {
+        self.options.coordinate_precision = precision;
+    }
+
+    /// Thins way nodes to about one per pixel at the query zoom level.
+    pub fn set_node_thinning(&mut self, enabled: bool) {
+        self.options.node_thinning = enabled;
+    }
+
+    /// Fills `Way::bbox` of the ways read from now on.
+    pub fn set_include_bboxes(&mut self, enabled: bool) {
+        self.options.include_bboxes = enabled;
+    }
+
+    /// Keeps the POIs and ways outside the queried tile that share its blocks
+    /// above the base zoom level, for callers clipping on their own.
+    pub fn set_keep_all_geometry(&mut self, enabled: bool) {
+        self.options.keep_all_geometry = enabled;
+    }
+
+    /// Only returns POIs and ways whose layer lies in the range, None for all
+    /// layers.
+    pub fn set_layer_filter(&mut self, layers: Option<RangeInclusive<i8>>) {
+        self.options.layer_filter = layers;
+    }
+
+    /// Sorts the tags of the features read from now on by key, instead of
+    /// the order they are stored in.
+    pub fn set_sort_tags(&mut self, enabled: bool) {
+        self.options.sort_tags = enabled;
+    }
+
+    /// Fails queries above the maximum zoom level of the map instead of
+    /// reading the data of the maximum zoom level.
+    pub fn set_strict_zoom(&mut self, strict: bool) {
+        self.options.strict_zoom = strict;
+    }
+
+    // The zoom level whose data answers a query at `zoom_level`
+    fn query_zoom_level(&self, zoom_level: u8) -> Result<u8> {
+        let query_zoom_level = self.header.get_query_zoom_level(zoom_level);
+        if self.options.strict_zoom && zoom_level > query_zoom_level {
+            return Err(MapFileException::with_kind(
+                MapFileErrorKind::NoCoverage {
+                    zoom_level,
+                    max: query_zoom_level,
+                },
+                format!(
+                    "no sub-file covers zoom level {} (maximum: {})",
+                    zoom_level, query_zoom_level
+                ),
+            ));
+        }
+        Ok(query_zoom_level)
+    }
+
+    // The sub-file answering queries at `query_zoom_level`, cloned so that
+    // self stays free to borrow
+    fn sub_file_for(&self, query_zoom_level: i32) -> Result<SubFileParameter> {
+        self.header
+            .get_sub_file_parameter(query_zoom_level as usize)
+            .cloned()
+            .ok_or_else(|| {
+                MapFileException::new(format!("no sub-file for zoom level: {}", query_zoom_level))
+            })
+    }
+
+    /// Lowercases the tag keys and trims the tag values of the features read
+    /// from now on. The case of values is kept.
+    pub fn set_normalize_tags(&mut self, enabled: bool) {
+        self.options.normalize_tags = enabled;
+    }
+
+    /// Clips the ways read from now on to the queried tiles.
+    pub fn set_clip_to_tile(&mut self, enabled: bool) {
+        self.options.clip_to_tile = enabled;
+    }
+
+    /// Drops consecutive duplicate nodes from the ways read from now on.
+    pub fn set_dedup_nodes(&mut self, enabled: bool) {
+        self.options.dedup_nodes = enabled;
+    }
+
+    /// Sends the block events and recoverable warnings of the reads from now
+    /// on to `observer`, e.g. a `TracingObserver` to log them.
+    pub fn set_observer(&mut self, observer: Arc<dyn ReadObserver>) {
+        self.observer = observer;
+    }
+
+    // Applies tag normalization, then sorting. The sort is stable, so tags
+    // sharing a key keep their stream order
+    fn finish_tags(&self, tags: &mut [Tag], interner: &mut Interner) {
+        if self.options.normalize_tags {
+            for tag in tags.iter_mut() {
+                if tag.key.chars().any(char::is_uppercase) {
+                    tag.key = interner.intern(&tag.key.to_lowercase());
+                }
+                if tag.value.trim().len() != tag.value.len() {
+                    tag.value = interner.intern(tag.value.trim());
+                }
+            }
+        }
+        if self.options.sort_tags {
+            tags.sort_by(|a, b| a.key.cmp(&b.key));
+        }
+    }
+
+    // Whether features on `layer` pass the layer filter
+    fn layer_wanted(&self, layer: i8) -> bool {
+        self.options
+            .layer_filter
+            .as_ref()
+            .is_none_or(|layers| layers.contains(&layer))
+    }
+
     pub fn restrict_to_zoom_range(&mut self, min_zoom: u8, max_zoom: u8) {
         self.zoom_level_max = max_zoom;
         self.zoom_level_min = min_zoom;
     }
 
+    /// Whether the tile overlaps the map's bounding box at all.
+    pub fn covers(&self, tile: &Tile) -> bool {
+        self.covers_bounding_box(&tile.get_bounding_box())
+    }
+
+    fn covers_bounding_box(&self, bounding_box: &BoundingBox) -> bool {
+        self.get_map_file_info()
+            .is_some_and(|info| info.bounding_box.intersects(bounding_box))
+    }
+
     pub fn start_position(&self) -> LatLong {
         if let Some(info) = self.get_map_file_info() {
             if let Some(pos) = &info.start_position {
@@ -134,6 +684,24 @@ impl MapFile {
         panic!("Missing MapFileInfo");
     }
 
+    /// Tile size in pixels as declared in the header.
+    pub fn tile_size(&self) -> i32 {
+        self.get_map_file_info()
+            .map(|info| info.tile_pixel_size)
+            .unwrap_or(256)
+    }
+
+    /// The tile at `zoom` containing the point, sized as declared in the
+    /// header.
+    pub fn make_tile(&self, latitude: f64, longitude: f64, zoom: u8) -> Tile {
+        Tile::new(
+            MercatorProjection::longitude_to_tile_x(longitude, zoom),
+            MercatorProjection::latitude_to_tile_y(latitude, zoom),
+            zoom,
+            self.tile_size(),
+        )
+    }
+
     pub fn start_zoom_level(&self) -> u8 {
         if let Some(info) = self.get_map_file_info() {
             info.start_zoom_level.unwrap_or(DEFAULT_START_ZOOM_LEVEL)
@@ -150,12 +718,11 @@ impl MapFile {
     }
 
     fn decode_way_nodes_double_delta(
-        &self,
         way_segment: &mut [LatLong],
         tile_latitude: f64,
         tile_longitude: f64,
         read_buffer: &mut ReadBuffer<impl Read + Seek>,
-    ) -> Result<(), MapFileException> {
+    ) -> Result<()> {
         // Get the first way node latitude offset (VBE-S)
         let way_node_latitude =
             tile_latitude + LatLongUtils::microdegrees_to_degrees(read_buffer.read_signed_int()?);
@@ -187,11 +754,11 @@ impl MapFile {
 
             // Handle international date line cases
             if way_node_lon < LatLongUtils::LONGITUDE_MIN
-                && (LatLongUtils::LONGITUDE_MIN - way_node_lon).abs() < 0.001
+                && (LatLongUtils::LONGITUDE_MIN - way_node_lon).abs() < DATE_LINE_EPSILON
             {
                 way_node_lon = LatLongUtils::LONGITUDE_MIN;
             } else if way_node_lon > LatLongUtils::LONGITUDE_MAX
-                && (way_node_lon - LatLongUtils::LONGITUDE_MAX).abs() < 0.001
+                && (way_node_lon - LatLongUtils::LONGITUDE_MAX).abs() < DATE_LINE_EPSILON
             {
                 way_node_lon = LatLongUtils::LONGITUDE_MAX;
             }
@@ -206,12 +773,11 @@ impl MapFile {
     }
 
     fn decode_way_nodes_single_delta(
-        &self,
         way_segment: &mut [LatLong],
         tile_latitude: f64,
         tile_longitude: f64,
         read_buffer: &mut ReadBuffer<impl Read + Seek>,
-    ) -> Result<(), MapFileException> {
+    ) -> Result<()> {
         // Get the first way node latitude offset (VBE-S)
         let mut way_node_latitude =
             tile_latitude + LatLongUtils::microdegrees_to_degrees(read_buffer.read_signed_int()?);
@@ -230,11 +796,11 @@ impl MapFile {
 
             // Handle international date line cases
             if way_node_longitude < LatLongUtils::LONGITUDE_MIN
-                && (LatLongUtils::LONGITUDE_MIN - way_node_longitude).abs() < 0.001
+                && (LatLongUtils::LONGITUDE_MIN - way_node_longitude).abs() < DATE_LINE_EPSILON
             {
                 way_node_longitude = LatLongUtils::LONGITUDE_MIN;
             } else if way_node_longitude > LatLongUtils::LONGITUDE_MAX
-                && (way_node_longitude - LatLongUtils::LONGITUDE_MAX).abs() < 0.001
+                && (way_node_longitude - LatLongUtils::LONGITUDE_MAX).abs() < DATE_LINE_EPSILON
             {
                 way_node_longitude = LatLongUtils::LONGITUDE_MAX;
             }
@@ -246,12 +812,14 @@ impl MapFile {
     }
 
     fn process_way_data_block(
-        &self,
         tile_latitude: f64,
         tile_longitude: f64,
         double_delta_encoding: bool,
+        thinning_zoom: Option<u8>,
+        coordinate_precision: CoordinatePrecision,
+        dedup_nodes: bool,
         read_buffer: &mut ReadBuffer<impl Read + Seek>,
-    ) -> Result<Vec<Vec<LatLong>>, MapFileException> {
+    ) -> Result<Vec<Vec<LatLong>>> {
         // Get and check the number of way coordinate blocks (VBE-U)
         let number_of_way_coordinate_blocks = read_buffer.read_unsigned_int()? as usize;
         if number_of_way_coordinate_blocks < 1
@@ -278,14 +846,14 @@ impl MapFile {
             let mut way_segment = vec![LatLong::new(0.0, 0.0); number_of_way_nodes];
 
             if double_delta_encoding {
-                self.decode_way_nodes_double_delta(
+                Self::decode_way_nodes_double_delta(
                     &mut way_segment,
                     tile_latitude,
                     tile_longitude,
                     read_buffer,
                 )?;
             } else {
-                self.decode_way_nodes_single_delta(
+                Self::decode_way_nodes_single_delta(
                     &mut way_segment,
                     tile_latitude,
                     tile_longitude,
@@ -293,21 +861,58 @@ impl MapFile {
                 )?;
             }
 
+            if let Some(zoom) = thinning_zoom {
+                way_segment = Self::thin_way_nodes(way_segment, zoom);
+            }
+            for node in way_segment.iter_mut() {
+                *node = coordinate_precision.apply(node.clone());
+            }
+            // After rounding, which can make neighboring nodes equal
+            if dedup_nodes {
+                way_segment.dedup();
+            }
             way_coordinates.push(way_segment);
         }
 
         Ok(way_coordinates)
     }
 
+    // Drops nodes closer than one pixel at `zoom` to the last kept node. The
+    // first and last node are always kept.
+    fn thin_way_nodes(way_segment: Vec<LatLong>, zoom: u8) -> Vec<LatLong> {
+        let last = way_segment.len() - 1;
+        let mut thinned: Vec<LatLong> = Vec::with_capacity(way_segment.len());
+        for (index, node) in way_segment.into_iter().enumerate() {
+            let keep = match thinned.last() {
+                Some(kept) if index < last => {
+                    let meters_per_degree = EARTH_RADIUS.to_radians();
+                    let dy = (node.latitude - kept.latitude) * meters_per_degree;
+                    let dx = (node.longitude - kept.longitude)
+                        * meters_per_degree
+                        * kept.latitude.to_radians().cos();
+                    dx.hypot(dy) >= MercatorProjection::meters_per_pixel(kept.latitude, zoom)
+                }
+                _ => true,
+            };
+            if keep {
+                thinned.push(node);
+            }
+        }
+        thinned
+    }
+
     fn process_pois(
         &self,
+        context: &mut QueryContext,
         tile_latitude: f64,
         tile_longitude: f64,
         number_of_pois: usize,
-        bounding_box: &BoundingBox,
         filter_required: bool,
         read_buffer: &mut ReadBuffer<impl Read + Seek>,
-    ) -> Result<Vec<PointOfInterest>, MapFileException> {
+    ) -> Result<Vec<PointOfInterest>> {
+        let interner = &mut context.interner;
+        let bounding_box = &context.bounding_box;
+        let poi_filter = context.poi_filter.as_ref();
         let mut pois = Vec::new();
         let poi_tags = self
             .get_map_file_info()
@@ -318,14 +923,7 @@ impl MapFile {
         for _ in 0..number_of_pois {
             if self.header.get_map_file_info().unwrap().debug_file {
                 // Check POI signature in debug mode
-                let signature_poi =
-                    read_buffer.read_utf8_encoded_string_with_length(SIGNATURE_LENGTH_POI)?;
-                if !signature_poi.starts_with("***POIStart") {
-                    return Err(MapFileException::new(format!(
-                        "invalid POI signature: {}",
-                        signature_poi
-                    )));
-                }
+                Self::check_poi_signature(read_buffer)?;
             }
 
             // Get POI position
@@ -336,43 +934,49 @@ impl MapFile {
 
             // Read special byte
             let special_byte = read_buffer.read_byte()?;
-            let layer = ((special_byte & POI_LAYER_BITMASK) >> POI_LAYER_SHIFT) as i8;
+            let layer = ((special_byte & POI_LAYER_BITMASK) >> POI_LAYER_SHIFT) as i8 - LAYER_BIAS;
             let number_of_tags = special_byte & POI_NUMBER_OF_TAGS_BITMASK;
 
             // Get tags
             let mut tags = read_buffer.read_tags(&poi_tags, number_of_tags)?;
 
             // Read feature byte
-            let feature_byte = read_buffer.read_byte()?;
-            let feature_name = (feature_byte & POI_FEATURE_NAME) != 0;
-            let feature_house_number = (feature_byte & POI_FEATURE_HOUSE_NUMBER) != 0;
-            let feature_elevation = (feature_byte & POI_FEATURE_ELEVATION) != 0;
+            let features = FeatureFlags::from_poi_byte(read_buffer.read_byte()?);
 
             // Add optional features
-            if feature_name {
+            if features.has_name {
                 tags.push(Tag::new(
-                    TAG_KEY_NAME.to_string(),
-                    read_buffer.read_utf8_encoded_string()?,
+                    interner.intern(TAG_KEY_NAME),
+                    interner.intern(&read_buffer.read_utf8_encoded_string()?),
                 ));
             }
 
-            if feature_house_number {
+            if features.has_house_number {
                 tags.push(Tag::new(
-                    TAG_KEY_HOUSE_NUMBER.to_string(),
-                    read_buffer.read_utf8_encoded_string()?,
+                    interner.intern(TAG_KEY_HOUSE_NUMBER),
+                    interner.intern(&read_buffer.read_utf8_encoded_string()?),
                 ));
             }
 
-            if feature_elevation {
+            if features.has_elevation {
                 tags.push(Tag::new(
-                    TAG_KEY_ELE.to_string(),
-                    read_buffer.read_signed_int()?.to_string(),
+                    interner.intern(TAG_KEY_ELE),
+                    interner.intern(&read_buffer.read_signed_int()?.to_string()),
                 ));
             }
-
-            let position = LatLong::new(latitude, longitude);
-            if !filter_required || bounding_box.contains(latitude, longitude) {
-                pois.push(PointOfInterest::new(layer, tags, position));
+            self.finish_tags(&mut tags, interner);
+
+            let position = self
+                .options
+                .coordinate_precision
+                .apply(LatLong::new(latitude, longitude));
+            if (!filter_required || bounding_box.contains(latitude, longitude))
+                && self.layer_wanted(layer)
+            {
+                let poi = PointOfInterest::new(layer, tags, position);
+                if poi_filter.is_none_or(|filter| filter.matches(&poi)) {
+                    pois.push(poi);
+                }
             }
         }
 
@@ -382,7 +986,7 @@ impl MapFile {
     fn process_block_signature(
         &self,
         read_buffer: &mut ReadBuffer<impl Read + Seek>,
-    ) -> Result<bool, MapFileException> {
+    ) -> Result<bool> {
         if self.header.get_map_file_info().unwrap().debug_file {
             let signature_block =
                 read_buffer.read_utf8_encoded_string_with_length(SIGNATURE_LENGTH_BLOCK)?;
@@ -396,11 +1000,35 @@ impl MapFile {
         Ok(true)
     }
 
+    fn check_poi_signature(read_buffer: &mut ReadBuffer<impl Read + Seek>) -> Result<()> {
+        let signature_poi =
+            read_buffer.read_utf8_encoded_string_with_length(SIGNATURE_LENGTH_POI)?;
+        if !signature_poi.starts_with("***POIStart") {
+            return Err(MapFileException::new(format!(
+                "invalid POI signature: {}",
+                signature_poi
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_way_signature(read_buffer: &mut ReadBuffer<impl Read + Seek>) -> Result<()> {
+        let signature_way =
+            read_buffer.read_utf8_encoded_string_with_length(SIGNATURE_LENGTH_WAY)?;
+        if !signature_way.starts_with("---WayStart") {
+            return Err(MapFileException::new(format!(
+                "invalid way signature: {}",
+                signature_way
+            )));
+        }
+        Ok(())
+    }
+
     fn read_zoom_table(
         &self,
         sub_file_parameter: &SubFileParameter,
         read_buffer: &mut ReadBuffer<impl Read + Seek>,
-    ) -> Result<Vec<[i32; 2]>, MapFileException> {
+    ) -> Result<Vec<[i32; 2]>> {
         let rows =
             (sub_file_parameter.zoom_level_max - sub_file_parameter.zoom_level_min + 1) as usize;
         let mut zoom_table = vec![[0, 0]; rows];
@@ -419,24 +1047,11 @@ impl MapFile {
         Ok(zoom_table)
     }
 
-    fn read_optional_label_position(
-        &self,
-        read_buffer: &mut ReadBuffer<impl Read + Seek>,
-    ) -> Result<[i32; 2], MapFileException> {
-        let mut label_position = [0, 0];
-
-        // Get label position offsets (VBE-S)
-        label_position[1] = read_buffer.read_signed_int()?;
-        label_position[0] = read_buffer.read_signed_int()?;
-
-        Ok(label_position)
-    }
-
     fn read_optional_way_data_blocks_byte(
         &self,
         feature_way_data_blocks_byte: bool,
         read_buffer: &mut ReadBuffer<impl Read + Seek>,
-    ) -> Result<i32, MapFileException> {
+    ) -> Result<i32> {
         if feature_way_data_blocks_byte {
             read_buffer.read_unsigned_int().map(|v| v as i32)
         } else {
@@ -446,15 +1061,21 @@ impl MapFile {
 
     fn process_ways(
         &self,
-        query_parameters: &QueryParameters,
+        context: &mut QueryContext,
         number_of_ways: usize,
-        bounding_box: &BoundingBox,
         filter_required: bool,
         tile_latitude: f64,
         tile_longitude: f64,
-        selector: Selector,
         read_buffer: &mut ReadBuffer<impl Read + Seek>,
-    ) -> Result<Vec<Way>, MapFileException> {
+    ) -> Result<Vec<Way>> {
+        let QueryContext {
+            query_parameters,
+            bounding_box,
+            selector,
+            interner,
+            ..
+        } = context;
+        let selector = *selector;
         let mut ways = Vec::new();
         let way_tags = self
             .get_map_file_info()
@@ -468,145 +1089,198 @@ impl MapFile {
         } else {
             bounding_box.clone()
         };
+        let thinning_zoom = self
+            .options
+            .node_thinning
+            .then_some(query_parameters.query_zoom_level as u8);
 
         for _ in 0..number_of_ways {
-            if self.header.get_map_file_info().unwrap().debug_file {
-                // Check way signature in debug mode
-                let signature_way =
-                    read_buffer.read_utf8_encoded_string_with_length(SIGNATURE_LENGTH_WAY)?;
-                if !signature_way.starts_with("---WayStart") {
+            let (way_data_size, tile_bitmask) = self.read_way_header(read_buffer)?;
+
+            if query_parameters.use_tile_bitmask
+                && (query_parameters.query_tile_bitmask & tile_bitmask as i32) == 0
+            {
+                // Skip the rest of the way, the tile bitmask was already read
+                read_buffer.skip_bytes((way_data_size - 2) as usize);
+                continue;
+            }
+
+            // The layer is in the first byte of the way data, so ways on other
+            // layers are skipped before decoding anything
+            if self.options.layer_filter.is_some() {
+                // The size covers the tile bitmask and the special byte at least
+                if way_data_size < 3 {
                     return Err(MapFileException::new(format!(
-                        "invalid way signature: {}",
-                        signature_way
+                        "invalid way data size: {}",
+                        way_data_size
                     )));
                 }
+                let special_byte = read_buffer.read_byte()?;
+                let layer =
+                    ((special_byte & WAY_LAYER_BITMASK) >> WAY_LAYER_SHIFT) as i8 - LAYER_BIAS;
+                if !self.layer_wanted(layer) {
+                    read_buffer.skip_bytes((way_data_size - 3) as usize);
+                    continue;
+                }
+                read_buffer.set_buffer_position(read_buffer.get_buffer_position() - 1);
             }
 
-            // Get way data size
-            let way_data_size = read_buffer.read_unsigned_int()? as i32;
-            if way_data_size < 0 {
-                return Err(MapFileException::new(format!(
-                    "invalid way data size: {}",
-                    way_data_size
-                )));
-            }
+            let (has_label_feature, decoded) = self.read_way(
+                tile_latitude,
+                tile_longitude,
+                &way_tags,
+                thinning_zoom,
+                read_buffer,
+                interner,
+            )?;
 
-            if query_parameters.use_tile_bitmask {
-                // Check if way is inside requested tile
-                let tile_bitmask = read_buffer.read_short()? as i32;
-                if (query_parameters.query_tile_bitmask & tile_bitmask) == 0 {
-                    // Skip the rest of the way
-                    read_buffer.skip_bytes((way_data_size - 2) as usize);
+            for way in decoded {
+                // Skip if way is outside filter area
+                if filter_required
+                    && unsafe { WAY_FILTER_ENABLED }
+                    && !Self::way_intersects_bbox(&way.way_nodes, &way_filter_bbox)
+                {
                     continue;
                 }
-            } else {
-                // Skip tile bitmask
-                read_buffer.skip_bytes(2);
+
+                // Add way if it meets selector criteria
+                if matches!(selector, Selector::All | Selector::Ways)
+                    || has_label_feature
+                    || Self::has_label_tag(&way.tags)
+                {
+                    if !self.options.clip_to_tile {
+                        ways.push(way);
+                    } else if let Some(clipped) = clip_way_to_bbox(&way, bounding_box) {
+                        ways.push(clipped);
+                    }
+                }
             }
+        }
 
-            // Read special byte
-            let special_byte = read_buffer.read_byte()?;
-            let layer = ((special_byte & WAY_LAYER_BITMASK) >> WAY_LAYER_SHIFT) as i8;
-            let number_of_tags = special_byte & WAY_NUMBER_OF_TAGS_BITMASK;
+        Ok(ways)
+    }
 
-            // Get tags
-            let mut tags = read_buffer.read_tags(&way_tags, number_of_tags)?;
+    // Reads the optional signature, the way data size and the tile bitmask
+    fn read_way_header(
+        &self,
+        read_buffer: &mut ReadBuffer<impl Read + Seek>,
+    ) -> Result<(i32, u16)> {
+        if self.header.get_map_file_info().unwrap().debug_file {
+            // Check way signature in debug mode
+            Self::check_way_signature(read_buffer)?;
+        }
 
-            // Read feature byte
-            let feature_byte = read_buffer.read_byte()?;
-            let feature_name = (feature_byte & WAY_FEATURE_NAME) != 0;
-            let feature_house_number = (feature_byte & WAY_FEATURE_HOUSE_NUMBER) != 0;
-            let feature_ref = (feature_byte & WAY_FEATURE_REF) != 0;
-            let feature_label_position = (feature_byte & WAY_FEATURE_LABEL_POSITION) != 0;
-            let feature_data_blocks_byte = (feature_byte & WAY_FEATURE_DATA_BLOCKS_BYTE) != 0;
-            let feature_double_delta_encoding =
-                (feature_byte & WAY_FEATURE_DOUBLE_DELTA_ENCODING) != 0;
+        // Get way data size
+        let way_data_size = read_buffer.read_unsigned_int()? as i32;
+        if way_data_size < 0 {
+            return Err(MapFileException::new(format!(
+                "invalid way data size: {}",
+                way_data_size
+            )));
+        }
 
-            // Add optional features
-            if feature_name {
-                tags.push(Tag::new(
-                    TAG_KEY_NAME.to_string(),
-                    read_buffer.read_utf8_encoded_string()?,
-                ));
-            }
+        let tile_bitmask = read_buffer.read_short()? as u16;
+        Ok((way_data_size, tile_bitmask))
+    }
 
-            if feature_house_number {
-                tags.push(Tag::new(
-                    TAG_KEY_HOUSE_NUMBER.to_string(),
-                    read_buffer.read_utf8_encoded_string()?,
-                ));
-            }
-
-            if feature_ref {
-                tags.push(Tag::new(
-                    TAG_KEY_REF.to_string(),
-                    read_buffer.read_utf8_encoded_string()?,
-                ));
-            }
+    // Decodes the rest of a way, returning one `Way` per way data block and
+    // whether it carries a name, house number or ref
+    fn read_way(
+        &self,
+        tile_latitude: f64,
+        tile_longitude: f64,
+        way_tags: &[Tag],
+        thinning_zoom: Option<u8>,
+        read_buffer: &mut ReadBuffer<impl Read + Seek>,
+        interner: &mut Interner,
+    ) -> Result<(bool, Vec<Way>)> {
+        // Read special byte
+        let special_byte = read_buffer.read_byte()?;
+        let layer = ((special_byte & WAY_LAYER_BITMASK) >> WAY_LAYER_SHIFT) as i8 - LAYER_BIAS;
+        let number_of_tags = special_byte & WAY_NUMBER_OF_TAGS_BITMASK;
+
+        // Get tags
+        let mut tags = read_buffer.read_tags(way_tags, number_of_tags)?;
+
+        // Read feature byte
+        let features = FeatureFlags::from_byte(read_buffer.read_byte()?);
+
+        // Add optional features
+        if features.has_name {
+            tags.push(Tag::new(
+                interner.intern(TAG_KEY_NAME),
+                interner.intern(&read_buffer.read_utf8_encoded_string()?),
+            ));
+        }
 
-            // Read label position if present
-            let label_position = if feature_label_position {
-                Some(self.read_optional_label_position(read_buffer)?)
-            } else {
-                None
-            };
+        if features.has_house_number {
+            tags.push(Tag::new(
+                interner.intern(TAG_KEY_HOUSE_NUMBER),
+                interner.intern(&read_buffer.read_utf8_encoded_string()?),
+            ));
+        }
 
-            // Read number of way data blocks
-            let way_data_blocks =
-                self.read_optional_way_data_blocks_byte(feature_data_blocks_byte, read_buffer)?;
-            if way_data_blocks < 1 {
-                return Err(MapFileException::new(format!(
-                    "invalid number of way data blocks: {}",
-                    way_data_blocks
-                )));
-            }
+        if features.has_ref {
+            tags.push(Tag::new(
+                interner.intern(TAG_KEY_REF),
+                interner.intern(&read_buffer.read_utf8_encoded_string()?),
+            ));
+        }
+        self.finish_tags(&mut tags, interner);
 
-            // Process each way data block
-            for _ in 0..way_data_blocks {
-                let way_nodes = self.process_way_data_block(
-                    tile_latitude,
-                    tile_longitude,
-                    feature_double_delta_encoding,
-                    read_buffer,
-                )?;
+        // Read label position if present
+        let label_position = if features.has_label_position {
+            Some(LabelPosition::read(read_buffer)?)
+        } else {
+            None
+        };
 
-                // Skip if way is outside filter area
-                if filter_required
-                    && unsafe { WAY_FILTER_ENABLED }
-                    && !Self::way_intersects_bbox(&way_nodes, &way_filter_bbox)
-                {
-                    continue;
-                }
+        // Read number of way data blocks
+        let way_data_blocks =
+            self.read_optional_way_data_blocks_byte(features.has_data_blocks_byte, read_buffer)?;
+        if way_data_blocks < 1 {
+            return Err(MapFileException::new(format!(
+                "invalid number of way data blocks: {}",
+                way_data_blocks
+            )));
+        }
 
-                // Add way if it meets selector criteria
-                if matches!(selector, Selector::All)
-                    || feature_name
-                    || feature_house_number
-                    || feature_ref
-                    || Self::has_label_tag(&tags)
-                {
-                    let label_pos = label_position.map(|pos| {
-                        LatLong::new(
-                            way_nodes[0][0].latitude
-                                + LatLongUtils::microdegrees_to_degrees(pos[1]),
-                            way_nodes[0][0].longitude
-                                + LatLongUtils::microdegrees_to_degrees(pos[0]),
-                        )
-                    });
-
-                    ways.push(Way::new(layer, tags.clone(), way_nodes, label_pos));
-                }
+        // Process each way data block
+        let mut ways = Vec::with_capacity(way_data_blocks as usize);
+        for _ in 0..way_data_blocks {
+            let way_nodes = Self::process_way_data_block(
+                tile_latitude,
+                tile_longitude,
+                features.double_delta,
+                thinning_zoom,
+                self.options.coordinate_precision,
+                self.options.dedup_nodes,
+                read_buffer,
+            )?;
+
+            let label_pos = label_position.map(|pos| {
+                self.options
+                    .coordinate_precision
+                    .apply(pos.apply_to(&way_nodes[0][0]))
+            });
+            let mut way = Way::new(layer, tags.clone(), way_nodes, label_pos);
+            if self.options.include_bboxes {
+                way.bbox = way.bounding_box();
             }
+            ways.push(way);
         }
 
-        Ok(ways)
+        Ok((
+            features.has_name || features.has_house_number || features.has_ref,
+            ways,
+        ))
     }
 
     fn has_label_tag(tags: &[Tag]) -> bool {
         // Implementation depends on your tag filtering logic
         // For now, return true if any tag might need a label
         tags.iter()
-            .any(|tag| tag.key == TAG_KEY_NAME || tag.key == TAG_KEY_REF)
+            .any(|tag| tag.as_key() == TAG_KEY_NAME || tag.as_key() == TAG_KEY_REF)
     }
 
     fn way_intersects_bbox(way_nodes: &[Vec<LatLong>], bbox: &BoundingBox) -> bool {
@@ -628,17 +1302,17 @@ impl Drop for MapFile {
 impl MapFile {
     fn process_block(
         &self,
-        query_parameters: &QueryParameters,
-        sub_file_parameter: &SubFileParameter,
-        bounding_box: &BoundingBox,
+        context: &mut QueryContext,
         tile_latitude: f64,
         tile_longitude: f64,
-        selector: Selector,
         read_buffer: &mut ReadBuffer<impl Read + Seek>,
-    ) -> Result<Option<PoiWayBundle>, MapFileException> {
+    ) -> Result<Option<PoiWayBundle>> {
         if !self.process_block_signature(read_buffer)? {
             return Ok(None);
         }
+        let query_parameters = &context.query_parameters;
+        let sub_file_parameter = &context.sub_file_parameter;
+        let selector = context.selector;
 
         let zoom_table = self.read_zoom_table(sub_file_parameter, read_buffer)?;
         let zoom_table_row =
@@ -647,55 +1321,71 @@ impl MapFile {
         let ways_on_query_zoom_level = zoom_table[zoom_table_row as usize][1] as usize;
 
         // Get first way offset
-        let first_way_offset = read_buffer.read_unsigned_int()? as i32;
-        if first_way_offset < 0 {
-            return Err(MapFileException::new(format!(
-                "{}{}",
-                INVALID_FIRST_WAY_OFFSET, first_way_offset
-            )));
-        }
-
-        let first_way_offset = first_way_offset + read_buffer.get_buffer_position() as i32;
-        if first_way_offset > read_buffer.get_buffer_size() as i32 {
-            return Err(MapFileException::new(format!(
-                "{}{}",
-                INVALID_FIRST_WAY_OFFSET, first_way_offset
-            )));
+        let way_offset = read_buffer.read_unsigned_int()? as i32;
+        let first_way_offset = way_offset as i64 + read_buffer.get_buffer_position() as i64;
+        let mut ways_readable = true;
+        if way_offset < 0 || first_way_offset > read_buffer.get_buffer_size() as i64 {
+            let invalid_offset = if way_offset < 0 {
+                way_offset as i64
+            } else {
+                first_way_offset
+            };
+            if !self.options.lenient_way_offset {
+                return Err(MapFileException::new(format!(
+                    "{}{}",
+                    INVALID_FIRST_WAY_OFFSET, invalid_offset
+                )));
+            }
+            self.observer.on_warning(&format!(
+                "{}{}, skipping the ways of the block",
+                INVALID_FIRST_WAY_OFFSET, invalid_offset
+            ));
+            ways_readable = false;
         }
 
-        let filter_required =
-            query_parameters.query_zoom_level > sub_file_parameter.base_zoom_level as i32;
+        let filter_required = !self.options.keep_all_geometry
+            && query_parameters.query_zoom_level > sub_file_parameter.base_zoom_level as i32;
 
         // Process POIs
-        let pois = self.process_pois(
-            tile_latitude,
-            tile_longitude,
-            pois_on_query_zoom_level,
-            bounding_box,
-            filter_required,
-            read_buffer,
-        )?;
-
-        let ways = if matches!(selector, Selector::Pois) {
+        let pois = if matches!(selector, Selector::Ways) {
             Vec::new()
         } else {
-            if read_buffer.get_buffer_position() > first_way_offset as usize {
+            self.process_pois(
+                context,
+                tile_latitude,
+                tile_longitude,
+                pois_on_query_zoom_level,
+                filter_required,
+                read_buffer,
+            )?
+        };
+
+        // The POIs must end at or before the first way
+        if ways_readable && read_buffer.get_buffer_position() > first_way_offset as usize {
+            if !self.options.lenient_way_offset {
                 return Err(MapFileException::new(format!(
                     "invalid buffer position: {}",
                     read_buffer.get_buffer_position()
                 )));
             }
+            self.observer.on_warning(&format!(
+                "invalid buffer position: {}, skipping the ways of the block",
+                read_buffer.get_buffer_position()
+            ));
+            ways_readable = false;
+        }
 
+        let ways = if matches!(selector, Selector::Pois) || !ways_readable {
+            Vec::new()
+        } else {
             read_buffer.set_buffer_position(first_way_offset as usize);
 
             self.process_ways(
-                query_parameters,
+                context,
                 ways_on_query_zoom_level,
-                bounding_box,
                 filter_required,
                 tile_latitude,
                 tile_longitude,
-                selector,
                 read_buffer,
             )?
         };
@@ -705,19 +1395,19 @@ impl MapFile {
 
     fn process_blocks(
         &mut self,
-        query_parameters: &QueryParameters,
-        sub_file_parameter: &SubFileParameter,
-        bounding_box: &BoundingBox,
-        selector: Selector,
-    ) -> Result<MapReadResult, MapFileException> {
+        context: &mut QueryContext,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<MapReadResult> {
         let mut query_is_water = true;
         let mut query_read_water_info = false;
         let mut result = MapReadResult {
             poi_way_bundles: Vec::new(),
             is_water: false,
         };
+        let query_parameters = context.query_parameters.clone();
+        let sub_file_parameter = context.sub_file_parameter.clone();
 
-        info!(
+        debug!(
             "Processing blocks from {} to {} (x) and {} to {} (y)",
             query_parameters.from_block_x,
             query_parameters.to_block_x,
@@ -725,14 +1415,23 @@ impl MapFile {
             query_parameters.to_block_y
         );
 
+        // Load the index entries of the whole query in as few reads as possible
+        self.prefetch_query(&sub_file_parameter, &query_parameters)?;
+
+        let total_blocks = ((query_parameters.to_block_y - query_parameters.from_block_y + 1)
+            * (query_parameters.to_block_x - query_parameters.from_block_x + 1))
+            .max(0) as usize;
+        let mut blocks_done = 0;
+
         // Process blocks from top to bottom and left to right
         for row in query_parameters.from_block_y..=query_parameters.to_block_y {
             for column in query_parameters.from_block_x..=query_parameters.to_block_x {
-                let block_number = row * sub_file_parameter.blocks_width + column;
-                info!(
-                    "Processing block {}, at row {} column {}",
-                    block_number, row, column
-                );
+                // Reported before each block, as skipped blocks leave the loop early
+                progress(blocks_done, total_blocks);
+                blocks_done += 1;
+
+                let block_number = sub_file_parameter.block_number(row, column)?;
+                self.observer.on_block(block_number, row, column);
 
                 // Get current index entry
                 let current_block_index_entry = match self
@@ -743,7 +1442,8 @@ impl MapFile {
                 {
                     Ok(entry) => entry,
                     Err(e) => {
-                        warn!("Error getting index entry: {}", e);
+                        self.observer
+                            .on_warning(&format!("Error getting index entry: {}", e));
                         continue; // Skip this block on error
                     }
                 };
@@ -754,143 +1454,967 @@ impl MapFile {
                     query_read_water_info = true;
                 }
 
-                // Get and check block pointer
-                let current_block_pointer = current_block_index_entry & BITMASK_INDEX_OFFSET;
-                info!("Block pointer: {}", current_block_pointer);
+                if let Some(bundle) =
+                    self.read_block_at(context, (column, row), current_block_index_entry)?
+                {
+                    result.poi_way_bundles.push(bundle);
+                }
+            }
+        }
 
-                // Skip blocks with invalid pointers, but log it
-                if current_block_pointer == 0 {
-                    warn!("Skipping block with zero pointer");
-                    continue;
+        progress(blocks_done, total_blocks);
+
+        if query_is_water && query_read_water_info {
+            result.is_water = true;
+        }
+
+        debug!(
+            "Processed all blocks, found {} bundles",
+            result.poi_way_bundles.len()
+        );
+        Ok(result)
+    }
+
+    // Reads and decodes the block at `column` and `row` of the sub-file, whose
+    // index entry is `current_block_index_entry`. Blocks that cannot be read
+    // are logged and skipped as None, errors end the query.
+    // The pointers to the start of block `block_number` and to its end, the
+    // start of the next block or the end of the sub-file for the last one
+    fn block_span(
+        &mut self,
+        sub_file_parameter: &SubFileParameter,
+        block_number: i64,
+    ) -> Result<(u64, u64)> {
+        let block_index_entry = self
+            .database_index_cache
+            .as_mut()
+            .ok_or_else(|| MapFileException::new("Missing index cache"))?
+            .get_index_entry(sub_file_parameter, block_number)?;
+        self.block_span_from(sub_file_parameter, block_number, block_index_entry)
+    }
+
+    // `block_span` for a block whose index entry was already looked up
+    fn block_span_from(
+        &mut self,
+        sub_file_parameter: &SubFileParameter,
+        block_number: i64,
+        block_index_entry: i64,
+    ) -> Result<(u64, u64)> {
+        let block_pointer = block_index_entry & BITMASK_INDEX_OFFSET;
+        let next_block_pointer = if block_number + 1 == sub_file_parameter.number_of_blocks {
+            sub_file_parameter.sub_file_size
+        } else {
+            self.database_index_cache
+                .as_mut()
+                .ok_or_else(|| MapFileException::new("Missing index cache"))?
+                .get_index_entry(sub_file_parameter, block_number + 1)?
+                & BITMASK_INDEX_OFFSET
+        };
+        for pointer in [block_pointer, next_block_pointer] {
+            if pointer > sub_file_parameter.sub_file_size {
+                return Err(MapFileException::new(format!(
+                    "block pointer > sub_file_size: {} > {}",
+                    pointer, sub_file_parameter.sub_file_size
+                )));
+            }
+        }
+        Ok((block_pointer as u64, next_block_pointer as u64))
+    }
+
+    fn read_block_at(
+        &mut self,
+        context: &mut QueryContext,
+        (column, row): (i64, i64),
+        current_block_index_entry: i64,
+    ) -> Result<Option<PoiWayBundle>> {
+        let sub_file_parameter = &context.sub_file_parameter;
+        let block_number = sub_file_parameter.block_number(row, column)?;
+        // Skip blocks with invalid pointers, but log it
+        let (current_block_pointer, next_block_pointer) =
+            match self.block_span_from(sub_file_parameter, block_number, current_block_index_entry)
+            {
+                Ok(span) => span,
+                Err(e) => {
+                    self.observer
+                        .on_warning(&format!("Skipping block {}: {}", block_number, e));
+                    return Ok(None);
                 }
-                if current_block_pointer > sub_file_parameter.sub_file_size {
-                    warn!(
-                        "Skipping block with pointer > sub_file_size: {} > {}",
-                        current_block_pointer, sub_file_parameter.sub_file_size
-                    );
-                    continue;
+            };
+        trace!("Block pointer: {}", current_block_pointer);
+        if current_block_pointer == 0 {
+            self.observer.on_warning("Skipping block with zero pointer");
+            return Ok(None);
+        }
+
+        // Calculate block size, a next pointer below this one wraps to a size
+        // the buffer size check rejects
+        let current_block_size = next_block_pointer.wrapping_sub(current_block_pointer) as usize;
+        trace!("Block size: {}", current_block_size);
+        if current_block_size == 0 {
+            self.observer.on_warning("Skipping block with zero size");
+            return Ok(None);
+        }
+
+        // Read and process block
+        let file_position = sub_file_parameter.start_address as u64 + current_block_pointer;
+        trace!("Reading from file position: {}", file_position);
+        check_buffer_size(current_block_size, self.options.maximum_buffer_size)?;
+        let block = match self.file.read_block(file_position, current_block_size) {
+            Ok(block) => block,
+            Err(e) => {
+                match e.kind() {
+                    MapFileErrorKind::UnexpectedEof => self
+                        .observer
+                        .on_warning(&format!("Block extends past the end of the file: {}", e)),
+                    _ => self
+                        .observer
+                        .on_warning(&format!("Error reading from file: {}", e)),
                 }
+                return Ok(None);
+            }
+        };
+        let mut read_buffer = ReadBuffer::from_bytes(block);
+
+        let tile_latitude = MercatorProjection::tile_y_to_latitude(
+            sub_file_parameter.boundary_tile_top + row,
+            sub_file_parameter.base_zoom_level,
+        );
+        let tile_longitude = MercatorProjection::tile_x_to_longitude(
+            sub_file_parameter.boundary_tile_left + column,
+            sub_file_parameter.base_zoom_level,
+        );
+
+        trace!(
+            "Processing block at tile coordinates: lat={}, lon={}",
+            tile_latitude,
+            tile_longitude
+        );
+        match self.process_block(context, tile_latitude, tile_longitude, &mut read_buffer) {
+            Ok(Some(mut bundle)) => {
+                self.observer.on_bundle(&bundle);
+                bundle.block = Some((column, row));
+                Ok(Some(bundle))
+            }
+            Ok(None) => {
+                trace!("No bundle found for this block");
+                Ok(None)
+            }
+            // A block that fails to decode is dropped on its own, the other
+            // blocks of the query are still read
+            Err(e) => {
+                self.observer.on_warning(&format!(
+                    "Error processing block {} at row {} column {}: {}",
+                    block_number, row, column, e
+                ));
+                Ok(None)
+            }
+        }
+    }
+
+    pub fn read_map_data(&mut self, tile: &Tile) -> Result<MapReadResult> {
+        self.read_map_data_impl(tile, tile, Selector::All, &mut |_, _| {})
+    }
+
+    /// Reads the blocks covering `tile` with the data of `data_zoom` instead
+    /// of the tile's own zoom level, e.g. to overzoom a zoom-18 tile from
+    /// zoom-14 data. Features are returned as a query at `data_zoom` would
+    /// return them.
+    pub fn read_map_data_at_zoom(&mut self, tile: &Tile, data_zoom: u8) -> Result<MapReadResult> {
+        if !self.covers(tile) {
+            return Ok(MapReadResult::new());
+        }
+
+        let query_zoom_level = self.query_zoom_level(data_zoom)? as i32;
+        let sub_file_parameter = self.sub_file_for(query_zoom_level)?;
+
+        self.read_sub_file_impl(
+            tile,
+            tile,
+            query_zoom_level,
+            &sub_file_parameter,
+            Selector::All,
+            &mut |_, _| {},
+        )
+    }
+
+    /// Reads `new` after `prev` was read into `prev_result`, decoding only the
+    /// blocks the previous query did not cover and reusing the bundles of the
+    /// others. Falls back to a full read if the tiles are at different zoom
+    /// levels or above the base zoom level, where blocks are filtered per
+    /// tile.
+    pub fn read_map_data_incremental(
+        &mut self,
+        prev: &Tile,
+        prev_result: &MapReadResult,
+        new: &Tile,
+    ) -> Result<MapReadResult> {
+        let query_parameters = self.explain_query(new)?;
+        let sub_file_parameter = self.sub_file_for(query_parameters.query_zoom_level)?;
+        // Clipped ways only fit the tile they were read for
+        if prev.zoom_level != new.zoom_level
+            || self.options.clip_to_tile
+            || query_parameters.query_zoom_level > sub_file_parameter.base_zoom_level as i32
+            || !covers_base_tile(
+                &sub_file_parameter,
+                query_parameters.from_base_tile_x,
+                query_parameters.from_base_tile_y,
+            )
+        {
+            return self.read_map_data(new);
+        }
+
+        // The blocks covered by both queries
+        let prev_parameters = self.explain_query(prev)?;
+        let from_x = query_parameters
+            .from_block_x
+            .max(prev_parameters.from_block_x);
+        let to_x = query_parameters.to_block_x.min(prev_parameters.to_block_x);
+        let from_y = query_parameters
+            .from_block_y
+            .max(prev_parameters.from_block_y);
+        let to_y = query_parameters.to_block_y.min(prev_parameters.to_block_y);
+        if from_x > to_x || from_y > to_y {
+            return self.read_map_data(new);
+        }
 
-                // Get next block pointer
-                let next_block_pointer = if block_number + 1 == sub_file_parameter.number_of_blocks
+        // Water like a full read: every index entry of the range carries the
+        // water bit, taken from the index for the overlap and merged in from
+        // the remainders below
+        let mut overlap_is_water = true;
+        let mut overlap_read_water_info = false;
+        for row in from_y..=to_y {
+            for column in from_x..=to_x {
+                let block_number = sub_file_parameter.block_number(row, column)?;
+                match self
+                    .database_index_cache
+                    .as_mut()
+                    .ok_or_else(|| MapFileException::new("Missing index cache"))?
+                    .get_index_entry(&sub_file_parameter, block_number)
                 {
-                    sub_file_parameter.sub_file_size
-                } else {
-                    match self
-                        .database_index_cache
-                        .as_mut()
-                        .unwrap()
-                        .get_index_entry(&sub_file_parameter, block_number + 1)
-                    {
-                        Ok(next_entry) => {
-                            let next_ptr = next_entry & BITMASK_INDEX_OFFSET;
-                            if next_ptr > sub_file_parameter.sub_file_size {
-                                warn!(
-                                    "Next block pointer > sub_file_size: {} > {}",
-                                    next_ptr, sub_file_parameter.sub_file_size
-                                );
-                                continue; // Skip if next pointer is invalid
-                            }
-                            next_ptr
-                        }
-                        Err(e) => {
-                            warn!("Error getting next index entry: {}", e);
-                            continue;
-                        }
+                    Ok(entry) => {
+                        overlap_is_water &= (entry & BITMASK_INDEX_WATER) != 0;
+                        overlap_read_water_info = true;
                     }
-                };
+                    Err(e) => self
+                        .observer
+                        .on_warning(&format!("Error getting index entry: {}", e)),
+                }
+            }
+        }
+
+        let mut result = MapReadResult::new();
+        result.is_water = overlap_is_water && overlap_read_water_info;
+        result.poi_way_bundles = prev_result
+            .poi_way_bundles
+            .iter()
+            .filter(|bundle| {
+                bundle.block.is_some_and(|(column, row)| {
+                    (from_x..=to_x).contains(&column) && (from_y..=to_y).contains(&row)
+                })
+            })
+            .cloned()
+            .collect();
+
+        // The rest of the new range: the rows above and below the overlap, then
+        // the columns left and right of it
+        let remainders = [
+            (
+                query_parameters.from_block_x,
+                query_parameters.to_block_x,
+                query_parameters.from_block_y,
+                from_y - 1,
+            ),
+            (
+                query_parameters.from_block_x,
+                query_parameters.to_block_x,
+                to_y + 1,
+                query_parameters.to_block_y,
+            ),
+            (query_parameters.from_block_x, from_x - 1, from_y, to_y),
+            (to_x + 1, query_parameters.to_block_x, from_y, to_y),
+        ];
+        let bounding_box = new.get_bounding_box();
+        for (left, right, top, bottom) in remainders {
+            if left > right || top > bottom {
+                continue;
+            }
+            let mut part_parameters = query_parameters.clone();
+            part_parameters.from_block_x = left;
+            part_parameters.to_block_x = right;
+            part_parameters.from_block_y = top;
+            part_parameters.to_block_y = bottom;
+            let mut context = QueryContext::new(
+                part_parameters,
+                sub_file_parameter.clone(),
+                bounding_box.clone(),
+                Selector::All,
+            );
+            result.merge(self.process_blocks(&mut context, &mut |_, _| {})?);
+        }
+
+        // Restore the top to bottom, left to right order of a full read
+        result
+            .poi_way_bundles
+            .sort_by_key(|bundle| bundle.block.map(|(column, row)| (row, column)));
+        Ok(result)
+    }
+
+    /// Reads the tile one block at a time. The iterator yields the bundle of
+    /// each block holding features, in the order of `read_map_data`, and can
+    /// be dropped at any point to stop reading.
+    pub fn read_iter(&mut self, tile: &Tile) -> Result<BlockIterator<'_>> {
+        let query_zoom_level = self.query_zoom_level(tile.zoom_level)? as i32;
+        let sub_file_parameter = self.sub_file_for(query_zoom_level)?;
+        let query_parameters = Self::plan_query(tile, tile, query_zoom_level, &sub_file_parameter);
+
+        let next_block = query_parameters
+            .as_ref()
+            .map(|query_parameters| (query_parameters.from_block_x, query_parameters.from_block_y));
+        if let Some(query_parameters) = &query_parameters {
+            self.prefetch_query(&sub_file_parameter, query_parameters)?;
+        }
+
+        Ok(BlockIterator {
+            map_file: self,
+            context: QueryContext::new(
+                query_parameters.unwrap_or_else(QueryParameters::new),
+                sub_file_parameter,
+                tile.get_bounding_box(),
+                Selector::All,
+            ),
+            next_block,
+        })
+    }
+
+    pub fn read_poi_data(&mut self, tile: &Tile) -> Result<MapReadResult> {
+        self.read_map_data_impl(tile, tile, Selector::Pois, &mut |_, _| {})
+    }
+
+    /// Reads only the ways of the tile, without decoding any POIs.
+    pub fn read_way_data(&mut self, tile: &Tile) -> Result<MapReadResult> {
+        self.read_map_data_impl(tile, tile, Selector::Ways, &mut |_, _| {})
+    }
+
+    pub fn read_named_items(&mut self, tile: &Tile) -> Result<MapReadResult> {
+        self.read_map_data_impl(tile, tile, Selector::Named, &mut |_, _| {})
+    }
+
+    /// The upper left and lower right tiles at `zoom` covering a viewport of
+    /// `width_px` by `height_px` pixels centered on `center`.
+    pub fn viewport_tiles(
+        &self,
+        center: &LatLong,
+        zoom: u8,
+        width_px: u32,
+        height_px: u32,
+    ) -> (Tile, Tile) {
+        let tile_size = self.tile_size();
+        let center_x =
+            MercatorProjection::longitude_to_pixel_x_with_size(center.longitude, zoom, tile_size);
+        let center_y =
+            MercatorProjection::latitude_to_pixel_y_with_size(center.latitude, zoom, tile_size);
+        let half_width = width_px as f64 / 2.0;
+        let half_height = height_px as f64 / 2.0;
+
+        // The viewport spans [left, right) by [top, bottom) in pixels
+        let max_tile = MercatorProjection::tile_count(zoom) - 1;
+        let to_tile = |pixel: f64| ((pixel / tile_size as f64).floor() as i64).clamp(0, max_tile);
+        let last_tile =
+            |pixel: f64| ((pixel / tile_size as f64).ceil() as i64 - 1).clamp(0, max_tile);
+        let upper_left = Tile::new(
+            to_tile(center_x - half_width),
+            to_tile(center_y - half_height),
+            zoom,
+            tile_size,
+        );
+        let lower_right = Tile::new(
+            last_tile(center_x + half_width),
+            last_tile(center_y + half_height),
+            zoom,
+            tile_size,
+        );
+        (upper_left, lower_right)
+    }
+
+    /// Reads everything visible in a viewport of `width_px` by `height_px`
+    /// pixels centered on `center`. The covering tiles are read as one range,
+    /// so each block is decoded once.
+    pub fn read_viewport(
+        &mut self,
+        center: &LatLong,
+        zoom: u8,
+        width_px: u32,
+        height_px: u32,
+    ) -> Result<MapReadResult> {
+        let (upper_left, lower_right) = self.viewport_tiles(center, zoom, width_px, height_px);
+        self.read_map_data_impl(&upper_left, &lower_right, Selector::All, &mut |_, _| {})
+    }
+
+    /// Returns the area ways of the tile at `zoom` around `point` that contain
+    /// it, smallest (most specific) first.
+    pub fn areas_containing(&mut self, point: &LatLong, zoom: u8) -> Result<Vec<Way>> {
+        let tile = self.make_tile(point.latitude, point.longitude, zoom);
+
+        let mut areas: Vec<Way> = self
+            .read_map_data(&tile)?
+            .into_ways()
+            .filter(|way| way.is_area() && way.contains_point(point))
+            .collect();
+        areas.sort_by(|a, b| a.area().total_cmp(&b.area()));
+        Ok(areas)
+    }
+
+    /// How often each `key=value` tag occurs on the POIs and ways of the tile.
+    /// The map header lists the tags but not their frequencies.
+    pub fn tag_histogram(&mut self, tile: &Tile) -> Result<HashMap<String, usize>> {
+        let result = self.read_map_data(tile)?;
+        let mut histogram = HashMap::new();
+        let poi_tags = result.pois().flat_map(|poi| &poi.tags);
+        for tag in poi_tags.chain(result.ways().flat_map(|way| &way.tags)) {
+            *histogram
+                .entry(format!("{}={}", tag.key, tag.value))
+                .or_insert(0) += 1;
+        }
+        Ok(histogram)
+    }
+
+    /// Returns up to `limit` POIs closest to `point`, nearest first. Searches
+    /// the tile at `zoom` around the point and its eight neighbors.
+    pub fn nearest_pois(
+        &mut self,
+        point: &LatLong,
+        zoom: u8,
+        limit: usize,
+    ) -> Result<Vec<PointOfInterest>> {
+        let max_tile = (1i64 << zoom) - 1;
+        let tile_x = MercatorProjection::longitude_to_tile_x(point.longitude, zoom);
+        let tile_y = MercatorProjection::latitude_to_tile_y(point.latitude, zoom);
+        let tile_size = self.tile_size();
+        let upper_left = Tile::new((tile_x - 1).max(0), (tile_y - 1).max(0), zoom, tile_size);
+        let lower_right = Tile::new(
+            (tile_x + 1).min(max_tile),
+            (tile_y + 1).min(max_tile),
+            zoom,
+            tile_size,
+        );
+
+        let mut pois: Vec<(f64, PointOfInterest)> = self
+            .read_map_data_impl(&upper_left, &lower_right, Selector::Pois, &mut |_, _| {})?
+            .into_pois()
+            .map(|poi| (poi.position.distance_to(point), poi))
+            .collect();
+        pois.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(pois.into_iter().take(limit).map(|(_, poi)| poi).collect())
+    }
+
+    /// Every POI of the map matching `filter`, e.g. for building a search
+    /// index. This decodes the POIs of every block of the most detailed
+    /// sub-file, so it costs about as much as reading the whole map.
+    pub fn scan_pois(&mut self, filter: &PoiFilter) -> Result<Vec<PointOfInterest>> {
+        // The sub-file of the highest zoom levels holds every POI
+        let query_zoom_level = self.header.get_query_zoom_level(u8::MAX);
+        let Some(sub_file_parameter) = self
+            .header
+            .get_sub_file_parameter(query_zoom_level as usize)
+            .cloned()
+        else {
+            return Ok(Vec::new());
+        };
+        let bounding_box = self
+            .get_map_file_info()
+            .ok_or_else(|| MapFileException::new("Missing map file info"))?
+            .bounding_box
+            .clone();
 
-                // Calculate block size
-                let current_block_size = (next_block_pointer - current_block_pointer) as usize;
-                info!("Block size: {}", current_block_size);
-                if current_block_size == 0 {
-                    warn!("Skipping block with zero size");
+        let tile_size = self.tile_size();
+        let base_zoom_level = sub_file_parameter.base_zoom_level;
+        let upper_left = Tile::new(
+            sub_file_parameter.boundary_tile_left,
+            sub_file_parameter.boundary_tile_top,
+            base_zoom_level,
+            tile_size,
+        );
+        let lower_right = Tile::new(
+            sub_file_parameter.boundary_tile_right,
+            sub_file_parameter.boundary_tile_bottom,
+            base_zoom_level,
+            tile_size,
+        );
+        let Some(mut query_parameters) = Self::plan_query(
+            &upper_left,
+            &lower_right,
+            base_zoom_level as i32,
+            &sub_file_parameter,
+        ) else {
+            return Ok(Vec::new());
+        };
+        // Include the POIs that only show up above the base zoom level
+        query_parameters.query_zoom_level = sub_file_parameter.zoom_level_max as i32;
+
+        debug!("Scanning all POIs of the map");
+        let mut context = QueryContext::new(
+            query_parameters,
+            sub_file_parameter,
+            bounding_box,
+            Selector::Pois,
+        );
+        context.poi_filter = Some(filter.clone());
+        Ok(self
+            .process_blocks(&mut context, &mut |_, _| {})?
+            .into_pois()
+            .collect())
+    }
+
+    /// Reads `center` and its eight neighbors, keyed by `(tile_x, tile_y)`.
+    /// Neighbors past the poles are left out, and at the lowest zoom levels
+    /// tiles wrapping onto each other are read once.
+    pub fn read_neighborhood(
+        &mut self,
+        center: &Tile,
+    ) -> Result<HashMap<(i64, i64), MapReadResult>> {
+        let mut results = HashMap::with_capacity(9);
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let Some(tile) = center.neighbor(dx, dy) else {
                     continue;
+                };
+                if let Entry::Vacant(entry) = results.entry((tile.tile_x, tile.tile_y)) {
+                    entry.insert(self.read_map_data(&tile)?);
                 }
+            }
+        }
+        Ok(results)
+    }
+
+    /// Reads a batch of tiles, keyed by `(tile_x, tile_y, zoom_level)`. Tiles
+    /// are grouped by sub-file and the index entries of each group are loaded
+    /// before the first read, so neighboring tiles share a warm index cache.
+    pub fn read_tiles(&mut self, tiles: &[Tile]) -> Result<HashMap<(i64, i64, u8), MapReadResult>> {
+        let mut groups: BTreeMap<u8, Vec<&Tile>> = BTreeMap::new();
+        for tile in tiles {
+            groups
+                .entry(self.query_zoom_level(tile.zoom_level)?)
+                .or_default()
+                .push(tile);
+        }
 
-                // Read and process block
-                let mut read_buffer = match ReadBuffer::new(self.file.try_clone()?) {
-                    read_buffer => read_buffer,
+        let mut results = HashMap::with_capacity(tiles.len());
+        for (query_zoom_level, group) in groups {
+            let sub_file_parameter = self.sub_file_for(query_zoom_level as i32)?;
+
+            // The blocks of all tiles of the group, prefetched at once
+            let mut group_parameters: Option<QueryParameters> = None;
+            for tile in &group {
+                let Some(query_parameters) =
+                    Self::plan_query(tile, tile, query_zoom_level as i32, &sub_file_parameter)
+                else {
+                    continue;
                 };
+                if let Some(group_parameters) = &mut group_parameters {
+                    group_parameters.from_block_x = group_parameters
+                        .from_block_x
+                        .min(query_parameters.from_block_x);
+                    group_parameters.from_block_y = group_parameters
+                        .from_block_y
+                        .min(query_parameters.from_block_y);
+                    group_parameters.to_block_x =
+                        group_parameters.to_block_x.max(query_parameters.to_block_x);
+                    group_parameters.to_block_y =
+                        group_parameters.to_block_y.max(query_parameters.to_block_y);
+                } else {
+                    group_parameters = Some(query_parameters);
+                }
+            }
+            if let Some(group_parameters) = &group_parameters {
+                self.prefetch_query(&sub_file_parameter, group_parameters)?;
+            }
 
-                let file_position =
-                    (sub_file_parameter.start_address + current_block_pointer) as u64;
-                info!("Reading from file position: {}", file_position);
-                match read_buffer.read_from_file_at_offset(file_position, current_block_size) {
-                    Ok(success) => {
-                        if !success {
-                            warn!("Failed to read from file");
-                            continue;
-                        }
-                    }
-                    Err(e) => {
-                        warn!("Error reading from file: {}", e);
-                        continue;
-                    }
+            for tile in group {
+                if let Entry::Vacant(entry) =
+                    results.entry((tile.tile_x, tile.tile_y, tile.zoom_level))
+                {
+                    entry.insert(self.read_map_data(tile)?);
                 }
+            }
+        }
+        Ok(results)
+    }
 
-                let tile_latitude = MercatorProjection::tile_y_to_latitude(
-                    sub_file_parameter.boundary_tile_top + row,
-                    sub_file_parameter.base_zoom_level,
-                );
-                let tile_longitude = MercatorProjection::tile_x_to_longitude(
-                    sub_file_parameter.boundary_tile_left + column,
-                    sub_file_parameter.base_zoom_level,
-                );
-
-                info!(
-                    "Processing block at tile coordinates: lat={}, lon={}",
-                    tile_latitude, tile_longitude
-                );
-                match self.process_block(
-                    query_parameters,
-                    sub_file_parameter,
-                    bounding_box,
-                    tile_latitude,
-                    tile_longitude,
-                    selector,
-                    &mut read_buffer,
-                ) {
-                    Ok(Some(bundle)) => {
-                        info!(
-                            "Found bundle with {} POIs and {} ways",
-                            bundle.pois.len(),
-                            bundle.ways.len()
-                        );
-                        result.poi_way_bundles.push(bundle);
-                    }
-                    Ok(None) => {
-                        info!("No bundle found for this block");
-                    }
-                    Err(e) => {
-                        warn!("Error processing block: {}", e);
-                        continue;
-                    }
+    /// The base tile and block ranges a query for `tile` resolves to, without
+    /// reading any blocks. Useful to see why a query comes back empty. Above
+    /// the base zoom level `query_tile_bitmask` holds the covered sub-tiles,
+    /// see `QueryCalculations::bitmask_popcount`.
+    pub fn explain_query(&self, tile: &Tile) -> Result<QueryParameters> {
+        let query_zoom_level = self.query_zoom_level(tile.zoom_level)?;
+        let sub_file_parameter = self.sub_file_for(query_zoom_level as i32)?;
+
+        let mut query_parameters = QueryParameters::new();
+        query_parameters.query_zoom_level = query_zoom_level as i32;
+        query_parameters.calculate_base_tiles(tile, tile, &sub_file_parameter);
+        query_parameters.calculate_blocks(&sub_file_parameter);
+        Ok(query_parameters)
+    }
+
+    /// The water flags of the index entries of all blocks covering the tile
+    /// range, row by row from the top-left, without reading any block. Empty
+    /// if the range lies outside the map.
+    pub fn water_grid(&mut self, upper_left: &Tile, lower_right: &Tile) -> Result<Vec<Vec<bool>>> {
+        let query_zoom_level = self.query_zoom_level(upper_left.zoom_level)? as i32;
+        let sub_file_parameter = self.sub_file_for(query_zoom_level)?;
+        let Some(query_parameters) = Self::plan_query(
+            upper_left,
+            lower_right,
+            query_zoom_level,
+            &sub_file_parameter,
+        ) else {
+            return Ok(Vec::new());
+        };
+
+        self.prefetch_query(&sub_file_parameter, &query_parameters)?;
+
+        let index_cache = self
+            .database_index_cache
+            .as_mut()
+            .ok_or_else(|| MapFileException::new("Missing index cache"))?;
+        (query_parameters.from_block_y..=query_parameters.to_block_y)
+            .map(|row| {
+                (query_parameters.from_block_x..=query_parameters.to_block_x)
+                    .map(|column| {
+                        let block_number = sub_file_parameter.block_number(row, column)?;
+                        let entry =
+                            index_cache.get_index_entry(&sub_file_parameter, block_number)?;
+                        Ok(entry & BITMASK_INDEX_WATER != 0)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The number of bytes in the blocks `read_map_data` would decode for the
+    /// tile, taken from the index without reading any block. A rough measure
+    /// of the work a read takes.
+    pub fn estimate_cost(&mut self, tile: &Tile) -> Result<usize> {
+        if !self.covers(tile) {
+            return Ok(0);
+        }
+        let query_zoom_level = self.query_zoom_level(tile.zoom_level)? as i32;
+        let sub_file_parameter = self.sub_file_for(query_zoom_level)?;
+        let Some(query_parameters) =
+            Self::plan_query(tile, tile, query_zoom_level, &sub_file_parameter)
+        else {
+            return Ok(0);
+        };
+
+        self.prefetch_query(&sub_file_parameter, &query_parameters)?;
+
+        let mut cost = 0;
+        for row in query_parameters.from_block_y..=query_parameters.to_block_y {
+            for column in query_parameters.from_block_x..=query_parameters.to_block_x {
+                let block_number = sub_file_parameter.block_number(row, column)?;
+                // Blocks with invalid pointers are skipped by the read as well
+                let Ok((block_pointer, next_block_pointer)) =
+                    self.block_span(&sub_file_parameter, block_number)
+                else {
+                    continue;
+                };
+                if block_pointer != 0 && next_block_pointer > block_pointer {
+                    cost += (next_block_pointer - block_pointer) as usize;
                 }
             }
         }
+        Ok(cost)
+    }
 
-        if query_is_water && query_read_water_info {
-            result.is_water = true;
+    /// Reads the first block covering the tile and checks that it starts with
+    /// the debug block signature and that its first POI and first way carry
+    /// theirs. Returns false without reading anything if the map is not a
+    /// debug file.
+    pub fn check_debug_signatures(&mut self, tile: &Tile) -> Result<bool> {
+        if !self.get_map_file_info().is_some_and(|info| info.debug_file) {
+            return Ok(false);
         }
 
-        info!(
-            "Processed all blocks, found {} bundles",
-            result.poi_way_bundles.len()
+        let query_parameters = self.explain_query(tile)?;
+        let block_number = self
+            .sub_file_for(query_parameters.query_zoom_level)?
+            .block_number(query_parameters.from_block_y, query_parameters.from_block_x)?;
+        let (sub_file_parameter, read_buffer) = self.read_block_by_number(tile, block_number)?;
+        let Some(mut read_buffer) = read_buffer else {
+            return Ok(true);
+        };
+        self.process_block_signature(&mut read_buffer)?;
+        let zoom_table = self.read_zoom_table(&sub_file_parameter, &mut read_buffer)?;
+        let [number_of_pois, number_of_ways] = zoom_table.last().copied().unwrap_or([0, 0]);
+
+        let first_way_offset = read_buffer.read_unsigned_int()? as usize;
+        let first_way_offset = first_way_offset + read_buffer.get_buffer_position();
+        if number_of_pois > 0 {
+            Self::check_poi_signature(&mut read_buffer)?;
+        }
+        if number_of_ways > 0 {
+            if first_way_offset > read_buffer.get_buffer_size() {
+                return Err(MapFileException::new(format!(
+                    "{}{}",
+                    INVALID_FIRST_WAY_OFFSET, first_way_offset
+                )));
+            }
+            read_buffer.set_buffer_position(first_way_offset);
+            Self::check_way_signature(&mut read_buffer)?;
+        }
+        Ok(true)
+    }
+
+    /// Reads the tile at the map's start zoom level containing its start
+    /// position, which falls back to the center of the bounding box.
+    pub fn read_start_tile(&mut self) -> Result<(Tile, MapReadResult)> {
+        let position = self.start_position();
+        let zoom = self.start_zoom_level();
+        let tile = self.make_tile(position.latitude, position.longitude, zoom);
+        let result = self.read_map_data(&tile)?;
+        Ok((tile, result))
+    }
+
+    /// Reads the named POIs and ways of the tile with their label anchors.
+    pub fn read_labels(&mut self, tile: &Tile) -> Result<Vec<NamedItem>> {
+        Ok(self.read_named_items(tile)?.labels())
+    }
+
+    /// Reads all map data for the tile with coordinates already converted to
+    /// pixels relative to the tile's top-left corner.
+    pub fn read_map_data_projected(&mut self, tile: &Tile) -> Result<ProjectedMapResult> {
+        let map_read_result = self.read_map_data(tile)?;
+        Ok(ProjectedMapResult::new(&map_read_result, tile))
+    }
+
+    /// Reads all map data for the tile from the sub-file with the given base
+    /// zoom level, instead of the one the tile's zoom level would select. The
+    /// query zoom level is clamped to the zoom range of that sub-file.
+    pub fn read_sub_file(&mut self, base_zoom: u8, tile: &Tile) -> Result<MapReadResult> {
+        let sub_file_parameter = self
+            .header
+            .get_sub_file_parameter_by_base_zoom(base_zoom)
+            .ok_or_else(|| {
+                MapFileException::new(format!("no sub-file with base zoom level: {}", base_zoom))
+            })?
+            .clone();
+        let query_zoom_level = tile.zoom_level.clamp(
+            sub_file_parameter.zoom_level_min,
+            sub_file_parameter.zoom_level_max,
+        ) as i32;
+
+        self.read_sub_file_impl(
+            tile,
+            tile,
+            query_zoom_level,
+            &sub_file_parameter,
+            Selector::All,
+            &mut |_, _| {},
+        )
+    }
+
+    /// Decodes a single block of `length` bytes at `offset` in `source`, using
+    /// the tag tables of this map. `tile` is the block's tile at the base zoom
+    /// level of its sub-file; the features of the base zoom level are returned.
+    pub fn decode_block(
+        &self,
+        source: &mut dyn BlockSource,
+        offset: u64,
+        length: usize,
+        tile: &Tile,
+    ) -> Result<PoiWayBundle> {
+        let sub_file_parameter = self
+            .header
+            .get_sub_file_parameter_by_base_zoom(tile.zoom_level)
+            .ok_or_else(|| {
+                MapFileException::new(format!(
+                    "no sub-file with base zoom level: {}",
+                    tile.zoom_level
+                ))
+            })?;
+
+        let mut query_parameters = QueryParameters::new();
+        query_parameters.query_zoom_level = tile.zoom_level as i32;
+
+        check_buffer_size(length, self.options.maximum_buffer_size)?;
+        let mut read_buffer = ReadBuffer::from_bytes(source.read_block(offset, length)?);
+        let mut context = QueryContext::new(
+            query_parameters,
+            sub_file_parameter.clone(),
+            tile.get_bounding_box(),
+            Selector::All,
         );
-        Ok(result)
+        let bundle = self.process_block(
+            &mut context,
+            MercatorProjection::tile_y_to_latitude(tile.tile_y, tile.zoom_level),
+            MercatorProjection::tile_x_to_longitude(tile.tile_x, tile.zoom_level),
+            &mut read_buffer,
+        )?;
+        Ok(bundle.unwrap_or_default())
+    }
+
+    /// Reads all map data for the tile, calling `progress` with the number of
+    /// blocks done and the total number of blocks as the read advances.
+    pub fn read_map_data_with_progress(
+        &mut self,
+        tile: &Tile,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<MapReadResult> {
+        self.read_map_data_impl(tile, tile, Selector::All, progress)
     }
 
-    pub fn read_map_data(&mut self, tile: &Tile) -> Result<MapReadResult, MapFileException> {
-        self.read_map_data_impl(tile, tile, Selector::All)
+    /// Reads all map data for the tile range from `upper_left` to `lower_right`.
+    /// `upper_left.tile_x` may be greater than `lower_right.tile_x` for ranges
+    /// that cross the antimeridian.
+    pub fn read_map_data_range(
+        &mut self,
+        upper_left: &Tile,
+        lower_right: &Tile,
+    ) -> Result<MapReadResult> {
+        self.read_map_data_impl(upper_left, lower_right, Selector::All, &mut |_, _| {})
+    }
+
+    /// The cumulative POI and way counts per zoom level of the first block
+    /// covering the tile. Empty for an empty block.
+    #[cfg(feature = "debug")]
+    pub fn dump_zoom_table(&mut self, tile: &Tile) -> Result<Vec<ZoomRow>> {
+        let query_parameters = self.explain_query(tile)?;
+        let sub_file_parameter = self.sub_file_for(query_parameters.query_zoom_level)?;
+        let block_number = sub_file_parameter
+            .block_number(query_parameters.from_block_y, query_parameters.from_block_x)?;
+
+        let (_, read_buffer) = self.read_block_by_number(tile, block_number)?;
+        let Some(mut read_buffer) = read_buffer else {
+            return Ok(Vec::new());
+        };
+        self.process_block_signature(&mut read_buffer)?;
+        let zoom_table = self.read_zoom_table(&sub_file_parameter, &mut read_buffer)?;
+
+        Ok(zoom_table
+            .iter()
+            .zip(sub_file_parameter.zoom_level_min..)
+            .map(|(&[pois, ways], zoom)| ZoomRow {
+                zoom,
+                cumulative_pois: pois as u32,
+                cumulative_ways: ways as u32,
+            })
+            .collect())
     }
 
-    pub fn read_poi_data(&mut self, tile: &Tile) -> Result<MapReadResult, MapFileException> {
-        self.read_map_data_impl(tile, tile, Selector::Pois)
+    // Reads block `block_number` of the sub-file selected by the tile's zoom
+    // level, None if the block is empty
+    fn read_block_by_number(
+        &mut self,
+        tile: &Tile,
+        block_number: i64,
+    ) -> Result<(SubFileParameter, Option<BlockBuffer>)> {
+        let query_zoom_level = self.query_zoom_level(tile.zoom_level)? as i32;
+        let sub_file_parameter = self.sub_file_for(query_zoom_level)?;
+        if block_number < 0 || block_number >= sub_file_parameter.number_of_blocks {
+            return Err(MapFileException::new(format!(
+                "invalid block number: {}",
+                block_number
+            )));
+        }
+
+        let (block_pointer, next_block_pointer) =
+            self.block_span(&sub_file_parameter, block_number)?;
+        if block_pointer == 0 || next_block_pointer < block_pointer {
+            return Err(MapFileException::new(format!(
+                "invalid block pointer: {}",
+                block_pointer
+            )));
+        }
+        if next_block_pointer == block_pointer {
+            return Ok((sub_file_parameter, None));
+        }
+
+        let block_size = (next_block_pointer - block_pointer) as usize;
+        check_buffer_size(block_size, self.options.maximum_buffer_size)?;
+        let read_buffer = ReadBuffer::from_bytes(self.file.read_block(
+            sub_file_parameter.start_address as u64 + block_pointer,
+            block_size,
+        )?);
+        Ok((sub_file_parameter, Some(read_buffer)))
     }
 
-    pub fn read_named_items(&mut self, tile: &Tile) -> Result<MapReadResult, MapFileException> {
-        self.read_map_data_impl(tile, tile, Selector::Named)
+    /// Decodes block `block_number` of the sub-file selected by the tile's
+    /// zoom level with no filtering: all POIs and ways of the last zoom table
+    /// row, together with each way's tile bitmask and data size.
+    #[cfg(feature = "debug")]
+    pub fn read_raw_block(&mut self, tile: &Tile, block_number: i64) -> Result<RawBlock> {
+        let (sub_file_parameter, read_buffer) = self.read_block_by_number(tile, block_number)?;
+        let Some(mut read_buffer) = read_buffer else {
+            return Ok(RawBlock::default());
+        };
+
+        let row = block_number / sub_file_parameter.blocks_width;
+        let column = block_number % sub_file_parameter.blocks_width;
+        let tile_latitude = MercatorProjection::tile_y_to_latitude(
+            sub_file_parameter.boundary_tile_top + row,
+            sub_file_parameter.base_zoom_level,
+        );
+        let tile_longitude = MercatorProjection::tile_x_to_longitude(
+            sub_file_parameter.boundary_tile_left + column,
+            sub_file_parameter.base_zoom_level,
+        );
+
+        self.process_block_signature(&mut read_buffer)?;
+        let zoom_table = self.read_zoom_table(&sub_file_parameter, &mut read_buffer)?;
+        let [number_of_pois, number_of_ways] = zoom_table.last().copied().unwrap_or([0, 0]);
+
+        let first_way_offset = read_buffer.read_unsigned_int()? as usize;
+        let first_way_offset = first_way_offset + read_buffer.get_buffer_position();
+        if first_way_offset > read_buffer.get_buffer_size() {
+            return Err(MapFileException::new(format!(
+                "{}{}",
+                INVALID_FIRST_WAY_OFFSET, first_way_offset
+            )));
+        }
+
+        let mut context = QueryContext::new(
+            QueryParameters::new(),
+            sub_file_parameter,
+            tile.get_bounding_box(),
+            Selector::All,
+        );
+        let pois = self.process_pois(
+            &mut context,
+            tile_latitude,
+            tile_longitude,
+            number_of_pois as usize,
+            false,
+            &mut read_buffer,
+        )?;
+
+        read_buffer.set_buffer_position(first_way_offset);
+        let way_tags = self
+            .get_map_file_info()
+            .ok_or_else(|| MapFileException::new("Missing map file info"))?
+            .way_tags
+            .clone();
+        let mut ways = Vec::with_capacity(number_of_ways as usize);
+        for _ in 0..number_of_ways {
+            let (way_data_size, tile_bitmask) = self.read_way_header(&mut read_buffer)?;
+            let (_, decoded) = self.read_way(
+                tile_latitude,
+                tile_longitude,
+                &way_tags,
+                None,
+                &mut read_buffer,
+                &mut context.interner,
+            )?;
+            ways.push(RawWay {
+                tile_bitmask,
+                way_data_size: way_data_size as u32,
+                ways: decoded,
+            });
+        }
+
+        Ok(RawBlock {
+            zoom_table,
+            pois,
+            ways,
+        })
     }
 
     fn read_map_data_impl(
@@ -898,38 +2422,217 @@ impl MapFile {
         upper_left: &Tile,
         lower_right: &Tile,
         selector: Selector,
-    ) -> Result<MapReadResult, MapFileException> {
-        if upper_left.tile_x > lower_right.tile_x || upper_left.tile_y > lower_right.tile_y {
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<MapReadResult> {
+        if upper_left.tile_y > lower_right.tile_y {
             return Err(MapFileException::new(
-                "upperLeft tile must be above and left of lowerRight tile",
+                "upperLeft tile must be above lowerRight tile",
             ));
         }
 
+        // A range crossing the antimeridian wraps around: split it into the part
+        // east of upper_left and the part west of lower_right and merge both reads
+        if upper_left.tile_x > lower_right.tile_x {
+            let last_tile_x = MercatorProjection::tile_count(upper_left.zoom_level) - 1;
+            let east_lower_right = Tile::new(
+                last_tile_x,
+                lower_right.tile_y,
+                lower_right.zoom_level,
+                lower_right.tile_size,
+            );
+            let west_upper_left = Tile::new(
+                0,
+                upper_left.tile_y,
+                upper_left.zoom_level,
+                upper_left.tile_size,
+            );
+
+            let mut result =
+                self.read_map_data_impl(upper_left, &east_lower_right, selector, progress)?;
+            result.merge(self.read_map_data_impl(
+                &west_upper_left,
+                lower_right,
+                selector,
+                progress,
+            )?);
+            return Ok(result);
+        }
+
+        // Tiles outside the map never reach the sub-file lookup, whose fallback
+        // to lower zoom levels could otherwise pick an unrelated sub-file
+        if !self.covers_bounding_box(&Tile::get_bounding_box_range(upper_left, lower_right)) {
+            return Ok(MapReadResult::new());
+        }
+
         // Get all the data we need from header first
-        let query_zoom_level = self.header.get_query_zoom_level(upper_left.zoom_level) as i32;
-        let sub_file_parameter = self
-            .header
-            .get_sub_file_parameter(query_zoom_level as usize)
-            .ok_or_else(|| {
-                MapFileException::new(format!("no sub-file for zoom level: {}", query_zoom_level))
-            })?
-            .clone(); // Clone the SubFileParameter to avoid borrowing self.header
+        let query_zoom_level = self.query_zoom_level(upper_left.zoom_level)? as i32;
+        let sub_file_parameter = self.sub_file_for(query_zoom_level)?;
 
-        // Create and populate query parameters
-        let mut query_parameters = QueryParameters::new();
-        query_parameters.query_zoom_level = query_zoom_level;
-        query_parameters.calculate_base_tiles(upper_left, lower_right, &sub_file_parameter);
-        query_parameters.calculate_blocks(&sub_file_parameter);
+        self.read_sub_file_impl(
+            upper_left,
+            lower_right,
+            query_zoom_level,
+            &sub_file_parameter,
+            selector,
+            progress,
+        )
+    }
+
+    fn read_sub_file_impl(
+        &mut self,
+        upper_left: &Tile,
+        lower_right: &Tile,
+        query_zoom_level: i32,
+        sub_file_parameter: &SubFileParameter,
+        selector: Selector,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<MapReadResult> {
+        let Some(query_parameters) = Self::plan_query(
+            upper_left,
+            lower_right,
+            query_zoom_level,
+            sub_file_parameter,
+        ) else {
+            return Ok(MapReadResult::new());
+        };
 
         // Create bounding box
         let bounding_box = Tile::get_bounding_box_range(upper_left, lower_right);
 
         // Now process blocks
-        self.process_blocks(
-            &query_parameters,
-            &sub_file_parameter,
-            &bounding_box,
+        let mut context = QueryContext::new(
+            query_parameters,
+            sub_file_parameter.clone(),
+            bounding_box,
             selector,
-        )
+        );
+        self.process_blocks(&mut context, progress)
     }
+
+    // The query parameters for the tile range, None if the range lies outside
+    // the sub-file
+    fn plan_query(
+        upper_left: &Tile,
+        lower_right: &Tile,
+        query_zoom_level: i32,
+        sub_file_parameter: &SubFileParameter,
+    ) -> Option<QueryParameters> {
+        let mut query_parameters = QueryParameters::new();
+        query_parameters.query_zoom_level = query_zoom_level;
+        query_parameters.calculate_base_tiles(upper_left, lower_right, sub_file_parameter);
+
+        // The base tile of the range closest to the sub-file is covered only if
+        // the range overlaps the sub-file at all
+        let nearest_x = query_parameters
+            .from_base_tile_x
+            .max(sub_file_parameter.boundary_tile_left)
+            .min(query_parameters.to_base_tile_x);
+        let nearest_y = query_parameters
+            .from_base_tile_y
+            .max(sub_file_parameter.boundary_tile_top)
+            .min(query_parameters.to_base_tile_y);
+        if !covers_base_tile(sub_file_parameter, nearest_x, nearest_y) {
+            debug!(
+                "Base tiles {}..={} (x) and {}..={} (y) lie outside the sub-file",
+                query_parameters.from_base_tile_x,
+                query_parameters.to_base_tile_x,
+                query_parameters.from_base_tile_y,
+                query_parameters.to_base_tile_y
+            );
+            return None;
+        }
+        query_parameters.calculate_blocks(sub_file_parameter);
+        Some(query_parameters)
+    }
+}
+
+/// Reads the blocks of a query one at a time, see `MapFile::read_iter`.
+pub struct BlockIterator<'a> {
+    map_file: &'a mut MapFile,
+    context: QueryContext,
+    // Column and row of the next block to read, None once done
+    next_block: Option<(i64, i64)>,
+}
+
+impl Iterator for BlockIterator<'_> {
+    type Item = Result<PoiWayBundle>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((column, row)) = self.next_block {
+            // Blocks go from top to bottom and left to right
+            self.next_block = if column < self.context.query_parameters.to_block_x {
+                Some((column + 1, row))
+            } else if row < self.context.query_parameters.to_block_y {
+                Some((self.context.query_parameters.from_block_x, row + 1))
+            } else {
+                None
+            };
+
+            let block_number = match self.context.sub_file_parameter.block_number(row, column) {
+                Ok(block_number) => block_number,
+                Err(e) => {
+                    self.next_block = None;
+                    return Some(Err(e));
+                }
+            };
+            self.map_file.observer.on_block(block_number, row, column);
+            let Some(index_cache) = self.map_file.database_index_cache.as_mut() else {
+                self.next_block = None;
+                return Some(Err(MapFileException::new("Missing index cache")));
+            };
+            let current_block_index_entry =
+                match index_cache.get_index_entry(&self.context.sub_file_parameter, block_number) {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        self.map_file
+                            .observer
+                            .on_warning(&format!("Error getting index entry: {}", e));
+                        continue;
+                    }
+                };
+
+            match self.map_file.read_block_at(
+                &mut self.context,
+                (column, row),
+                current_block_index_entry,
+            ) {
+                Ok(Some(bundle)) => return Some(Ok(bundle)),
+                Ok(None) => continue,
+                Err(e) => {
+                    self.next_block = None;
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Decodes the coordinate blocks of a single way data block, i.e. the way
+/// data that follows the optional way fields. Offsets are relative to the
+/// tile origin at `tile_lat`/`tile_lon`.
+pub fn decode_way_coordinates(
+    bytes: &[u8],
+    tile_lat: f64,
+    tile_lon: f64,
+    double_delta: bool,
+) -> Result<Vec<Vec<LatLong>>> {
+    let mut read_buffer = ReadBuffer::from_bytes(bytes.to_vec());
+    MapFile::process_way_data_block(
+        tile_lat,
+        tile_lon,
+        double_delta,
+        None,
+        CoordinatePrecision::default(),
+        false,
+        &mut read_buffer,
+    )
+}
+
+/// Whether the base tile lies within the boundary tiles of the sub-file.
+fn covers_base_tile(sub_file_parameter: &SubFileParameter, tile_x: i64, tile_y: i64) -> bool {
+    (sub_file_parameter.boundary_tile_left..=sub_file_parameter.boundary_tile_right)
+        .contains(&tile_x)
+        && (sub_file_parameter.boundary_tile_top..=sub_file_parameter.boundary_tile_bottom)
+            .contains(&tile_y)
 }
@@ -1,11 +1,14 @@
+use crate::types::LatLong;
+
 pub struct MercatorProjection;
 
 // Constants
-const EARTH_RADIUS: f64 = 6_378_137.0;
+pub(crate) const EARTH_RADIUS: f64 = 6_378_137.0;
 const EARTH_CIRCUMFERENCE: f64 = 40075016.686;
-const LATITUDE_MAX: f64 = 85.05112877980659;
-const LATITUDE_MIN: f64 = -LATITUDE_MAX;
+pub(crate) const LATITUDE_MAX: f64 = 85.05112877980659;
+pub(crate) const LATITUDE_MIN: f64 = -LATITUDE_MAX;
 const TILE_SIZE: i32 = 256; // Standard tile size
+const TILE_EDGE_EPSILON: f64 = 1e-6; // In tiles, far below a pixel
 const PI: f64 = std::f64::consts::PI;
 
 impl MercatorProjection {
@@ -23,12 +26,14 @@ impl MercatorProjection {
 
     pub fn longitude_to_tile_x(longitude: f64, zoom_level: u8) -> i64 {
         let n = 1i64 << zoom_level;
-        ((longitude + 180.0) / 360.0 * n as f64).floor() as i64
+        let tile_x = Self::snap_to_tile_edge((longitude + 180.0) / 360.0 * n as f64);
+
+        tile_x.clamp(0, n - 1)
     }
 
     pub fn latitude_to_tile_y(latitude: f64, zoom_level: u8) -> i64 {
         // Clamp latitude to valid range
-        let latitude = latitude.max(LATITUDE_MIN).min(LATITUDE_MAX);
+        let latitude = latitude.clamp(LATITUDE_MIN, LATITUDE_MAX);
 
         let n = 1i64 << zoom_level;
 
@@ -39,32 +44,52 @@ impl MercatorProjection {
         let y = 0.5 - (lat_rad.sin().atanh() / (2.0 * PI));
 
         // Handle potential numerical issues near the poles
-        let tile_y = (y * n as f64).floor() as i64;
+        let tile_y = Self::snap_to_tile_edge(y * n as f64);
 
         // Ensure result is within valid range
         tile_y.clamp(0, n - 1)
     }
 
+    // The forward and inverse formulas don't agree to the last bit, so a tile
+    // edge converted to degrees and back can land just below the integer and
+    // floor to the previous tile. Values this close to an edge belong to it.
+    fn snap_to_tile_edge(tile: f64) -> i64 {
+        let nearest = tile.round();
+        if (tile - nearest).abs() < TILE_EDGE_EPSILON {
+            nearest as i64
+        } else {
+            tile.floor() as i64
+        }
+    }
+
     // Use TILE_SIZE instead of passing it as parameter if not needed
     pub fn latitude_to_pixel_y(latitude: f64, zoom_level: u8) -> f64 {
-        let map_size = Self::get_map_size(zoom_level);
+        Self::latitude_to_pixel_y_with_size(latitude, zoom_level, TILE_SIZE)
+    }
+
+    pub fn longitude_to_pixel_x(longitude: f64, zoom_level: u8) -> f64 {
+        Self::longitude_to_pixel_x_with_size(longitude, zoom_level, TILE_SIZE)
+    }
+
+    pub fn latitude_to_pixel_y_with_size(latitude: f64, zoom_level: u8, tile_size: i32) -> f64 {
+        let map_size = Self::get_map_size_with_size(zoom_level, tile_size);
         let sin_latitude = latitude.to_radians().sin();
         let pixel_y = (0.5 - ((1.0 + sin_latitude) / (1.0 - sin_latitude)).ln() / (4.0 * PI))
             * map_size as f64;
         pixel_y.min(map_size as f64).max(0.0)
     }
 
-    pub fn longitude_to_pixel_x(longitude: f64, zoom_level: u8) -> f64 {
-        let map_size = Self::get_map_size(zoom_level);
+    pub fn longitude_to_pixel_x_with_size(longitude: f64, zoom_level: u8, tile_size: i32) -> f64 {
+        let map_size = Self::get_map_size_with_size(zoom_level, tile_size);
         (longitude + 180.0) / 360.0 * map_size as f64
     }
 
     pub fn get_map_size(zoom_level: u8) -> i64 {
-        if zoom_level as i32 >= 0 {
-            (TILE_SIZE as i64) << zoom_level
-        } else {
-            0
-        }
+        Self::get_map_size_with_size(zoom_level, TILE_SIZE)
+    }
+
+    pub fn get_map_size_with_size(zoom_level: u8, tile_size: i32) -> i64 {
+        (tile_size as i64) << zoom_level
     }
 
     // Your other methods remain the same
@@ -75,6 +100,22 @@ impl MercatorProjection {
         distance_per_tile / TILE_SIZE as f64
     }
 
+    /// Projects a coordinate to spherical Web Mercator (EPSG:3857) meters.
+    /// Latitudes are clamped to the Mercator limits.
+    pub fn lat_long_to_meters(latitude: f64, longitude: f64) -> (f64, f64) {
+        let latitude = latitude.clamp(LATITUDE_MIN, LATITUDE_MAX);
+        let x = EARTH_RADIUS * longitude.to_radians();
+        let y = EARTH_RADIUS * (PI / 4.0 + latitude.to_radians() / 2.0).tan().ln();
+        (x, y)
+    }
+
+    /// Inverse of `lat_long_to_meters`.
+    pub fn meters_to_lat_long(x: f64, y: f64) -> LatLong {
+        let longitude = (x / EARTH_RADIUS).to_degrees();
+        let latitude = (2.0 * (y / EARTH_RADIUS).exp().atan() - PI / 2.0).to_degrees();
+        LatLong::new(latitude, longitude)
+    }
+
     pub fn tile_count(zoom_level: u8) -> i64 {
         1i64 << zoom_level
     }
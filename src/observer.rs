@@ -0,0 +1,51 @@
+use crate::map_data::PoiWayBundle;
+use tracing::{trace, warn};
+
+/// Follows the blocks a `MapFile` reads, e.g. to count them or to route its
+/// warnings somewhere other than the global `tracing` subscriber. Every
+/// method does nothing unless overridden.
+pub trait ReadObserver: Send + Sync {
+    /// A query is about to process the block at `row` and `column`.
+    fn on_block(&self, _block_number: i64, _row: i64, _column: i64) {}
+
+    /// Something went wrong that the read recovers from, usually by skipping
+    /// a block.
+    fn on_warning(&self, _message: &str) {}
+
+    /// A block was decoded into `bundle`.
+    fn on_bundle(&self, _bundle: &PoiWayBundle) {}
+}
+
+/// Ignores every event. The observer of every `MapFile` until another one is
+/// set.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopObserver;
+
+impl ReadObserver for NoopObserver {}
+
+/// Logs the events through `tracing`, the block events at trace level.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TracingObserver;
+
+impl ReadObserver for TracingObserver {
+    fn on_block(&self, block_number: i64, row: i64, column: i64) {
+        trace!(
+            "Processing block {}, at row {} column {}",
+            block_number,
+            row,
+            column
+        );
+    }
+
+    fn on_warning(&self, message: &str) {
+        warn!("{}", message);
+    }
+
+    fn on_bundle(&self, bundle: &PoiWayBundle) {
+        trace!(
+            "Found bundle with {} POIs and {} ways",
+            bundle.pois.len(),
+            bundle.ways.len()
+        );
+    }
+}
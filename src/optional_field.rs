@@ -1,6 +1,6 @@
 use std::io::{Read, Seek};
 
-use crate::{reader::ReadBuffer, LatLong, MapFileException};
+use crate::{reader::ReadBuffer, LatLong, MapFileException, Result};
 
 pub struct OptionalFields {
     pub comment: Option<String>,
@@ -53,7 +53,7 @@ impl OptionalFields {
     pub fn read_optional_fields<R: Read + Seek>(
         &mut self,
         read_buffer: &mut ReadBuffer<R>,
-    ) -> Result<(), MapFileException> {
+    ) -> Result<()> {
         // Read each optional field in order, only if its flag is set
         if self.has_start_position {
             let lat = read_buffer.read_int()? as f64 / 1_000_000.0;
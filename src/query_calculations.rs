@@ -43,6 +43,11 @@ impl QueryCalculations {
         bitmask
     }
 
+    /// How many of the 16 sub-tiles of a base tile the bitmask covers.
+    pub fn bitmask_popcount(bitmask: i32) -> u32 {
+        (bitmask & 0xffff).count_ones()
+    }
+
     fn get_first_level_tile_bitmask(tile: &Tile) -> i32 {
         if tile.tile_x % 2 == 0 && tile.tile_y % 2 == 0 {
             // upper left quadrant
@@ -0,0 +1,127 @@
+use std::cmp::{max, min};
+
+/// Sets a single pixel if it lies inside the buffer.
+pub fn set_pixel(x: i32, y: i32, color: u32, buffer: &mut [u32], width: usize) {
+    if width == 0 {
+        return;
+    }
+    let height = (buffer.len() / width) as i32;
+    if x >= 0 && x < width as i32 && y >= 0 && y < height {
+        buffer[(y as usize) * width + (x as usize)] = color;
+    }
+}
+
+/// Fills a polygon using an even-odd scanline rasterizer.
+/// Pixels outside the `width` x `height` buffer are clipped.
+pub fn fill_polygon(
+    points: &[(i32, i32)],
+    color: u32,
+    buffer: &mut [u32],
+    width: usize,
+    height: usize,
+) {
+    if points.len() < 3 {
+        return; // Need at least 3 points for a polygon
+    }
+
+    // Find the vertical extent of the polygon
+    let mut min_y = i32::MAX;
+    let mut max_y = i32::MIN;
+
+    for &(_, y) in points {
+        min_y = min(min_y, y);
+        max_y = max(max_y, y);
+    }
+
+    // Clip to screen bounds
+    min_y = max(0, min_y);
+    max_y = min(height as i32 - 1, max_y);
+
+    let mut nodes = Vec::new();
+
+    // For each scanline
+    for y in min_y..=max_y {
+        nodes.clear();
+
+        // Find intersections with polygon edges
+        for i in 0..points.len() {
+            let j = (i + 1) % points.len();
+            let (x1, y1) = points[i];
+            let (x2, y2) = points[j];
+
+            // Check if the edge crosses this scanline
+            if (y1 <= y && y2 > y) || (y2 <= y && y1 > y) {
+                // Calculate x-coordinate of intersection
+                let x = x1 + ((y - y1) as f64 * (x2 - x1) as f64 / (y2 - y1) as f64) as i32;
+                nodes.push(x);
+            }
+        }
+
+        // Sort intersections
+        nodes.sort_unstable();
+
+        // Fill pixel pairs
+        for pair in nodes.chunks_exact(2) {
+            let start_x = max(0, pair[0]);
+            let end_x = min(width as i32 - 1, pair[1]);
+
+            for x in start_x..=end_x {
+                buffer[(y as usize) * width + (x as usize)] = color;
+            }
+        }
+    }
+}
+
+/// Draws a line using Bresenham's algorithm, stamping a square of
+/// `thickness` pixels around each point.
+pub fn draw_thick_line(
+    from: (i32, i32),
+    to: (i32, i32),
+    color: u32,
+    thickness: u8,
+    buffer: &mut [u32],
+    width: usize,
+) {
+    let (x0, y0) = from;
+    let (x1, y1) = to;
+
+    let dx = (x1 - x0).abs();
+    let dy = -(y1 - y0).abs();
+    let sx = if x0 < x1 { 1 } else { -1 };
+    let sy = if y0 < y1 { 1 } else { -1 };
+    let mut err = dx + dy;
+
+    let mut x = x0;
+    let mut y = y0;
+
+    // For thickness, draw pixels in a square pattern around each point
+    let half_thickness = thickness as i32 / 2;
+
+    loop {
+        for offset_y in -half_thickness..=half_thickness {
+            for offset_x in -half_thickness..=half_thickness {
+                set_pixel(x + offset_x, y + offset_y, color, buffer, width);
+            }
+        }
+
+        if x == x1 && y == y1 {
+            break;
+        }
+
+        let e2 = 2 * err;
+        if e2 >= dy {
+            if x == x1 {
+                break;
+            }
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            if y == y1 {
+                break;
+            }
+            err += dx;
+            y += sy;
+        }
+    }
+}
@@ -1,14 +1,51 @@
+use crate::errors::{MapFileErrorKind, Result};
 use crate::{types::Tag, MapFileException};
-use std::io::{self, Read, Seek, SeekFrom};
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
 
 const CHARSET_UTF8: &str = "UTF-8";
-const MAXIMUM_BUFFER_SIZE: usize = 1024 * 1024 * 10; // Similar to Java's Parameters.MAXIMUM_BUFFER_SIZE
+pub(crate) const MAXIMUM_BUFFER_SIZE: usize = 1024 * 1024 * 10; // Similar to Java's Parameters.MAXIMUM_BUFFER_SIZE
+
+pub(crate) fn check_buffer_size(length: usize, max: usize) -> Result<()> {
+    if length > max {
+        return Err(MapFileException::with_kind(
+            MapFileErrorKind::BufferTooLarge { length, max },
+            format!(
+                "read of {} bytes exceeds maximum buffer size {}",
+                length, max
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Byte order of the fixed-width numbers a `ReadBuffer` reads. Map files are
+/// big-endian; little-endian is there to test hypothetical format variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ByteOrder {
+    #[default]
+    BigEndian,
+    LittleEndian,
+}
 
 pub struct ReadBuffer<R: Read + Seek> {
     buffer_data: Vec<u8>,
     buffer_position: usize,
     input_channel: R,
     tag_ids: Vec<i32>,
+    byte_order: ByteOrder,
+}
+
+impl ReadBuffer<Cursor<Vec<u8>>> {
+    /// A buffer over bytes that were already read, e.g. a whole block.
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Self {
+            buffer_data: bytes,
+            buffer_position: 0,
+            input_channel: Cursor::new(Vec::new()),
+            tag_ids: Vec::new(),
+            byte_order: ByteOrder::default(),
+        }
+    }
 }
 
 impl<R: Read + Seek> ReadBuffer<R> {
@@ -18,10 +55,39 @@ impl<R: Read + Seek> ReadBuffer<R> {
             buffer_position: 0,
             input_channel,
             tag_ids: Vec::new(),
+            byte_order: ByteOrder::default(),
         }
     }
 
-    pub fn read_byte(&mut self) -> Result<u8, MapFileException> {
+    pub fn byte_order(&self) -> ByteOrder {
+        self.byte_order
+    }
+
+    /// Sets the byte order of the ints, longs, shorts and floats read from
+    /// now on. Variable-length numbers are not affected.
+    pub fn set_byte_order(&mut self, byte_order: ByteOrder) {
+        self.byte_order = byte_order;
+    }
+
+    // The next N bytes, in the configured byte order turned big-endian
+    fn read_fixed<const N: usize>(&mut self, name: &str) -> Result<[u8; N]> {
+        if self.buffer_position + N > self.buffer_data.len() {
+            return Err(MapFileException::new(format!(
+                "Buffer overflow when reading {}",
+                name
+            )));
+        }
+        let mut bytes: [u8; N] = self.buffer_data[self.buffer_position..self.buffer_position + N]
+            .try_into()
+            .unwrap();
+        self.buffer_position += N;
+        if self.byte_order == ByteOrder::LittleEndian {
+            bytes.reverse();
+        }
+        Ok(bytes)
+    }
+
+    pub fn read_byte(&mut self) -> Result<u8> {
         if self.buffer_position >= self.buffer_data.len() {
             return Err(MapFileException::new("Buffer overflow when reading byte"));
         }
@@ -30,87 +96,51 @@ impl<R: Read + Seek> ReadBuffer<R> {
         Ok(byte)
     }
 
-    pub fn read_float(&mut self) -> Result<f32, MapFileException> {
+    pub fn read_float(&mut self) -> Result<f32> {
         Ok(f32::from_bits(self.read_int()? as u32))
     }
 
-    pub fn read_from_file(&mut self, length: usize) -> Result<bool, MapFileException> {
+    /// Fills the buffer with the next `length` bytes of the source.
+    pub fn read_from_file(&mut self, length: usize) -> Result<()> {
         // ensure the read buffer is large enough
-        if length > MAXIMUM_BUFFER_SIZE {
-            return Ok(false);
-        }
+        check_buffer_size(length, MAXIMUM_BUFFER_SIZE)?;
 
         self.buffer_data.resize(length, 0);
         self.buffer_position = 0;
 
-        match self
-            .input_channel
+        self.input_channel
             .read_exact(&mut self.buffer_data[..length])
-        {
-            Ok(_) => Ok(true),
-            Err(_) => Ok(false),
-        }
+            .map_err(|e| {
+                if e.kind() == io::ErrorKind::UnexpectedEof {
+                    MapFileException::with_kind(
+                        MapFileErrorKind::UnexpectedEof,
+                        format!("unexpected end of file reading {} bytes", length),
+                    )
+                } else {
+                    e.into()
+                }
+            })
     }
 
-    pub fn read_from_file_at_offset(
-        &mut self,
-        offset: u64,
-        length: usize,
-    ) -> Result<bool, MapFileException> {
-        if length > MAXIMUM_BUFFER_SIZE {
-            return Ok(false);
-        }
-
-        self.buffer_data.resize(length, 0);
-        self.buffer_position = 0;
-
+    /// Fills the buffer with `length` bytes starting at `offset`.
+    pub fn read_from_file_at_offset(&mut self, offset: u64, length: usize) -> Result<()> {
         self.input_channel.seek(SeekFrom::Start(offset))?;
-        match self
-            .input_channel
-            .read_exact(&mut self.buffer_data[..length])
-        {
-            Ok(_) => Ok(true),
-            Err(_) => Ok(false),
-        }
+        self.read_from_file(length)
     }
 
-    pub fn read_int(&mut self) -> Result<i32, MapFileException> {
-        if self.buffer_position + 4 > self.buffer_data.len() {
-            return Err(MapFileException::new("Buffer overflow when reading int"));
-        }
-        self.buffer_position += 4;
-        Ok(i32::from_be_bytes(
-            self.buffer_data[self.buffer_position - 4..self.buffer_position]
-                .try_into()
-                .unwrap(),
-        ))
+    pub fn read_int(&mut self) -> Result<i32> {
+        Ok(i32::from_be_bytes(self.read_fixed("int")?))
     }
 
-    pub fn read_long(&mut self) -> Result<i64, MapFileException> {
-        if self.buffer_position + 8 > self.buffer_data.len() {
-            return Err(MapFileException::new("Buffer overflow when reading long"));
-        }
-        self.buffer_position += 8;
-        Ok(i64::from_be_bytes(
-            self.buffer_data[self.buffer_position - 8..self.buffer_position]
-                .try_into()
-                .unwrap(),
-        ))
+    pub fn read_long(&mut self) -> Result<i64> {
+        Ok(i64::from_be_bytes(self.read_fixed("long")?))
     }
 
-    pub fn read_short(&mut self) -> Result<i16, MapFileException> {
-        if self.buffer_position + 2 > self.buffer_data.len() {
-            return Err(MapFileException::new("Buffer overflow when reading short"));
-        }
-        self.buffer_position += 2;
-        Ok(i16::from_be_bytes(
-            self.buffer_data[self.buffer_position - 2..self.buffer_position]
-                .try_into()
-                .unwrap(),
-        ))
+    pub fn read_short(&mut self) -> Result<i16> {
+        Ok(i16::from_be_bytes(self.read_fixed("short")?))
     }
 
-    pub fn read_signed_int(&mut self) -> Result<i32, MapFileException> {
+    pub fn read_signed_int(&mut self) -> Result<i32> {
         let mut variable_byte_decode = 0;
         let mut variable_byte_shift = 0;
 
@@ -143,16 +173,36 @@ impl<R: Read + Seek> ReadBuffer<R> {
         Ok(result)
     }
 
-    pub fn read_tags(
-        &mut self,
-        tags_array: &[Tag],
-        number_of_tags: u8,
-    ) -> Result<Vec<Tag>, MapFileException> {
+    /// Reads `number_of_tags` tag ids and resolves them against `tags_array`.
+    /// A tag whose value in the table is a `%` placeholder takes its value
+    /// from the feature instead, stored after the ids in tag order:
+    ///
+    /// - `%b` a byte
+    /// - `%h` a short
+    /// - `%i` an int, in hex after a `#` for keys containing `:colour`
+    /// - `%f` a float
+    /// - `%s` a string
+    pub fn read_tags(&mut self, tags_array: &[Tag], number_of_tags: u8) -> Result<Vec<Tag>> {
         self.tag_ids.clear();
         let max_tag = tags_array.len();
 
-        for _ in 0..number_of_tags {
-            let tag_id = self.read_unsigned_int()? as usize;
+        // Every tag id takes at least one byte
+        let remaining = self.buffer_data.len().saturating_sub(self.buffer_position);
+        if remaining < number_of_tags as usize {
+            return Err(MapFileException::new(format!(
+                "feature declares {} tags, but only {} bytes remain",
+                number_of_tags, remaining
+            )));
+        }
+
+        for index in 0..number_of_tags {
+            let tag_id = self.read_unsigned_int().map_err(|_| {
+                MapFileException::new(format!(
+                    "truncated tag id {} of {} declared",
+                    index + 1,
+                    number_of_tags
+                ))
+            })? as usize;
             if tag_id >= max_tag {
                 return Err(MapFileException::new(format!("invalid tag ID: {}", tag_id)));
             }
@@ -160,24 +210,35 @@ impl<R: Read + Seek> ReadBuffer<R> {
         }
 
         let mut result = Vec::new();
-        for &tag_id in &self.tag_ids {
-            let tag = &tags_array[tag_id as usize];
-            result.push(tag.clone());
+        for index in 0..self.tag_ids.len() {
+            let tag = &tags_array[self.tag_ids[index] as usize];
+            let value = match tag.as_value() {
+                "%b" => (self.read_byte()? as i8).to_string(),
+                "%h" => self.read_short()?.to_string(),
+                "%i" if tag.as_key().contains(":colour") => format!("#{:x}", self.read_int()?),
+                "%i" => self.read_int()?.to_string(),
+                "%f" => self.read_float()?.to_string(),
+                "%s" => self.read_utf8_encoded_string()?,
+                _ => {
+                    result.push(tag.clone());
+                    continue;
+                }
+            };
+            result.push(Tag::new(tag.key.clone(), value));
         }
 
         Ok(result)
     }
 
-    pub fn read_unsigned_int(&mut self) -> Result<u32, MapFileException> {
+    pub fn read_unsigned_int(&mut self) -> Result<u32> {
         let mut variable_byte_decode = 0;
         let mut variable_byte_shift = 0;
 
-        while (self.buffer_data[self.buffer_position] & 0x80) != 0 {
-            if self.buffer_position >= self.buffer_data.len() {
-                return Err(MapFileException::new(
-                    "Buffer overflow when reading unsigned int",
-                ));
-            }
+        while self
+            .buffer_data
+            .get(self.buffer_position)
+            .is_some_and(|byte| byte & 0x80 != 0)
+        {
             variable_byte_decode |=
                 ((self.buffer_data[self.buffer_position] & 0x7f) as u32) << variable_byte_shift;
             self.buffer_position += 1;
@@ -196,15 +257,12 @@ impl<R: Read + Seek> ReadBuffer<R> {
         Ok(result)
     }
 
-    pub fn read_utf8_encoded_string(&mut self) -> Result<String, MapFileException> {
+    pub fn read_utf8_encoded_string(&mut self) -> Result<String> {
         let length = self.read_unsigned_int()? as usize;
         self.read_utf8_encoded_string_with_length(length)
     }
 
-    pub fn read_utf8_encoded_string_with_length(
-        &mut self,
-        string_length: usize,
-    ) -> Result<String, MapFileException> {
+    pub fn read_utf8_encoded_string_with_length(&mut self, string_length: usize) -> Result<String> {
         if string_length > 0 && self.buffer_position + string_length <= self.buffer_data.len() {
             self.buffer_position += string_length;
             String::from_utf8(
@@ -224,6 +282,11 @@ impl<R: Read + Seek> ReadBuffer<R> {
         self.buffer_position
     }
 
+    /// The bytes of the last read from file.
+    pub fn get_buffer_data(&self) -> &[u8] {
+        &self.buffer_data
+    }
+
     pub fn get_buffer_size(&self) -> usize {
         self.buffer_data.len()
     }
@@ -1,8 +1,10 @@
-use crate::errors::MapFileException;
+use crate::errors::{MapFileErrorKind, MapFileException, Result};
 use crate::header::MapFileInfoBuilder;
+use crate::mercator::{LATITUDE_MAX, LATITUDE_MIN};
 use crate::reader::ReadBuffer;
 use crate::types::{BoundingBox, Tag};
 use std::io::{Read, Seek};
+use tracing::warn;
 
 const BINARY_OSM_MAGIC_BYTE: &str = "mapsforge binary OSM";
 const HEADER_SIZE_MAX: i32 = 1000000;
@@ -14,16 +16,12 @@ const SUPPORTED_FILE_VERSION_MAX: i32 = 5;
 pub struct RequiredFields;
 
 impl RequiredFields {
-    pub fn read_magic_byte<R: Read + Seek>(
-        read_buffer: &mut ReadBuffer<R>,
-    ) -> Result<(), MapFileException> {
+    pub fn read_magic_byte<R: Read + Seek>(read_buffer: &mut ReadBuffer<R>) -> Result<()> {
         const BINARY_OSM_MAGIC_BYTE: &str = "mapsforge binary OSM";
         let magic_byte_length = BINARY_OSM_MAGIC_BYTE.len();
 
         // Read the magic byte directly with known length, not as a length-prefixed string
-        if !read_buffer.read_from_file(magic_byte_length + 4)? {
-            return Err(MapFileException::new("reading magic byte has failed"));
-        }
+        read_buffer.read_from_file(magic_byte_length + 4)?;
 
         let magic_byte = read_buffer.read_utf8_encoded_string_with_length(magic_byte_length)?;
 
@@ -37,9 +35,7 @@ impl RequiredFields {
         Ok(())
     }
 
-    pub fn read_remaining_header<R: Read + Seek>(
-        read_buffer: &mut ReadBuffer<R>,
-    ) -> Result<(), MapFileException> {
+    pub fn read_remaining_header<R: Read + Seek>(read_buffer: &mut ReadBuffer<R>) -> Result<()> {
         let remaining_header_size = read_buffer.read_int()?;
         if remaining_header_size < HEADER_SIZE_MIN || remaining_header_size > HEADER_SIZE_MAX {
             return Err(MapFileException::new(format!(
@@ -48,12 +44,7 @@ impl RequiredFields {
             )));
         }
 
-        if !read_buffer.read_from_file(remaining_header_size as usize)? {
-            return Err(MapFileException::new(format!(
-                "reading header data has failed: {}",
-                remaining_header_size
-            )));
-        }
+        read_buffer.read_from_file(remaining_header_size as usize)?;
 
         Ok(())
     }
@@ -61,7 +52,7 @@ impl RequiredFields {
     pub fn read_file_version<R: Read + Seek>(
         read_buffer: &mut ReadBuffer<R>,
         map_file_info_builder: &mut MapFileInfoBuilder,
-    ) -> Result<(), MapFileException> {
+    ) -> Result<()> {
         let file_version = read_buffer.read_int()?;
         if file_version < SUPPORTED_FILE_VERSION_MIN || file_version > SUPPORTED_FILE_VERSION_MAX {
             return Err(MapFileException::new(format!(
@@ -73,17 +64,26 @@ impl RequiredFields {
         Ok(())
     }
 
+    /// Checks the file size stored in the header against the actual size.
+    /// With `lenient` a mismatch is only logged.
     pub fn read_file_size<R: Read + Seek>(
         read_buffer: &mut ReadBuffer<R>,
         file_size: i64,
+        lenient: bool,
         map_file_info_builder: &mut MapFileInfoBuilder,
-    ) -> Result<(), MapFileException> {
+    ) -> Result<()> {
         let header_file_size = read_buffer.read_long()?;
         if header_file_size != file_size {
-            return Err(MapFileException::new(format!(
-                "invalid file size: {}",
-                header_file_size
-            )));
+            if !lenient {
+                return Err(MapFileException::new(format!(
+                    "invalid file size: header says {} bytes, file has {}",
+                    header_file_size, file_size
+                )));
+            }
+            warn!(
+                "file size mismatch: header says {} bytes, file has {}; continuing",
+                header_file_size, file_size
+            );
         }
         map_file_info_builder.file_size = file_size;
         Ok(())
@@ -92,25 +92,29 @@ impl RequiredFields {
     pub fn read_bounding_box<R: Read + Seek>(
         read_buffer: &mut ReadBuffer<R>,
         map_file_info_builder: &mut MapFileInfoBuilder,
-    ) -> Result<(), MapFileException> {
+    ) -> Result<()> {
         let min_latitude = read_buffer.read_int()? as f64 / 1_000_000.0;
         let min_longitude = read_buffer.read_int()? as f64 / 1_000_000.0;
         let max_latitude = read_buffer.read_int()? as f64 / 1_000_000.0;
         let max_longitude = read_buffer.read_int()? as f64 / 1_000_000.0;
 
-        map_file_info_builder.bounding_box = Some(BoundingBox::new(
-            min_latitude,
-            min_longitude,
-            max_latitude,
-            max_longitude,
-        )?);
+        let raw_bounding_box =
+            BoundingBox::new(min_latitude, min_longitude, max_latitude, max_longitude)?;
+
+        // Latitudes beyond the Mercator limit have no tiles
+        map_file_info_builder.bounding_box = Some(BoundingBox {
+            min_latitude: min_latitude.clamp(LATITUDE_MIN, LATITUDE_MAX),
+            max_latitude: max_latitude.clamp(LATITUDE_MIN, LATITUDE_MAX),
+            ..raw_bounding_box.clone()
+        });
+        map_file_info_builder.raw_bounding_box = Some(raw_bounding_box);
         Ok(())
     }
 
     pub fn read_tile_pixel_size<R: Read + Seek>(
         read_buffer: &mut ReadBuffer<R>,
         map_file_info_builder: &mut MapFileInfoBuilder,
-    ) -> Result<(), MapFileException> {
+    ) -> Result<()> {
         let tile_pixel_size = read_buffer.read_short()? as i32;
         // If you want to validate against a specific tile size:
         // if tile_pixel_size != TILE_SIZE {
@@ -125,7 +129,7 @@ impl RequiredFields {
     pub fn read_map_date<R: Read + Seek>(
         read_buffer: &mut ReadBuffer<R>,
         map_file_info_builder: &mut MapFileInfoBuilder,
-    ) -> Result<(), MapFileException> {
+    ) -> Result<()> {
         let map_date = read_buffer.read_long()?;
         if map_date < 1200000000000 {
             return Err(MapFileException::new(format!(
@@ -139,7 +143,7 @@ impl RequiredFields {
     pub fn read_poi_tags<R: Read + Seek>(
         read_buffer: &mut ReadBuffer<R>,
         map_file_info_builder: &mut MapFileInfoBuilder,
-    ) -> Result<(), MapFileException> {
+    ) -> Result<()> {
         let number_of_poi_tags = read_buffer.read_short()? as i32;
         if number_of_poi_tags < 0 {
             return Err(MapFileException::new(format!(
@@ -157,7 +161,7 @@ impl RequiredFields {
                     current_tag_id
                 )));
             }
-            poi_tags.push(Tag::from_string(tag));
+            poi_tags.push(Tag::from_string(tag)?);
         }
         map_file_info_builder.poi_tags = poi_tags;
         Ok(())
@@ -166,13 +170,13 @@ impl RequiredFields {
     pub fn read_projection_name<R: Read + Seek>(
         read_buffer: &mut ReadBuffer<R>,
         map_file_info_builder: &mut MapFileInfoBuilder,
-    ) -> Result<(), MapFileException> {
+    ) -> Result<()> {
         let projection_name = read_buffer.read_utf8_encoded_string()?;
         if projection_name != MERCATOR {
-            return Err(MapFileException::new(format!(
-                "unsupported projection: {}",
-                projection_name
-            )));
+            return Err(MapFileException::with_kind(
+                MapFileErrorKind::UnsupportedProjection(projection_name.clone()),
+                format!("unsupported projection: {}", projection_name),
+            ));
         }
         map_file_info_builder.projection_name = projection_name;
         Ok(())
@@ -181,7 +185,7 @@ impl RequiredFields {
     pub fn read_way_tags<R: Read + Seek>(
         read_buffer: &mut ReadBuffer<R>,
         map_file_info_builder: &mut MapFileInfoBuilder,
-    ) -> Result<(), MapFileException> {
+    ) -> Result<()> {
         let number_of_way_tags = read_buffer.read_short()? as i32;
         if number_of_way_tags < 0 {
             return Err(MapFileException::new(format!(
@@ -199,7 +203,7 @@ impl RequiredFields {
                     current_tag_id
                 )));
             }
-            way_tags.push(Tag::from_string(tag));
+            way_tags.push(Tag::from_string(tag)?);
         }
         map_file_info_builder.way_tags = way_tags;
         Ok(())
@@ -0,0 +1,50 @@
+pub struct Serializer;
+
+impl Serializer {
+    /// Appends a signed int as four bytes.
+    /// The byte order is big-endian.
+    pub fn write_int(buffer: &mut Vec<u8>, value: i32) {
+        buffer.extend_from_slice(&value.to_be_bytes());
+    }
+
+    /// Appends a signed long as eight bytes.
+    /// The byte order is big-endian.
+    pub fn write_long(buffer: &mut Vec<u8>, value: i64) {
+        buffer.extend_from_slice(&value.to_be_bytes());
+    }
+
+    /// Appends a signed short as two bytes.
+    /// The byte order is big-endian.
+    pub fn write_short(buffer: &mut Vec<u8>, value: i16) {
+        buffer.extend_from_slice(&value.to_be_bytes());
+    }
+
+    /// Appends an unsigned int as a variable amount of bytes (VBE-U): seven
+    /// bits per byte, least significant first, with the high bit set on all
+    /// but the last byte.
+    pub fn write_unsigned_int(buffer: &mut Vec<u8>, mut value: u32) {
+        while value > 0x7f {
+            buffer.push((value & 0x7f) as u8 | 0x80);
+            value >>= 7;
+        }
+        buffer.push(value as u8);
+    }
+
+    /// Appends a signed int as a variable amount of bytes (VBE-S): like VBE-U,
+    /// but the last byte holds six bits of the magnitude and the sign in 0x40.
+    pub fn write_signed_int(buffer: &mut Vec<u8>, value: i32) {
+        let mut magnitude = value.unsigned_abs();
+        while magnitude > 0x3f {
+            buffer.push((magnitude & 0x7f) as u8 | 0x80);
+            magnitude >>= 7;
+        }
+        let sign = if value < 0 { 0x40 } else { 0 };
+        buffer.push(magnitude as u8 | sign);
+    }
+
+    /// Appends a string as its VBE-U encoded byte length and UTF-8 bytes.
+    pub fn write_utf8_encoded_string(buffer: &mut Vec<u8>, value: &str) {
+        Self::write_unsigned_int(buffer, value.len() as u32);
+        buffer.extend_from_slice(value.as_bytes());
+    }
+}
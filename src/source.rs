@@ -0,0 +1,67 @@
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::sync::Arc;
+
+use crate::errors::{MapFileErrorKind, MapFileException, Result};
+
+/// Random access to the raw bytes of map blocks.
+pub trait BlockSource: Send {
+    /// Returns the `len` bytes starting at `offset`.
+    fn read_block(&mut self, offset: u64, len: usize) -> Result<Vec<u8>>;
+}
+
+/// A seekable byte source backing a `MapFile`. The index cache works on its
+/// own handle, so sources must be cheap to clone.
+pub(crate) trait MapSource: BlockSource + Read + Seek {
+    fn try_clone_source(&self) -> Result<Box<dyn MapSource>>;
+}
+
+fn read_block_from(source: &mut (impl Read + Seek), offset: u64, len: usize) -> Result<Vec<u8>> {
+    let mut block = vec![0u8; len];
+    source.seek(SeekFrom::Start(offset))?;
+    source.read_exact(&mut block)?;
+    Ok(block)
+}
+
+impl BlockSource for File {
+    fn read_block(&mut self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        read_block_from(self, offset, len)
+    }
+}
+
+impl BlockSource for Cursor<Arc<[u8]>> {
+    fn read_block(&mut self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        read_block_from(self, offset, len)
+    }
+}
+
+impl BlockSource for Vec<u8> {
+    fn read_block(&mut self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let start = offset as usize;
+        match start.checked_add(len) {
+            Some(end) if end <= self.len() => Ok(self[start..end].to_vec()),
+            _ => Err(MapFileException::with_kind(
+                MapFileErrorKind::UnexpectedEof,
+                format!(
+                    "block of {} bytes at offset {} exceeds source of {} bytes",
+                    len,
+                    offset,
+                    self.len()
+                ),
+            )),
+        }
+    }
+}
+
+impl MapSource for File {
+    fn try_clone_source(&self) -> Result<Box<dyn MapSource>> {
+        Ok(Box::new(self.try_clone()?))
+    }
+}
+
+// In-memory maps share one allocation between all handles
+impl MapSource for Cursor<Arc<[u8]>> {
+    fn try_clone_source(&self) -> Result<Box<dyn MapSource>> {
+        Ok(Box::new(Cursor::new(Arc::clone(self.get_ref()))))
+    }
+}
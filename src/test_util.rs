@@ -0,0 +1,97 @@
+use crate::map_data::{PointOfInterest, Way};
+use crate::types::{LatLong, Tag};
+
+/// Fluent construction of a `Way`, e.g. for tests.
+///
+/// Nodes are appended to the current segment; `segment` starts a new one.
+#[derive(Debug, Clone, Default)]
+pub struct WayBuilder {
+    layer: i8,
+    tags: Vec<Tag>,
+    way_nodes: Vec<Vec<LatLong>>,
+    label_position: Option<LatLong>,
+}
+
+impl WayBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tag(mut self, key: &str, value: &str) -> Self {
+        self.tags.push(Tag::new(key, value));
+        self
+    }
+
+    pub fn layer(mut self, layer: i8) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    pub fn node(mut self, latitude: f64, longitude: f64) -> Self {
+        if self.way_nodes.is_empty() {
+            self.way_nodes.push(Vec::new());
+        }
+        if let Some(segment) = self.way_nodes.last_mut() {
+            segment.push(LatLong::new(latitude, longitude));
+        }
+        self
+    }
+
+    pub fn segment(mut self) -> Self {
+        self.way_nodes.push(Vec::new());
+        self
+    }
+
+    pub fn label_position(mut self, latitude: f64, longitude: f64) -> Self {
+        self.label_position = Some(LatLong::new(latitude, longitude));
+        self
+    }
+
+    /// Builds the way, dropping segments without nodes.
+    pub fn build(self) -> Way {
+        let way_nodes = self
+            .way_nodes
+            .into_iter()
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        Way::new(self.layer, self.tags, way_nodes, self.label_position)
+    }
+}
+
+/// Fluent construction of a `PointOfInterest`, e.g. for tests.
+#[derive(Debug, Clone)]
+pub struct PoiBuilder {
+    layer: i8,
+    tags: Vec<Tag>,
+    position: LatLong,
+}
+
+impl PoiBuilder {
+    pub fn new(latitude: f64, longitude: f64) -> Self {
+        Self {
+            layer: 0,
+            tags: Vec::new(),
+            position: LatLong::new(latitude, longitude),
+        }
+    }
+
+    pub fn tag(mut self, key: &str, value: &str) -> Self {
+        self.tags.push(Tag::new(key, value));
+        self
+    }
+
+    pub fn layer(mut self, layer: i8) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    /// Moves the POI to the given position.
+    pub fn node(mut self, latitude: f64, longitude: f64) -> Self {
+        self.position = LatLong::new(latitude, longitude);
+        self
+    }
+
+    pub fn build(self) -> PointOfInterest {
+        PointOfInterest::new(self.layer, self.tags, self.position)
+    }
+}
@@ -1,3 +1,4 @@
+use crate::errors::{MapFileException, Result};
 use crate::mercator::MercatorProjection;
 use crate::types::BoundingBox;
 
@@ -33,32 +34,136 @@ impl Tile {
         }
     }
 
+    /// The tile `dx` columns and `dy` rows away at the same zoom level. Columns
+    /// wrap around the antimeridian; rows past the poles give None.
+    pub fn neighbor(&self, dx: i64, dy: i64) -> Option<Tile> {
+        let tile_count = MercatorProjection::tile_count(self.zoom_level);
+        let tile_y = self.tile_y + dy;
+        if !(0..tile_count).contains(&tile_y) {
+            return None;
+        }
+        Some(Tile::new(
+            (self.tile_x + dx).rem_euclid(tile_count),
+            tile_y,
+            self.zoom_level,
+            self.tile_size,
+        ))
+    }
+
+    /// Horizontal pixel position of a longitude relative to this tile's left edge,
+    /// using the tile's own `tile_size`.
+    pub fn longitude_to_pixel_x(&self, longitude: f64) -> f64 {
+        MercatorProjection::longitude_to_pixel_x_with_size(
+            longitude,
+            self.zoom_level,
+            self.tile_size,
+        ) - (self.tile_x * self.tile_size as i64) as f64
+    }
+
+    /// Vertical pixel position of a latitude relative to this tile's top edge,
+    /// using the tile's own `tile_size`.
+    pub fn latitude_to_pixel_y(&self, latitude: f64) -> f64 {
+        MercatorProjection::latitude_to_pixel_y_with_size(latitude, self.zoom_level, self.tile_size)
+            - (self.tile_y * self.tile_size as i64) as f64
+    }
+
+    /// `(left, top, right, bottom)` of this tile in absolute map pixels at its
+    /// zoom level, using the tile's own `tile_size`.
+    pub fn pixel_bounds(&self) -> (i64, i64, i64, i64) {
+        let tile_size = self.tile_size as i64;
+        let left = self.tile_x * tile_size;
+        let top = self.tile_y * tile_size;
+        (left, top, left + tile_size, top + tile_size)
+    }
+
+    /// Like [`Tile::pixel_bounds`], but relative to a viewport whose top-left
+    /// corner sits at `viewport_origin_px` in absolute map pixels.
+    pub fn screen_rect_in(&self, viewport_origin_px: (i64, i64)) -> (i64, i64, i64, i64) {
+        let (left, top, right, bottom) = self.pixel_bounds();
+        let (origin_x, origin_y) = viewport_origin_px;
+        (
+            left - origin_x,
+            top - origin_y,
+            right - origin_x,
+            bottom - origin_y,
+        )
+    }
+
+    /// Meters per pixel at the latitude of the tile center, for the tile's
+    /// own `tile_size`.
+    pub fn ground_resolution(&self) -> f64 {
+        let latitude = self.get_bounding_box().get_center_point().latitude;
+        MercatorProjection::meters_per_pixel(latitude, self.zoom_level) * 256.0
+            / self.tile_size as f64
+    }
+
+    /// The map scale 1:n at the tile center on a display with `dpi` pixels
+    /// per inch.
+    pub fn scale_denominator(&self, dpi: f64) -> f64 {
+        const METERS_PER_INCH: f64 = 0.0254;
+        self.ground_resolution() * dpi / METERS_PER_INCH
+    }
+
+    /// The quadkey of the tile: one digit per zoom level, most significant
+    /// first, with bit 0 of each digit taken from x and bit 1 from y.
+    pub fn to_quadkey(&self) -> String {
+        (1..=self.zoom_level)
+            .rev()
+            .map(|level| {
+                let mask = 1i64 << (level - 1);
+                let mut digit = b'0';
+                if self.tile_x & mask != 0 {
+                    digit += 1;
+                }
+                if self.tile_y & mask != 0 {
+                    digit += 2;
+                }
+                digit as char
+            })
+            .collect()
+    }
+
+    /// The tile of a quadkey, whose length is the zoom level.
+    pub fn from_quadkey(quadkey: &str, tile_size: i32) -> Result<Tile> {
+        // Tile numbers must fit an i64
+        if quadkey.len() > 62 {
+            return Err(MapFileException::new(format!(
+                "quadkey too long: {}",
+                quadkey
+            )));
+        }
+
+        let mut tile_x = 0;
+        let mut tile_y = 0;
+        for digit in quadkey.chars() {
+            let value = match digit {
+                '0'..='3' => digit as i64 - '0' as i64,
+                _ => {
+                    return Err(MapFileException::new(format!(
+                        "invalid quadkey digit '{}' in {}",
+                        digit, quadkey
+                    )))
+                }
+            };
+            tile_x = (tile_x << 1) | (value & 1);
+            tile_y = (tile_y << 1) | (value >> 1);
+        }
+        Ok(Tile::new(tile_x, tile_y, quadkey.len() as u8, tile_size))
+    }
+
+    /// Bounding box of the tiles from `upper_left` to `lower_right`, both
+    /// included. A range of a single tile is that tile's bounding box.
     pub fn get_bounding_box_range(upper_left: &Tile, lower_right: &Tile) -> BoundingBox {
-        // Calculate the bounding box covering the range of tiles
-        // Ensure safe calculations to prevent overflow
-
-        // Use saturating operations to prevent overflow
-        let min_latitude = MercatorProjection::tile_y_to_latitude(
-            lower_right.tile_y.min(upper_left.tile_y),
-            upper_left.zoom_level,
-        );
-
-        let max_latitude = MercatorProjection::tile_y_to_latitude(
-            lower_right.tile_y.max(upper_left.tile_y),
-            upper_left.zoom_level,
-        );
-
-        let min_longitude = MercatorProjection::tile_x_to_longitude(
-            lower_right.tile_x.min(upper_left.tile_x),
-            upper_left.zoom_level,
-        );
-
-        let max_longitude = MercatorProjection::tile_x_to_longitude(
-            lower_right.tile_x.max(upper_left.tile_x),
-            upper_left.zoom_level,
-        );
-
-        BoundingBox::new(min_latitude, min_longitude, max_latitude, max_longitude)
-            .expect("Failed to create bounding box")
+        // The range covers everything from the north-west corner of upper_left
+        // to the south-east corner of lower_right
+        let upper_left_box = upper_left.get_bounding_box();
+        let lower_right_box = lower_right.get_bounding_box();
+
+        BoundingBox {
+            min_latitude: lower_right_box.min_latitude,
+            min_longitude: upper_left_box.min_longitude,
+            max_latitude: upper_left_box.max_latitude,
+            max_longitude: lower_right_box.max_longitude,
+        }
     }
 }
@@ -1,4 +1,9 @@
-use crate::MapFileException;
+use crate::mercator::EARTH_RADIUS;
+use crate::reader::ReadBuffer;
+use crate::{MapFileException, Result};
+use std::collections::HashSet;
+use std::io::{Read, Seek};
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct BoundingBox {
@@ -14,7 +19,7 @@ impl BoundingBox {
         min_longitude: f64,
         max_latitude: f64,
         max_longitude: f64,
-    ) -> Result<Self, MapFileException> {
+    ) -> Result<Self> {
         if min_latitude > max_latitude || min_longitude > max_longitude {
             return Err(MapFileException::new("Invalid bounding box coordinates"));
         }
@@ -26,6 +31,26 @@ impl BoundingBox {
         })
     }
 
+    /// The smallest bounding box containing all the given points.
+    pub fn from_lat_longs(lat_longs: &[LatLong]) -> Result<Self> {
+        let first = lat_longs
+            .first()
+            .ok_or_else(|| MapFileException::new("no points for bounding box"))?;
+        let mut bounding_box = Self {
+            min_latitude: first.latitude,
+            min_longitude: first.longitude,
+            max_latitude: first.latitude,
+            max_longitude: first.longitude,
+        };
+        for lat_long in &lat_longs[1..] {
+            bounding_box.min_latitude = bounding_box.min_latitude.min(lat_long.latitude);
+            bounding_box.min_longitude = bounding_box.min_longitude.min(lat_long.longitude);
+            bounding_box.max_latitude = bounding_box.max_latitude.max(lat_long.latitude);
+            bounding_box.max_longitude = bounding_box.max_longitude.max(lat_long.longitude);
+        }
+        Ok(bounding_box)
+    }
+
     pub fn get_center_point(&self) -> LatLong {
         LatLong {
             latitude: (self.min_latitude + self.max_latitude) / 2.0,
@@ -33,6 +58,23 @@ impl BoundingBox {
         }
     }
 
+    /// Like `get_center_point`, but a box whose `min_longitude` exceeds its
+    /// `max_longitude` is taken to cross the antimeridian, and its center
+    /// lies on that side of the globe.
+    pub fn center_point_wrapped(&self) -> LatLong {
+        if self.min_longitude <= self.max_longitude {
+            return self.get_center_point();
+        }
+        let mut longitude = (self.min_longitude + self.max_longitude + 360.0) / 2.0;
+        if longitude > 180.0 {
+            longitude -= 360.0;
+        }
+        LatLong {
+            latitude: (self.min_latitude + self.max_latitude) / 2.0,
+            longitude,
+        }
+    }
+
     pub fn contains(&self, latitude: f64, longitude: f64) -> bool {
         latitude >= self.min_latitude
             && latitude <= self.max_latitude
@@ -47,14 +89,38 @@ impl BoundingBox {
             || other.max_longitude < self.min_longitude)
     }
 
+    /// Width divided by height, both in degrees.
+    pub fn aspect_ratio(&self) -> f64 {
+        (self.max_longitude - self.min_longitude) / (self.max_latitude - self.min_latitude)
+    }
+
+    /// Grows the width symmetrically until the aspect ratio reaches `target`.
+    /// Boxes that are already at least that wide are returned unchanged, so
+    /// no side ever shrinks.
+    pub fn expand_to_aspect(&self, target: f64) -> BoundingBox {
+        if self.aspect_ratio() >= target {
+            return self.clone();
+        }
+        let height = self.max_latitude - self.min_latitude;
+        let grow = (height * target - (self.max_longitude - self.min_longitude)) / 2.0;
+        BoundingBox {
+            min_longitude: self.min_longitude - grow,
+            max_longitude: self.max_longitude + grow,
+            ..self.clone()
+        }
+    }
+
     pub fn extend_meters(&self, meters: i32) -> BoundingBox {
-        // Rough approximation: 1 degree = 111km at equator
-        let degree_delta = (meters as f64) / 111_000.0;
+        // Rough approximation: 1 degree = 111km at equator. Meridians converge
+        // towards the poles, so a degree of longitude shrinks by cos(latitude).
+        let latitude_delta = (meters as f64) / 111_000.0;
+        let center_latitude = self.get_center_point().latitude.to_radians();
+        let longitude_delta = latitude_delta / center_latitude.cos();
         BoundingBox {
-            min_latitude: self.min_latitude - degree_delta,
-            min_longitude: self.min_longitude - degree_delta,
-            max_latitude: self.max_latitude + degree_delta,
-            max_longitude: self.max_longitude + degree_delta,
+            min_latitude: self.min_latitude - latitude_delta,
+            min_longitude: (self.min_longitude - longitude_delta).max(-180.0),
+            max_latitude: self.max_latitude + latitude_delta,
+            max_longitude: (self.max_longitude + longitude_delta).min(180.0),
         }
     }
 }
@@ -73,25 +139,213 @@ impl LatLong {
             longitude,
         }
     }
+
+    /// Like `new`, but fails for a latitude outside [-90, 90] or a longitude
+    /// outside [-180, 180], including NaN.
+    pub fn try_new(latitude: f64, longitude: f64) -> Result<Self> {
+        if !(LatLongUtils::LATITUDE_MIN..=LatLongUtils::LATITUDE_MAX).contains(&latitude) {
+            return Err(MapFileException::new(format!(
+                "invalid latitude: {}",
+                latitude
+            )));
+        }
+        if !(LatLongUtils::LONGITUDE_MIN..=LatLongUtils::LONGITUDE_MAX).contains(&longitude) {
+            return Err(MapFileException::new(format!(
+                "invalid longitude: {}",
+                longitude
+            )));
+        }
+        Ok(Self::new(latitude, longitude))
+    }
+
+    /// Great-circle (haversine) distance to `other` in meters.
+    pub fn distance_to(&self, other: &LatLong) -> f64 {
+        let d_lat = (other.latitude - self.latitude).to_radians();
+        let d_lon = (other.longitude - self.longitude).to_radians();
+        let h = (d_lat / 2.0).sin().powi(2)
+            + self.latitude.to_radians().cos()
+                * other.latitude.to_radians().cos()
+                * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS * h.sqrt().min(1.0).asin()
+    }
+
+    /// Latitude and longitude rounded to integer microdegrees.
+    pub fn to_microdegrees(&self) -> (i32, i32) {
+        (
+            LatLongUtils::degrees_to_microdegrees(self.latitude),
+            LatLongUtils::degrees_to_microdegrees(self.longitude),
+        )
+    }
+}
+
+/// A position in integer microdegrees, the precision of the map file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LatLongE6 {
+    pub lat_e6: i32,
+    pub lon_e6: i32,
+}
+
+impl From<&LatLong> for LatLongE6 {
+    fn from(lat_long: &LatLong) -> Self {
+        let (lat_e6, lon_e6) = lat_long.to_microdegrees();
+        Self { lat_e6, lon_e6 }
+    }
+}
+
+impl From<LatLong> for LatLongE6 {
+    fn from(lat_long: LatLong) -> Self {
+        Self::from(&lat_long)
+    }
+}
+
+impl From<LatLongE6> for LatLong {
+    fn from(lat_long: LatLongE6) -> Self {
+        LatLong::new(
+            LatLongUtils::microdegrees_to_degrees(lat_long.lat_e6),
+            LatLongUtils::microdegrees_to_degrees(lat_long.lon_e6),
+        )
+    }
+}
+
+/// Drawing layer of a feature, from -5 (bottom) to 10 (top). Layer 0 is the
+/// default ground level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Layer(pub i8);
+
+impl Layer {
+    pub const MIN: i8 = -5;
+    pub const MAX: i8 = 10;
+
+    /// Position in the drawing stack, 0 for the bottom layer.
+    pub fn ordering(self) -> u8 {
+        (self.0.clamp(Self::MIN, Self::MAX) - Self::MIN) as u8
+    }
 }
 
+impl From<i8> for Layer {
+    fn from(layer: i8) -> Self {
+        Layer(layer)
+    }
+}
+
+impl PartialEq<i8> for Layer {
+    fn eq(&self, other: &i8) -> bool {
+        self.0 == *other
+    }
+}
+
+impl std::fmt::Display for Layer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+const KEY_VALUE_SEPARATOR: char = '=';
+
 #[derive(Debug, Clone)]
 pub struct Tag {
-    pub key: String,
-    pub value: String,
+    pub key: Arc<str>,
+    pub value: Arc<str>,
 }
 
 impl Tag {
-    pub fn new(key: String, value: String) -> Self {
-        Self { key, value }
+    pub fn new(key: impl Into<Arc<str>>, value: impl Into<Arc<str>>) -> Self {
+        Self {
+            key: key.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn as_key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn as_value(&self) -> &str {
+        &self.value
     }
 
-    pub fn from_string(tag: impl Into<String>) -> Self {
+    /// Parses a `key=value` tag as stored in the map header, splitting on the
+    /// first `=`. An empty value (`key=`) is allowed, while a missing key
+    /// (`=value`) or a missing `=` is rejected as malformed.
+    pub fn from_string(tag: impl Into<String>) -> Result<Self> {
         let tag = tag.into();
-        // Assuming the tag string contains both key and value
+        match tag.split_once(KEY_VALUE_SEPARATOR) {
+            Some((key, value)) if !key.is_empty() => Ok(Self::new(key, value)),
+            _ => Err(MapFileException::new(format!("malformed tag: {}", tag))),
+        }
+    }
+}
+
+/// Hands out shared strings, so that repeated names and values decoded in
+/// one read share a single allocation.
+#[derive(Debug, Default)]
+pub struct Interner {
+    strings: HashSet<Arc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn intern(&mut self, string: &str) -> Arc<str> {
+        if let Some(shared) = self.strings.get(string) {
+            return Arc::clone(shared);
+        }
+        let shared: Arc<str> = Arc::from(string);
+        self.strings.insert(Arc::clone(&shared));
+        shared
+    }
+}
+
+/// Offset of a way label from the first node of the way, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LabelPosition {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl LabelPosition {
+    pub fn from_microdegrees(latitude_offset: i32, longitude_offset: i32) -> Self {
         Self {
-            key: tag.clone(),
-            value: tag,
+            lat: LatLongUtils::microdegrees_to_degrees(latitude_offset),
+            lon: LatLongUtils::microdegrees_to_degrees(longitude_offset),
+        }
+    }
+
+    /// Reads the offset as stored in a way: latitude first, then longitude,
+    /// both VBE-S microdegrees.
+    pub(crate) fn read(read_buffer: &mut ReadBuffer<impl Read + Seek>) -> Result<Self> {
+        let latitude_offset = read_buffer.read_signed_int()?;
+        let longitude_offset = read_buffer.read_signed_int()?;
+        Ok(Self::from_microdegrees(latitude_offset, longitude_offset))
+    }
+
+    /// Absolute label position for a way whose first node is `origin`.
+    pub fn apply_to(&self, origin: &LatLong) -> LatLong {
+        LatLong::new(origin.latitude + self.lat, origin.longitude + self.lon)
+    }
+}
+
+/// How coordinates are rounded after decoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CoordinatePrecision {
+    /// Round to 6 decimals, the microdegree precision of the map file. This
+    /// removes float noise like `0.0800000001` left by summing deltas.
+    #[default]
+    Microdegrees,
+    /// Keep the decoded values as they are.
+    Raw,
+}
+
+impl CoordinatePrecision {
+    pub fn apply(self, position: LatLong) -> LatLong {
+        match self {
+            CoordinatePrecision::Microdegrees => LatLong::new(
+                LatLongUtils::round_to_microdegrees(position.latitude),
+                LatLongUtils::round_to_microdegrees(position.longitude),
+            ),
+            CoordinatePrecision::Raw => position,
         }
     }
 }
@@ -101,6 +355,8 @@ pub struct LatLongUtils;
 impl LatLongUtils {
     pub const LONGITUDE_MAX: f64 = 180.0;
     pub const LONGITUDE_MIN: f64 = -180.0;
+    pub const LATITUDE_MAX: f64 = 90.0;
+    pub const LATITUDE_MIN: f64 = -90.0;
     const CONVERSION_FACTOR: f64 = 1_000_000.0;
 
     pub fn microdegrees_to_degrees(microdegrees: i32) -> f64 {
@@ -113,6 +369,10 @@ impl LatLongUtils {
         (degrees * Self::CONVERSION_FACTOR).round() as i32
     }
 
+    pub fn round_to_microdegrees(degrees: f64) -> f64 {
+        (degrees * Self::CONVERSION_FACTOR).round() / Self::CONVERSION_FACTOR
+    }
+
     // Approximate equality check for floating-point comparisons
     pub fn approx_eq(a: f64, b: f64, epsilon: f64) -> bool {
         (a - b).abs() < epsilon
@@ -0,0 +1,482 @@
+//! Synthesizes small mapsforge `.map` files for integration tests.
+#![allow(dead_code)]
+
+use reader::{MercatorProjection, Serializer, Tile};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+const MAGIC_BYTE: &str = "mapsforge binary OSM";
+const SIGNATURE_LENGTH_BLOCK: usize = 32;
+const SIGNATURE_LENGTH_INDEX: usize = 16;
+const WATER_BIT: u64 = 0x80_0000_0000;
+
+pub struct FixturePoi {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub layer: i8,
+    pub tag_ids: Vec<u32>,
+    pub name: Option<String>,
+    pub elevation: Option<i32>,
+}
+
+pub struct FixtureWay {
+    pub layer: i8,
+    pub tag_ids: Vec<u32>,
+    pub name: Option<String>,
+    pub label_position: Option<(i32, i32)>,
+    pub tile_bitmask: u16,
+    pub segments: Vec<Vec<(f64, f64)>>,
+}
+
+#[derive(Clone, Copy)]
+pub struct FixtureSubFile {
+    pub base_zoom_level: u8,
+    pub zoom_level_min: u8,
+    pub zoom_level_max: u8,
+}
+
+pub struct MapFixture {
+    pub min_latitude: f64,
+    pub min_longitude: f64,
+    pub max_latitude: f64,
+    pub max_longitude: f64,
+    pub file_version: i32,
+    pub map_date: i64,
+    pub tile_pixel_size: i16,
+    pub projection_name: String,
+    pub debug_file: bool,
+    pub start_position: Option<(f64, f64)>,
+    pub start_zoom_level: Option<u8>,
+    pub languages_preference: Option<String>,
+    pub comment: Option<String>,
+    pub created_by: Option<String>,
+    pub poi_tags: Vec<String>,
+    pub way_tags: Vec<String>,
+    pub sub_files: Vec<FixtureSubFile>,
+    pub pois: Vec<FixturePoi>,
+    pub ways: Vec<FixtureWay>,
+    /// Base tiles (x, y) whose index entry carries the water bit.
+    pub water_tiles: Vec<(i64, i64)>,
+    /// Added to the first way offset of every block, to simulate corruption.
+    pub first_way_offset_bias: u32,
+}
+
+/// A map of about a kilometre square north-east of (0, 0), with one sub-file
+/// at base zoom level 14 covering zoom levels 0 to 16.
+pub fn small_fixture() -> MapFixture {
+    MapFixture::new(0.001, 0.001, 0.01, 0.01).sub_file(14, 0, 16)
+}
+
+/// The 256 pixel tile at `zoom` holding the point.
+pub fn tile_at(latitude: f64, longitude: f64, zoom: u8) -> Tile {
+    Tile::new(
+        MercatorProjection::longitude_to_tile_x(longitude, zoom),
+        MercatorProjection::latitude_to_tile_y(latitude, zoom),
+        zoom,
+        256,
+    )
+}
+
+// Collects formatted log output for assertions on warnings
+#[derive(Clone, Default)]
+struct LogBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for LogBuffer {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs `f` with a subscriber at the default level and returns its result
+/// together with everything that was logged.
+pub fn capture_logs<T>(f: impl FnOnce() -> T) -> (T, String) {
+    let logs = LogBuffer::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer({
+            let logs = logs.clone();
+            move || logs.clone()
+        })
+        .with_ansi(false)
+        .finish();
+    let result = tracing::subscriber::with_default(subscriber, f);
+    let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    (result, logs)
+}
+
+impl MapFixture {
+    pub fn new(
+        min_latitude: f64,
+        min_longitude: f64,
+        max_latitude: f64,
+        max_longitude: f64,
+    ) -> Self {
+        Self {
+            min_latitude,
+            min_longitude,
+            max_latitude,
+            max_longitude,
+            file_version: 3,
+            map_date: 1335871456973,
+            tile_pixel_size: 256,
+            projection_name: "Mercator".to_string(),
+            debug_file: false,
+            start_position: None,
+            start_zoom_level: None,
+            languages_preference: None,
+            comment: None,
+            created_by: None,
+            poi_tags: Vec::new(),
+            way_tags: Vec::new(),
+            sub_files: vec![FixtureSubFile {
+                base_zoom_level: 10,
+                zoom_level_min: 0,
+                zoom_level_max: 22,
+            }],
+            pois: Vec::new(),
+            ways: Vec::new(),
+            water_tiles: Vec::new(),
+            first_way_offset_bias: 0,
+        }
+    }
+
+    pub fn sub_file(mut self, base_zoom_level: u8, zoom_level_min: u8, zoom_level_max: u8) -> Self {
+        self.sub_files = vec![FixtureSubFile {
+            base_zoom_level,
+            zoom_level_min,
+            zoom_level_max,
+        }];
+        self
+    }
+
+    /// Appends a sub-file instead of replacing the existing ones.
+    pub fn add_sub_file(
+        mut self,
+        base_zoom_level: u8,
+        zoom_level_min: u8,
+        zoom_level_max: u8,
+    ) -> Self {
+        self.sub_files.push(FixtureSubFile {
+            base_zoom_level,
+            zoom_level_min,
+            zoom_level_max,
+        });
+        self
+    }
+
+    pub fn poi(mut self, latitude: f64, longitude: f64, layer: i8, tag_ids: &[u32]) -> Self {
+        self.pois.push(FixturePoi {
+            latitude,
+            longitude,
+            layer,
+            tag_ids: tag_ids.to_vec(),
+            name: None,
+            elevation: None,
+        });
+        self
+    }
+
+    pub fn way(mut self, nodes: &[(f64, f64)], layer: i8, tag_ids: &[u32]) -> Self {
+        self.ways.push(FixtureWay {
+            layer,
+            tag_ids: tag_ids.to_vec(),
+            name: None,
+            label_position: None,
+            tile_bitmask: 0xffff,
+            segments: vec![nodes.to_vec()],
+        });
+        self
+    }
+
+    /// Encodes the fixture into the bytes of a complete `.map` file.
+    pub fn build(&self) -> Vec<u8> {
+        let mut header = Vec::new();
+        Serializer::write_int(&mut header, self.file_version);
+        header.extend_from_slice(&[0; 8]); // file size, patched below
+        Serializer::write_long(&mut header, self.map_date);
+        for degrees in [
+            self.min_latitude,
+            self.min_longitude,
+            self.max_latitude,
+            self.max_longitude,
+        ] {
+            Serializer::write_int(&mut header, to_microdegrees(degrees));
+        }
+        header.extend_from_slice(&self.tile_pixel_size.to_be_bytes());
+        Serializer::write_utf8_encoded_string(&mut header, &self.projection_name);
+
+        let mut flags = 0u8;
+        if self.debug_file {
+            flags |= 0x80;
+        }
+        if self.start_position.is_some() {
+            flags |= 0x40;
+        }
+        if self.start_zoom_level.is_some() {
+            flags |= 0x20;
+        }
+        if self.languages_preference.is_some() {
+            flags |= 0x10;
+        }
+        if self.comment.is_some() {
+            flags |= 0x08;
+        }
+        if self.created_by.is_some() {
+            flags |= 0x04;
+        }
+        header.push(flags);
+        if let Some((latitude, longitude)) = self.start_position {
+            Serializer::write_int(&mut header, to_microdegrees(latitude));
+            Serializer::write_int(&mut header, to_microdegrees(longitude));
+        }
+        if let Some(zoom_level) = self.start_zoom_level {
+            header.push(zoom_level);
+        }
+        for value in [&self.languages_preference, &self.comment, &self.created_by]
+            .into_iter()
+            .flatten()
+        {
+            Serializer::write_utf8_encoded_string(&mut header, value);
+        }
+
+        for tags in [&self.poi_tags, &self.way_tags] {
+            Serializer::write_short(&mut header, tags.len() as i16);
+            for tag in tags {
+                Serializer::write_utf8_encoded_string(&mut header, tag);
+            }
+        }
+
+        header.push(self.sub_files.len() as u8);
+        let sub_file_table_offset = header.len();
+        header.resize(header.len() + self.sub_files.len() * 19, 0);
+
+        let header_length = MAGIC_BYTE.len() + 4 + header.len();
+        let mut sub_file_bytes = Vec::new();
+        let mut sub_file_table = Vec::new();
+        for sub_file in &self.sub_files {
+            let start_address = (header_length + sub_file_bytes.len()) as i64;
+            let encoded = self.build_sub_file(sub_file);
+            sub_file_table.push(sub_file.base_zoom_level);
+            sub_file_table.push(sub_file.zoom_level_min);
+            sub_file_table.push(sub_file.zoom_level_max);
+            Serializer::write_long(&mut sub_file_table, start_address);
+            Serializer::write_long(&mut sub_file_table, encoded.len() as i64);
+            sub_file_bytes.extend_from_slice(&encoded);
+        }
+        header[sub_file_table_offset..].copy_from_slice(&sub_file_table);
+
+        let file_size = (header_length + sub_file_bytes.len()) as i64;
+        header[4..12].copy_from_slice(&file_size.to_be_bytes());
+
+        let mut bytes = Vec::with_capacity(file_size as usize);
+        bytes.extend_from_slice(MAGIC_BYTE.as_bytes());
+        Serializer::write_int(&mut bytes, header.len() as i32);
+        bytes.extend_from_slice(&header);
+        bytes.extend_from_slice(&sub_file_bytes);
+        bytes
+    }
+
+    /// Writes the fixture to a uniquely named file in the temp directory.
+    pub fn write(&self, name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("gps-fixture-{}-{}.map", name, std::process::id()));
+        std::fs::write(&path, self.build()).expect("Failed to write fixture");
+        path
+    }
+
+    fn build_sub_file(&self, sub_file: &FixtureSubFile) -> Vec<u8> {
+        let zoom = sub_file.base_zoom_level;
+        let left = MercatorProjection::longitude_to_tile_x(self.min_longitude, zoom);
+        let right = MercatorProjection::longitude_to_tile_x(self.max_longitude, zoom);
+        let top = MercatorProjection::latitude_to_tile_y(self.max_latitude, zoom);
+        let bottom = MercatorProjection::latitude_to_tile_y(self.min_latitude, zoom);
+        let blocks_width = right - left + 1;
+        let blocks_height = bottom - top + 1;
+
+        let mut index_size = (blocks_width * blocks_height) as usize * 5;
+        if self.debug_file {
+            index_size += SIGNATURE_LENGTH_INDEX;
+        }
+
+        let mut index = Vec::new();
+        if self.debug_file {
+            index.extend_from_slice(&signature("+++IndexStart+++", SIGNATURE_LENGTH_INDEX));
+        }
+        let mut blocks = Vec::new();
+        for row in 0..blocks_height {
+            for column in 0..blocks_width {
+                let tile_x = left + column;
+                let tile_y = top + row;
+                let mut pointer = (index_size + blocks.len()) as u64;
+                if self.water_tiles.contains(&(tile_x, tile_y)) {
+                    pointer |= WATER_BIT;
+                }
+                index.extend_from_slice(&pointer.to_be_bytes()[3..]);
+                blocks.extend_from_slice(&self.build_block(sub_file, tile_x, tile_y));
+            }
+        }
+
+        index.extend_from_slice(&blocks);
+        index
+    }
+
+    fn build_block(&self, sub_file: &FixtureSubFile, tile_x: i64, tile_y: i64) -> Vec<u8> {
+        let zoom = sub_file.base_zoom_level;
+        let in_block = |latitude: f64, longitude: f64| {
+            MercatorProjection::longitude_to_tile_x(longitude, zoom) == tile_x
+                && MercatorProjection::latitude_to_tile_y(latitude, zoom) == tile_y
+        };
+        let pois: Vec<&FixturePoi> = self
+            .pois
+            .iter()
+            .filter(|poi| in_block(poi.latitude, poi.longitude))
+            .collect();
+        let ways: Vec<&FixtureWay> = self
+            .ways
+            .iter()
+            .filter(|way| {
+                let (latitude, longitude) = way.segments[0][0];
+                in_block(latitude, longitude)
+            })
+            .collect();
+        if pois.is_empty() && ways.is_empty() {
+            return Vec::new();
+        }
+
+        let tile_latitude = MercatorProjection::tile_y_to_latitude(tile_y, zoom);
+        let tile_longitude = MercatorProjection::tile_x_to_longitude(tile_x, zoom);
+
+        let mut block = Vec::new();
+        if self.debug_file {
+            block.extend_from_slice(&signature(
+                &format!("###TileStart{},{}###", tile_x, tile_y),
+                SIGNATURE_LENGTH_BLOCK,
+            ));
+        }
+
+        // All features are visible from the minimum zoom level upwards
+        let rows = sub_file.zoom_level_max - sub_file.zoom_level_min + 1;
+        for row in 0..rows {
+            let (number_of_pois, number_of_ways) = if row == 0 {
+                (pois.len() as u32, ways.len() as u32)
+            } else {
+                (0, 0)
+            };
+            Serializer::write_unsigned_int(&mut block, number_of_pois);
+            Serializer::write_unsigned_int(&mut block, number_of_ways);
+        }
+
+        let mut poi_bytes = Vec::new();
+        for (index, poi) in pois.iter().enumerate() {
+            if self.debug_file {
+                poi_bytes.extend_from_slice(&signature(
+                    &format!("***POIStart{}***", index),
+                    SIGNATURE_LENGTH_BLOCK,
+                ));
+            }
+            Serializer::write_signed_int(&mut poi_bytes, offset(poi.latitude, tile_latitude));
+            Serializer::write_signed_int(&mut poi_bytes, offset(poi.longitude, tile_longitude));
+            poi_bytes.push((encode_layer(poi.layer) << 4) | poi.tag_ids.len() as u8);
+            for &tag_id in &poi.tag_ids {
+                Serializer::write_unsigned_int(&mut poi_bytes, tag_id);
+            }
+            let mut feature_byte = 0u8;
+            if poi.name.is_some() {
+                feature_byte |= 0x80;
+            }
+            if poi.elevation.is_some() {
+                feature_byte |= 0x20;
+            }
+            poi_bytes.push(feature_byte);
+            if let Some(name) = &poi.name {
+                Serializer::write_utf8_encoded_string(&mut poi_bytes, name);
+            }
+            if let Some(elevation) = poi.elevation {
+                Serializer::write_signed_int(&mut poi_bytes, elevation);
+            }
+        }
+
+        Serializer::write_unsigned_int(
+            &mut block,
+            poi_bytes.len() as u32 + self.first_way_offset_bias,
+        );
+        block.extend_from_slice(&poi_bytes);
+
+        for (index, way) in ways.iter().enumerate() {
+            if self.debug_file {
+                block.extend_from_slice(&signature(
+                    &format!("---WayStart{}---", index),
+                    SIGNATURE_LENGTH_BLOCK,
+                ));
+            }
+            let way_bytes = encode_way(way, tile_latitude, tile_longitude);
+            Serializer::write_unsigned_int(&mut block, way_bytes.len() as u32);
+            block.extend_from_slice(&way_bytes);
+        }
+
+        block
+    }
+}
+
+// Layers -5..10 are stored with a bias of 5
+fn encode_layer(layer: i8) -> u8 {
+    (layer + 5) as u8
+}
+
+fn encode_way(way: &FixtureWay, tile_latitude: f64, tile_longitude: f64) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&way.tile_bitmask.to_be_bytes());
+    bytes.push((encode_layer(way.layer) << 4) | way.tag_ids.len() as u8);
+    for &tag_id in &way.tag_ids {
+        Serializer::write_unsigned_int(&mut bytes, tag_id);
+    }
+    let mut feature_byte = 0u8;
+    if way.name.is_some() {
+        feature_byte |= 0x80;
+    }
+    if way.label_position.is_some() {
+        feature_byte |= 0x10;
+    }
+    bytes.push(feature_byte);
+    if let Some(name) = &way.name {
+        Serializer::write_utf8_encoded_string(&mut bytes, name);
+    }
+    if let Some((latitude_offset, longitude_offset)) = way.label_position {
+        Serializer::write_signed_int(&mut bytes, latitude_offset);
+        Serializer::write_signed_int(&mut bytes, longitude_offset);
+    }
+
+    // A single way data block holding every segment, single-delta encoded
+    Serializer::write_unsigned_int(&mut bytes, way.segments.len() as u32);
+    for segment in &way.segments {
+        Serializer::write_unsigned_int(&mut bytes, segment.len() as u32);
+        let mut previous = (tile_latitude, tile_longitude);
+        for &(latitude, longitude) in segment {
+            Serializer::write_signed_int(&mut bytes, offset(latitude, previous.0));
+            Serializer::write_signed_int(&mut bytes, offset(longitude, previous.1));
+            previous = (
+                previous.0 + offset(latitude, previous.0) as f64 / 1_000_000.0,
+                previous.1 + offset(longitude, previous.1) as f64 / 1_000_000.0,
+            );
+        }
+    }
+    bytes
+}
+
+fn offset(degrees: f64, origin: f64) -> i32 {
+    to_microdegrees(degrees - origin)
+}
+
+fn to_microdegrees(degrees: f64) -> i32 {
+    (degrees * 1_000_000.0).round() as i32
+}
+
+fn signature(text: &str, length: usize) -> Vec<u8> {
+    let mut bytes = text.as_bytes().to_vec();
+    bytes.resize(length, b' ');
+    bytes
+}
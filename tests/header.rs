@@ -1,10 +1,17 @@
+mod common;
+
 #[cfg(test)]
 mod tests {
-    use reader::{BoundingBox, LatLong, MapFile};
+    use reader::{
+        BoundingBox, LatLong, MapFile, MapFileErrorKind, MapFileHeader, ReadBuffer, Tile,
+        WriterInfo,
+    };
 
     use super::*;
 
+    use std::io::Cursor;
     use std::path::PathBuf;
+    use std::time::UNIX_EPOCH;
 
     const BOUNDING_BOX: BoundingBox = BoundingBox {
         min_latitude: 0.1,
@@ -57,4 +64,259 @@ mod tests {
         assert_eq!(map_file_info.comment, Some(COMMENT.to_string()));
         assert_eq!(map_file_info.created_by, Some(CREATED_BY.to_string()));
     }
+
+    #[test]
+    fn test_from_bytes_matches_path_open() {
+        let mut fixture = common::MapFixture::new(0.1, 0.2, 0.3, 0.4);
+        fixture.comment = Some(COMMENT.to_string());
+        fixture.created_by = Some(CREATED_BY.to_string());
+        fixture.start_zoom_level = Some(START_ZOOM_LEVEL);
+        let path = fixture.write("from-bytes");
+
+        let from_path = MapFile::new(&path).expect("Failed to open map file");
+        let from_bytes =
+            MapFile::from_bytes(std::fs::read(&path).unwrap()).expect("Failed to open map bytes");
+
+        let expected = from_path.get_map_file_info().unwrap();
+        let actual = from_bytes.get_map_file_info().unwrap();
+        assert_eq!(actual.file_size, expected.file_size);
+        assert_eq!(actual.file_version, expected.file_version);
+        assert_eq!(actual.map_date, expected.map_date);
+        assert_eq!(actual.number_of_sub_files, expected.number_of_sub_files);
+        assert_eq!(actual.projection_name, expected.projection_name);
+        assert_eq!(actual.tile_pixel_size, expected.tile_pixel_size);
+        assert_eq!(actual.start_zoom_level, expected.start_zoom_level);
+        assert_eq!(actual.comment, expected.comment);
+        assert_eq!(actual.created_by, expected.created_by);
+        assert_eq!(
+            actual.bounding_box.min_latitude,
+            expected.bounding_box.min_latitude
+        );
+        assert_eq!(
+            actual.bounding_box.max_longitude,
+            expected.bounding_box.max_longitude
+        );
+    }
+
+    #[test]
+    fn test_read_info_only() {
+        let mut fixture = common::MapFixture::new(0.1, 0.2, 0.3, 0.4);
+        fixture.comment = Some(COMMENT.to_string());
+        let path = fixture.write("info-only");
+
+        let info = MapFile::read_info_only(&path).unwrap();
+        let map_file = MapFile::new(&path).unwrap();
+        let expected = map_file.get_map_file_info().unwrap();
+        assert_eq!(info.map_date, expected.map_date);
+        assert_eq!(info.comment, expected.comment);
+        assert_eq!(
+            info.bounding_box.min_latitude,
+            expected.bounding_box.min_latitude
+        );
+        assert_eq!(
+            info.bounding_box.max_longitude,
+            expected.bounding_box.max_longitude
+        );
+
+        // No sub-file parameters are built
+        let bytes = fixture.build();
+        let file_size = bytes.len() as i64;
+        let mut header = MapFileHeader::new();
+        header
+            .read_info_only(&mut ReadBuffer::new(Cursor::new(bytes)), file_size)
+            .unwrap();
+        assert!(header.get_map_file_info().is_some());
+        assert!(header.get_sub_file_parameter(0).is_none());
+    }
+
+    #[test]
+    fn test_info_snapshot() {
+        let mut fixture = common::MapFixture::new(0.1, 0.2, 0.3, 0.4);
+        fixture.comment = Some(COMMENT.to_string());
+        let map_file = MapFile::from_bytes(fixture.build()).unwrap();
+        let snapshot = map_file.info_snapshot().unwrap();
+        drop(map_file);
+
+        let (comment, max_longitude) =
+            std::thread::spawn(move || (snapshot.comment, snapshot.bounding_box.max_longitude))
+                .join()
+                .unwrap();
+        assert_eq!(comment.as_deref(), Some(COMMENT));
+        assert_eq!(max_longitude, 0.4);
+    }
+
+    #[test]
+    fn test_map_date_as_system_time() {
+        let mut fixture = common::MapFixture::new(0.1, 0.2, 0.3, 0.4);
+        fixture.map_date = MAP_DATE;
+        let map_file = MapFile::from_bytes(fixture.build()).unwrap();
+        let info = map_file.get_map_file_info().unwrap();
+
+        let millis = info
+            .map_date_as_system_time()
+            .unwrap()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        assert_eq!(millis, MAP_DATE as u128);
+    }
+
+    #[test]
+    fn test_data_timestamp_in_seconds() {
+        let mut fixture = common::MapFixture::new(0.1, 0.2, 0.3, 0.4);
+        fixture.map_date = MAP_DATE;
+        let map_file = MapFile::from_bytes(fixture.build()).unwrap();
+
+        assert_eq!(
+            map_file.get_data_timestamp(&Tile::new(0, 0, 0, 256)),
+            MAP_DATE / 1000
+        );
+    }
+
+    #[test]
+    fn test_writer_version() {
+        let mut fixture = common::MapFixture::new(0.1, 0.2, 0.3, 0.4);
+        fixture.created_by = Some(CREATED_BY.to_string());
+        let map_file = MapFile::from_bytes(fixture.build()).unwrap();
+        let info = map_file.get_map_file_info().unwrap();
+
+        assert_eq!(
+            info.writer_version(),
+            Some(WriterInfo {
+                tool: "mapsforge-map-writer".to_string(),
+                major: 0,
+                minor: 3,
+                patch: 1,
+                pre_release: Some("SNAPSHOT".to_string()),
+            })
+        );
+
+        for (created_by, expected) in [
+            (Some("osmosis-mapsforge-writer-0.21"), Some((0, 21, 0))),
+            (Some("mapsforge-map-writer"), None),
+            (Some("writer-1.x"), None),
+            (None, None),
+        ] {
+            let mut fixture = common::MapFixture::new(0.1, 0.2, 0.3, 0.4);
+            fixture.created_by = created_by.map(str::to_string);
+            let map_file = MapFile::from_bytes(fixture.build()).unwrap();
+            let version = map_file.get_map_file_info().unwrap().writer_version();
+            assert_eq!(
+                version.map(|info| (info.major, info.minor, info.patch)),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_sub_file_parameters() {
+        let bytes = common::MapFixture::new(0.1, 0.2, 0.3, 0.4)
+            .sub_file(5, 0, 7)
+            .add_sub_file(10, 8, 11)
+            .add_sub_file(14, 12, 21)
+            .build();
+        let map_file = MapFile::from_bytes(bytes).unwrap();
+
+        let base_zoom_levels: Vec<u8> = map_file
+            .sub_files()
+            .iter()
+            .map(|sub_file| sub_file.base_zoom_level)
+            .collect();
+        assert_eq!(base_zoom_levels, vec![5, 10, 14]);
+        assert_eq!(map_file.header.sub_file_parameters(), map_file.sub_files());
+    }
+
+    #[test]
+    fn test_block_number_overflow() {
+        let bytes = common::MapFixture::new(0.1, 0.2, 0.3, 0.4)
+            .sub_file(14, 0, 16)
+            .build();
+        let map_file = MapFile::from_bytes(bytes).unwrap();
+        let mut sub_file = map_file.sub_files()[0].clone();
+        assert_eq!(
+            sub_file.block_number(1, 1).unwrap(),
+            sub_file.blocks_width + 1
+        );
+
+        sub_file.blocks_width = i64::MAX - 1;
+        assert_eq!(sub_file.block_number(0, 1).unwrap(), 1);
+        assert_eq!(sub_file.block_number(1, 1).unwrap(), i64::MAX);
+        let error = sub_file.block_number(2, 0).unwrap_err();
+        assert!(error.to_string().contains("overflow"), "{}", error);
+        assert!(sub_file.block_number(1, 2).is_err());
+    }
+
+    #[test]
+    fn test_lenient_file_size() {
+        let mut bytes = common::MapFixture::new(0.1, 0.2, 0.3, 0.4).build();
+        // The file size follows the magic bytes, header size and file version
+        let declared_size = bytes.len() as i64 + 100;
+        bytes[28..36].copy_from_slice(&declared_size.to_be_bytes());
+
+        let error = MapFile::from_bytes(bytes.clone()).err().unwrap();
+        assert!(error.to_string().contains(&declared_size.to_string()));
+        assert!(error.to_string().contains(&bytes.len().to_string()));
+
+        let (map_file, logs) = common::capture_logs(|| {
+            MapFile::builder()
+                .lenient_file_size(true)
+                .from_bytes(bytes.clone())
+        });
+        let map_file = map_file.unwrap();
+
+        assert_eq!(
+            map_file.get_map_file_info().unwrap().file_size,
+            bytes.len() as i64
+        );
+        assert!(logs.contains("WARN"));
+        assert!(logs.contains("file size mismatch"));
+    }
+
+    #[test]
+    fn test_unsupported_projection() {
+        let mut fixture = common::MapFixture::new(0.1, 0.2, 0.3, 0.4);
+        fixture.projection_name = "Lambert".to_string();
+
+        let error = MapFile::from_bytes(fixture.build()).err().unwrap();
+        assert_eq!(
+            error.kind(),
+            &MapFileErrorKind::UnsupportedProjection("Lambert".to_string())
+        );
+        assert!(error.to_string().contains("Lambert"));
+    }
+
+    #[test]
+    fn test_raw_header_bytes() {
+        let bytes = common::MapFixture::new(0.1, 0.2, 0.3, 0.4).build();
+        let map_file = MapFile::from_bytes(bytes.clone()).unwrap();
+        let raw = map_file.raw_header_bytes();
+
+        assert!(raw.starts_with(b"mapsforge binary OSM"));
+        // The magic bytes and header size precede the remaining header
+        let remaining = i32::from_be_bytes(raw[20..24].try_into().unwrap()) as usize;
+        assert_eq!(raw.len(), 24 + remaining);
+        assert_eq!(raw, &bytes[..raw.len()]);
+    }
+
+    #[test]
+    fn test_bounding_box_clamped_to_mercator() {
+        let bytes = common::MapFixture::new(-89.0, 0.2, 89.0, 0.4).build();
+        let map_file = MapFile::from_bytes(bytes).unwrap();
+        let info = map_file.get_map_file_info().unwrap();
+
+        assert!((info.bounding_box.max_latitude - 85.0511).abs() < 1e-4);
+        assert!((info.bounding_box.min_latitude + 85.0511).abs() < 1e-4);
+        assert_eq!(info.bounding_box.min_longitude, 0.2);
+        assert_eq!(info.bounding_box.max_longitude, 0.4);
+        assert_eq!(info.raw_bounding_box.max_latitude, 89.0);
+        assert_eq!(info.raw_bounding_box.min_latitude, -89.0);
+
+        // Boxes within the Mercator range are left alone
+        let bytes = common::MapFixture::new(0.1, 0.2, 0.3, 0.4).build();
+        let map_file = MapFile::from_bytes(bytes).unwrap();
+        let info = map_file.get_map_file_info().unwrap();
+        assert_eq!(info.bounding_box.min_latitude, 0.1);
+        assert_eq!(info.bounding_box.max_latitude, 0.3);
+        assert_eq!(info.raw_bounding_box.max_latitude, 0.3);
+    }
 }
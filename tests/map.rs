@@ -1,7 +1,14 @@
+mod common;
+
 #[cfg(test)]
 mod tests {
     use env_logger;
-    use reader::{Deserializer, LatLong, MapFile, MercatorProjection, QueryParameters, Tile};
+    use reader::{
+        decode_way_coordinates, BoundingBox, CoordinatePrecision, Deserializer, FeatureFlags,
+        LatLong, Layer, MapFile, MapFileErrorKind, MapFileHeader, MapReadResult,
+        MercatorProjection, PoiFilter, PoiWayBundle, QueryCalculations, QueryParameters,
+        ReadBuffer, ReadObserver, ReadOptions, Serializer, Tag, Tile, TracingObserver,
+    };
     use tracing::{error, info};
 
     fn init() {
@@ -10,7 +17,9 @@ mod tests {
 
     use super::*;
 
+    use std::io::Cursor;
     use std::path::PathBuf;
+    use std::sync::Arc;
 
     #[test]
     fn test_deserializer() {
@@ -37,6 +46,31 @@ mod tests {
         let buffer = vec![0, 127];
         assert_eq!(Deserializer::get_short(&buffer, 0), 127);
     }
+
+    #[test]
+    fn test_bounding_box_range() {
+        let tile = Tile::new(8, 5, 4, 256);
+        let single = Tile::get_bounding_box_range(&tile, &tile);
+        let expected = tile.get_bounding_box();
+        assert_eq!(single.min_latitude, expected.min_latitude);
+        assert_eq!(single.min_longitude, expected.min_longitude);
+        assert_eq!(single.max_latitude, expected.max_latitude);
+        assert_eq!(single.max_longitude, expected.max_longitude);
+
+        // The lower right tile is included, not just its north-west corner
+        let lower_right = Tile::new(9, 6, 4, 256);
+        let range = Tile::get_bounding_box_range(&tile, &lower_right);
+        assert_eq!(range.max_latitude, expected.max_latitude);
+        assert_eq!(range.min_longitude, expected.min_longitude);
+        assert_eq!(
+            range.min_latitude,
+            lower_right.get_bounding_box().min_latitude
+        );
+        assert_eq!(
+            range.max_longitude,
+            lower_right.get_bounding_box().max_longitude
+        );
+    }
     fn run_encoding_test(map_file: &mut MapFile) {
         init();
         const ZOOM_LEVEL: u8 = 8;
@@ -93,6 +127,56 @@ mod tests {
         info!("Actual: {:?}", way.way_nodes);
         assert_eq!(way.way_nodes, expected_coords);
     }
+    fn way_data_block(offsets: &[(i32, i32)]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        Serializer::write_unsigned_int(&mut bytes, 1);
+        Serializer::write_unsigned_int(&mut bytes, offsets.len() as u32);
+        for &(latitude, longitude) in offsets {
+            Serializer::write_signed_int(&mut bytes, latitude);
+            Serializer::write_signed_int(&mut bytes, longitude);
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_decode_way_coordinates() {
+        let expected = vec![vec![
+            LatLong::new(0.0, 0.0),
+            LatLong::new(0.0, 0.1),
+            LatLong::new(-0.1, 0.1),
+            LatLong::new(-0.1, 0.0),
+            LatLong::new(0.0, 0.0),
+        ]];
+
+        let single_delta = way_data_block(&[
+            (0, 0),
+            (0, 100_000),
+            (-100_000, 0),
+            (0, -100_000),
+            (100_000, 0),
+        ]);
+        assert_eq!(
+            decode_way_coordinates(&single_delta, 0.0, 0.0, false).unwrap(),
+            expected
+        );
+
+        let double_delta = way_data_block(&[
+            (0, 0),
+            (0, 100_000),
+            (-100_000, -100_000),
+            (100_000, -100_000),
+            (100_000, 100_000),
+        ]);
+        assert_eq!(
+            decode_way_coordinates(&double_delta, 0.0, 0.0, true).unwrap(),
+            expected
+        );
+
+        // A single node is not a valid way segment
+        let invalid = way_data_block(&[(0, 0)]);
+        assert!(decode_way_coordinates(&invalid, 0.0, 0.0, false).is_err());
+    }
+
     #[test]
     fn test_double_delta_encoding() {
         let mut map_file =
@@ -174,6 +258,7 @@ mod tests {
         let tile_x = MercatorProjection::longitude_to_tile_x(0.04, 10);
         let tile_y = MercatorProjection::latitude_to_tile_y(0.04, 10);
         let tile = Tile::new(tile_x, tile_y, 10, 256);
+        assert!(map_file.check_debug_signatures(&tile).unwrap());
 
         let map_read_result = map_file.read_map_data(&tile).unwrap();
         assert_eq!(map_read_result.poi_way_bundles.len(), 1);
@@ -192,6 +277,1702 @@ mod tests {
         // Check way coordinates and tags...
     }
 
+    #[test]
+    fn test_read_map_data_across_antimeridian() {
+        let path = common::MapFixture::new(-10.0, -179.9, 10.0, 179.9)
+            .sub_file(2, 0, 4)
+            .poi(5.0, 170.0, 0, &[])
+            .poi(5.0, -170.0, 0, &[])
+            .write("antimeridian");
+        let mut map_file = MapFile::new(&path).unwrap();
+
+        let upper_left = Tile::new(3, 1, 2, 256);
+        let lower_right = Tile::new(0, 1, 2, 256);
+        let map_read_result = map_file
+            .read_map_data_range(&upper_left, &lower_right)
+            .unwrap();
+
+        let longitudes: Vec<f64> = map_read_result
+            .poi_way_bundles
+            .iter()
+            .flat_map(|bundle| bundle.pois.iter().map(|poi| poi.position.longitude))
+            .collect();
+        assert_eq!(longitudes.len(), 2);
+        assert!(approx_equal(longitudes[0], 170.0, 0.0001));
+        assert!(approx_equal(longitudes[1], -170.0, 0.0001));
+    }
+
+    #[test]
+    fn test_flat_iterators() {
+        let path = common::MapFixture::new(0.0, 0.0, 0.1, 0.1)
+            .sub_file(14, 0, 16)
+            .poi(0.01, 0.01, 0, &[])
+            .poi(0.05, 0.09, 0, &[])
+            .way(&[(0.01, 0.01), (0.02, 0.02)], 0, &[])
+            .way(&[(0.06, 0.08), (0.07, 0.09)], 0, &[])
+            .way(&[(0.06, 0.01), (0.07, 0.02)], 0, &[])
+            .write("flat-iterators");
+        let mut map_file = MapFile::new(&path).unwrap();
+
+        let upper_left = common::tile_at(0.1, 0.0, 14);
+        let lower_right = common::tile_at(0.0, 0.1, 14);
+        let map_read_result = map_file
+            .read_map_data_range(&upper_left, &lower_right)
+            .unwrap();
+        assert!(map_read_result.poi_way_bundles.len() > 1);
+
+        let nested_ways: usize = map_read_result
+            .poi_way_bundles
+            .iter()
+            .map(|bundle| bundle.ways.len())
+            .sum();
+        let nested_pois: usize = map_read_result
+            .poi_way_bundles
+            .iter()
+            .map(|bundle| bundle.pois.len())
+            .sum();
+        assert_eq!(map_read_result.ways().count(), nested_ways);
+        assert_eq!(map_read_result.pois().count(), nested_pois);
+        assert_eq!(nested_ways, 3);
+        assert_eq!(nested_pois, 2);
+
+        assert_eq!(map_read_result.clone().into_ways().count(), nested_ways);
+        assert_eq!(map_read_result.into_pois().count(), nested_pois);
+    }
+
+    #[test]
+    fn test_read_map_data_projected() {
+        let path = common::MapFixture::new(0.0, 0.0, 0.1, 0.1)
+            .sub_file(14, 0, 16)
+            .poi(0.004, 0.003, 0, &[])
+            .way(&[(0.001, 0.001), (0.005, 0.006), (0.009, 0.002)], 0, &[])
+            .write("projected");
+        let mut map_file = MapFile::new(&path).unwrap();
+
+        let tile = common::tile_at(0.005, 0.005, 14);
+        let projected = map_file.read_map_data_projected(&tile).unwrap();
+        let tile_left = (tile.tile_x * 256) as f64;
+        let tile_top = (tile.tile_y * 256) as f64;
+
+        assert_eq!(projected.pois.len(), 1);
+        let (x, y) = projected.pois[0].position;
+        assert!(approx_equal(
+            x as f64,
+            MercatorProjection::longitude_to_pixel_x(0.003, 14) - tile_left,
+            1.0
+        ));
+        assert!(approx_equal(
+            y as f64,
+            MercatorProjection::latitude_to_pixel_y(0.004, 14) - tile_top,
+            1.0
+        ));
+
+        assert_eq!(projected.ways.len(), 1);
+        let (x, y) = projected.ways[0].way_nodes[0][1];
+        assert!(approx_equal(
+            x as f64,
+            MercatorProjection::longitude_to_pixel_x(0.006, 14) - tile_left,
+            1.0
+        ));
+        assert!(approx_equal(
+            y as f64,
+            MercatorProjection::latitude_to_pixel_y(0.005, 14) - tile_top,
+            1.0
+        ));
+    }
+
+    #[test]
+    fn test_index_cache_stats() {
+        let bytes = common::small_fixture().poi(0.005, 0.005, 0, &[]).build();
+        let mut map_file = MapFile::from_bytes(bytes).unwrap();
+
+        let tile = common::tile_at(0.005, 0.005, 14);
+        assert_eq!(map_file.index_cache_stats().misses, 0);
+
+        map_file.read_map_data(&tile).unwrap();
+        map_file.read_map_data(&tile).unwrap();
+
+        // The index block is prefetched once, then each read looks it up
+        let stats = map_file.index_cache_stats();
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.len, 1);
+    }
+
+    #[test]
+    fn test_index_cache_disabled() {
+        let bytes = common::small_fixture().poi(0.005, 0.005, 0, &[]).build();
+        let mut map_file = MapFile::builder()
+            .index_cache_size(0)
+            .from_bytes(bytes)
+            .unwrap();
+
+        let tile = common::tile_at(0.005, 0.005, 14);
+        for _ in 0..2 {
+            let result = map_file.read_map_data(&tile).unwrap();
+            assert_eq!(result.pois().count(), 1);
+        }
+
+        // Every lookup went to the file
+        let stats = map_file.index_cache_stats();
+        assert_eq!(stats.capacity, 0);
+        assert_eq!(stats.len, 0);
+        assert_eq!(stats.hits, 0);
+        assert_eq!(stats.misses, 2);
+    }
+
+    #[test]
+    fn test_tile_outside_sub_file() {
+        let bytes = common::small_fixture().poi(0.005, 0.005, 0, &[]).build();
+        let mut map_file = MapFile::from_bytes(bytes).unwrap();
+        let sub_file = map_file.header.get_sub_file_parameter(14).unwrap().clone();
+
+        for (tile_x, tile_y) in [
+            (sub_file.boundary_tile_right + 1, sub_file.boundary_tile_top),
+            (sub_file.boundary_tile_left - 1, sub_file.boundary_tile_top),
+            (
+                sub_file.boundary_tile_left,
+                sub_file.boundary_tile_bottom + 1,
+            ),
+        ] {
+            let mut progress_calls = 0;
+            let result = map_file
+                .read_map_data_with_progress(&Tile::new(tile_x, tile_y, 14, 256), &mut |_, _| {
+                    progress_calls += 1
+                })
+                .unwrap();
+            assert_eq!(result.pois().count(), 0);
+            assert_eq!(result.ways().count(), 0);
+            // Short-circuited before any block was visited
+            assert_eq!(progress_calls, 0);
+        }
+        assert_eq!(map_file.index_cache_stats().misses, 0);
+
+        let tile = Tile::new(
+            sub_file.boundary_tile_left,
+            sub_file.boundary_tile_top,
+            14,
+            256,
+        );
+        let mut progress_calls = 0;
+        map_file
+            .read_map_data_with_progress(&tile, &mut |_, _| progress_calls += 1)
+            .unwrap();
+        assert!(progress_calls > 0);
+    }
+
+    #[test]
+    fn test_read_tiles() {
+        let bytes = common::MapFixture::new(0.001, 0.001, 0.03, 0.03)
+            .sub_file(14, 0, 16)
+            .poi(0.005, 0.005, 0, &[])
+            .poi(0.005, 0.028, 0, &[])
+            .way(&[(0.004, 0.004), (0.006, 0.027)], 0, &[])
+            .build();
+        let mut map_file = MapFile::from_bytes(bytes.clone()).unwrap();
+        let tile_x = MercatorProjection::longitude_to_tile_x(0.005, 14);
+        let tile_y = MercatorProjection::latitude_to_tile_y(0.005, 14);
+        let tiles: Vec<Tile> = (0..3)
+            .map(|offset| Tile::new(tile_x + offset, tile_y, 14, 256))
+            .collect();
+
+        let results = map_file.read_tiles(&tiles).unwrap();
+        assert_eq!(results.len(), 3);
+
+        let mut single = MapFile::from_bytes(bytes).unwrap();
+        for tile in &tiles {
+            let expected = single.read_map_data(tile).unwrap();
+            let actual = &results[&(tile.tile_x, tile.tile_y, tile.zoom_level)];
+            assert_eq!(actual.content_hash(), expected.content_hash());
+            assert_eq!(actual.pois().count(), expected.pois().count());
+            assert_eq!(actual.ways().count(), expected.ways().count());
+        }
+    }
+
+    #[test]
+    fn test_read_iter() {
+        let bytes = common::MapFixture::new(0.001, 0.001, 0.03, 0.03)
+            .sub_file(14, 0, 16)
+            .poi(0.005, 0.005, 0, &[])
+            .poi(0.005, 0.028, 0, &[])
+            .poi(0.028, 0.005, 0, &[])
+            .way(&[(0.004, 0.004), (0.006, 0.027)], 0, &[])
+            .build();
+        let mut map_file = MapFile::from_bytes(bytes).unwrap();
+        let tile = common::tile_at(0.015, 0.015, 12);
+
+        let expected = map_file.read_map_data(&tile).unwrap();
+        let mut streamed = MapReadResult::new();
+        for bundle in map_file.read_iter(&tile).unwrap() {
+            streamed.add(bundle.unwrap());
+        }
+        assert!(expected.poi_way_bundles.len() > 1);
+        assert_eq!(
+            streamed.poi_way_bundles.len(),
+            expected.poi_way_bundles.len()
+        );
+        for (actual, expected) in streamed
+            .poi_way_bundles
+            .iter()
+            .zip(&expected.poi_way_bundles)
+        {
+            assert_eq!(actual.pois.len(), expected.pois.len());
+            assert_eq!(actual.ways.len(), expected.ways.len());
+        }
+        assert_eq!(streamed.content_hash(), expected.content_hash());
+
+        // Stopping early leaves the remaining blocks unread
+        assert_eq!(map_file.read_iter(&tile).unwrap().take(1).count(), 1);
+    }
+
+    #[test]
+    fn test_corrupt_block_is_skipped() {
+        let bytes = common::MapFixture::new(0.001, 0.001, 0.03, 0.03)
+            .sub_file(14, 0, 16)
+            .poi(0.005, 0.005, 0, &[])
+            .poi(0.005, 0.028, 0, &[])
+            // No POI tags are declared, so this block fails to decode
+            .poi(0.028, 0.005, 0, &[99])
+            .build();
+        let mut map_file = MapFile::from_bytes(bytes).unwrap();
+        let tile = common::tile_at(0.015, 0.015, 12);
+
+        let result = map_file.read_map_data(&tile).unwrap();
+        assert_eq!(result.poi_way_bundles.len(), 2);
+        let mut longitudes: Vec<f64> = result.pois().map(|poi| poi.position.longitude).collect();
+        longitudes.sort_by(f64::total_cmp);
+        assert_eq!(longitudes.len(), 2);
+        assert!(approx_equal(longitudes[0], 0.005, 1e-6));
+        assert!(approx_equal(longitudes[1], 0.028, 1e-6));
+    }
+
+    #[test]
+    fn test_water_grid() {
+        let mut fixture = common::MapFixture::new(0.001, 0.001, 0.03, 0.03)
+            .sub_file(14, 0, 16)
+            .poi(0.005, 0.005, 0, &[]);
+        let tile_x = MercatorProjection::longitude_to_tile_x(0.005, 14);
+        let tile_y = MercatorProjection::latitude_to_tile_y(0.005, 14);
+        // The sea covers the eastern column of the coast
+        fixture.water_tiles = vec![(tile_x + 1, tile_y - 1), (tile_x + 1, tile_y)];
+        let mut map_file = MapFile::from_bytes(fixture.build()).unwrap();
+
+        let upper_left = Tile::new(tile_x, tile_y - 1, 14, 256);
+        let lower_right = Tile::new(tile_x + 1, tile_y, 14, 256);
+        let grid = map_file.water_grid(&upper_left, &lower_right).unwrap();
+        assert_eq!(grid, vec![vec![false, true], vec![false, true]]);
+        assert!(grid.iter().flatten().any(|&water| water));
+        assert!(grid.iter().flatten().any(|&water| !water));
+
+        let outside = Tile::new(0, 0, 14, 256);
+        assert!(map_file.water_grid(&outside, &outside).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_read_map_data_incremental() {
+        let bytes = common::MapFixture::new(0.001, 0.001, 0.03, 0.03)
+            .sub_file(14, 0, 16)
+            .poi(0.005, 0.005, 0, &[])
+            .poi(0.005, 0.028, 0, &[])
+            .way(&[(0.004, 0.004), (0.006, 0.027)], 0, &[])
+            .build();
+        let mut map_file = MapFile::from_bytes(bytes.clone()).unwrap();
+        let mut fresh = MapFile::from_bytes(bytes).unwrap();
+        let tile_x = MercatorProjection::longitude_to_tile_x(0.005, 14);
+        let tile_y = MercatorProjection::latitude_to_tile_y(0.005, 14);
+        let prev = Tile::new(tile_x, tile_y, 14, 256);
+        let prev_result = map_file.read_map_data(&prev).unwrap();
+
+        for new in [Tile::new(tile_x + 1, tile_y, 14, 256), prev.clone()] {
+            let actual = map_file
+                .read_map_data_incremental(&prev, &prev_result, &new)
+                .unwrap();
+            let expected = fresh.read_map_data(&new).unwrap();
+            assert_eq!(actual.content_hash(), expected.content_hash());
+            assert_eq!(actual.pois().count(), expected.pois().count());
+            assert_eq!(actual.ways().count(), expected.ways().count());
+            assert_eq!(actual.is_water, expected.is_water);
+        }
+    }
+
+    #[test]
+    fn test_read_map_data_incremental_onto_water() {
+        let mut fixture = common::MapFixture::new(0.001, 0.001, 0.03, 0.03)
+            .sub_file(14, 0, 16)
+            .poi(0.005, 0.005, 0, &[]);
+        let tile_x = MercatorProjection::longitude_to_tile_x(0.005, 14);
+        let tile_y = MercatorProjection::latitude_to_tile_y(0.005, 14);
+        // The eastern column is sea, so the 2x2 zoom-13 tile is mixed
+        fixture.water_tiles = vec![(tile_x + 1, tile_y - 1), (tile_x + 1, tile_y)];
+        let bytes = fixture.build();
+        let mut map_file = MapFile::from_bytes(bytes.clone()).unwrap();
+        let mut fresh = MapFile::from_bytes(bytes).unwrap();
+
+        let prev = Tile::new(tile_x / 2, tile_y / 2, 13, 256);
+        let prev_result = map_file.read_map_data(&prev).unwrap();
+        assert!(!prev_result.is_water);
+
+        let water = Tile::new(tile_x + 1, tile_y, 14, 256);
+        for (prev, prev_result, new) in [
+            (prev.clone(), prev_result, water.clone()),
+            (
+                water.clone(),
+                map_file.read_map_data(&water).unwrap(),
+                water.clone(),
+            ),
+            // The flag comes from the index, not from the previous result
+            (water.clone(), MapReadResult::new(), water.clone()),
+        ] {
+            let actual = map_file
+                .read_map_data_incremental(&prev, &prev_result, &new)
+                .unwrap();
+            let expected = fresh.read_map_data(&new).unwrap();
+            assert!(expected.is_water);
+            assert_eq!(actual.is_water, expected.is_water);
+            assert_eq!(actual.content_hash(), expected.content_hash());
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_read_map_data() {
+        let bytes = common::small_fixture()
+            .poi(0.005, 0.005, 0, &[])
+            .way(&[(0.004, 0.004), (0.006, 0.006)], 0, &[])
+            .build();
+        let tile = common::tile_at(0.005, 0.005, 14);
+
+        let expected = MapFile::from_bytes(bytes.clone())
+            .unwrap()
+            .read_map_data(&tile)
+            .unwrap();
+        let map_file = reader::AsyncMapFile::new(MapFile::from_bytes(bytes).unwrap());
+        let actual = map_file.read_map_data(&tile).await.unwrap();
+
+        assert_eq!(actual.pois().count(), 1);
+        assert_eq!(actual.ways().count(), 1);
+        assert_eq!(actual.content_hash(), expected.content_hash());
+    }
+
+    #[test]
+    fn test_explain_query() {
+        let bytes = common::small_fixture().build();
+        let map_file = MapFile::from_bytes(bytes).unwrap();
+
+        for (zoom, use_tile_bitmask) in [(12, false), (14, false), (15, true), (16, true)] {
+            let tile = common::tile_at(0.005, 0.005, zoom);
+            let query = map_file.explain_query(&tile).unwrap();
+            assert_eq!(query.use_tile_bitmask, use_tile_bitmask, "zoom {}", zoom);
+            assert_eq!(query.query_zoom_level, zoom as i32);
+            assert!(query.from_block_x <= query.to_block_x);
+            assert!(query.from_block_y <= query.to_block_y);
+        }
+        // Nothing was read
+        assert_eq!(map_file.index_cache_stats().misses, 0);
+    }
+
+    #[test]
+    fn test_bitmask_popcount() {
+        assert_eq!(QueryCalculations::bitmask_popcount(0xffff), 16);
+        assert_eq!(QueryCalculations::bitmask_popcount(0xcc00), 4);
+        assert_eq!(QueryCalculations::bitmask_popcount(0x80), 1);
+        assert_eq!(QueryCalculations::bitmask_popcount(0), 0);
+
+        let bytes = common::small_fixture().build();
+        let map_file = MapFile::from_bytes(bytes).unwrap();
+        for (zoom, covered) in [(15, 4), (16, 1)] {
+            let tile = common::tile_at(0.005, 0.005, zoom);
+            let query = map_file.explain_query(&tile).unwrap();
+            assert_eq!(
+                QueryCalculations::bitmask_popcount(query.query_tile_bitmask),
+                covered
+            );
+        }
+    }
+
+    #[test]
+    fn test_lenient_way_offset() {
+        let mut fixture = common::small_fixture().poi(0.005, 0.005, 0, &[]).way(
+            &[(0.005, 0.005), (0.006, 0.006)],
+            0,
+            &[],
+        );
+        // Points the first way past the end of the block
+        fixture.first_way_offset_bias = 10_000;
+        let bytes = fixture.build();
+        let tile = common::tile_at(0.005, 0.005, 14);
+
+        // By default the whole block is dropped
+        let mut strict = MapFile::from_bytes(bytes.clone()).unwrap();
+        let result = strict.read_map_data(&tile).unwrap();
+        assert_eq!(result.pois().count(), 0);
+        assert_eq!(result.ways().count(), 0);
+
+        let mut lenient = MapFile::builder()
+            .lenient_way_offset(true)
+            .from_bytes(bytes)
+            .unwrap();
+        let result = lenient.read_map_data(&tile).unwrap();
+        assert_eq!(result.pois().count(), 1);
+        assert_eq!(result.ways().count(), 0);
+    }
+
+    #[test]
+    fn test_read_options() {
+        let mut fixture = common::small_fixture().poi(0.005, 0.005, 0, &[]).way(
+            &[(0.005, 0.005), (0.006, 0.006)],
+            0,
+            &[],
+        );
+        fixture.first_way_offset_bias = 10_000;
+        let tile = common::tile_at(0.005, 0.005, 14);
+
+        let options = ReadOptions {
+            lenient_way_offset: true,
+            ..ReadOptions::default()
+        };
+        let mut map_file = MapFile::builder()
+            .options(options)
+            .from_bytes(fixture.build())
+            .unwrap();
+        assert!(map_file.options().lenient_way_offset);
+        assert_eq!(map_file.read_map_data(&tile).unwrap().pois().count(), 1);
+
+        // Options changed after opening apply to the next read
+        map_file.options_mut().lenient_way_offset = false;
+        assert_eq!(map_file.read_map_data(&tile).unwrap().pois().count(), 0);
+    }
+
+    #[test]
+    fn test_include_bboxes() {
+        let bytes = common::small_fixture()
+            .way(&[(0.004, 0.004), (0.006, 0.007)], 0, &[])
+            .way(&[(0.002, 0.008), (0.005, 0.003), (0.003, 0.006)], 0, &[])
+            .build();
+        let tile = common::tile_at(0.005, 0.005, 14);
+
+        let mut map_file = MapFile::from_bytes(bytes.clone()).unwrap();
+        let result = map_file.read_map_data(&tile).unwrap();
+        assert!(result.ways().all(|way| way.bbox.is_none()));
+
+        let mut map_file = MapFile::builder()
+            .include_bboxes(true)
+            .from_bytes(bytes)
+            .unwrap();
+        let result = map_file.read_map_data(&tile).unwrap();
+        assert_eq!(result.ways().count(), 2);
+        for way in result.ways() {
+            let nodes: Vec<LatLong> = way.way_nodes.iter().flatten().cloned().collect();
+            let expected = BoundingBox::from_lat_longs(&nodes).unwrap();
+            let bbox = way.bbox.as_ref().unwrap();
+            assert_eq!(bbox.min_latitude, expected.min_latitude);
+            assert_eq!(bbox.min_longitude, expected.min_longitude);
+            assert_eq!(bbox.max_latitude, expected.max_latitude);
+            assert_eq!(bbox.max_longitude, expected.max_longitude);
+        }
+    }
+
+    #[test]
+    fn test_keep_all_geometry() {
+        let bytes = common::MapFixture::new(0.001, 0.001, 0.02, 0.02)
+            .sub_file(14, 0, 16)
+            .way(&[(0.004, 0.004), (0.005, 0.005)], 0, &[])
+            .way(&[(0.015, 0.015), (0.016, 0.016)], 0, &[])
+            .build();
+        let tile = common::tile_at(0.004, 0.004, 16);
+
+        let mut filtered = MapFile::from_bytes(bytes.clone()).unwrap();
+        let filtered_ways = filtered.read_map_data(&tile).unwrap().ways().count();
+        assert_eq!(filtered_ways, 1);
+
+        let mut unfiltered = MapFile::builder()
+            .keep_all_geometry(true)
+            .from_bytes(bytes)
+            .unwrap();
+        let unfiltered_ways = unfiltered.read_map_data(&tile).unwrap().ways().count();
+        assert!(unfiltered_ways >= filtered_ways);
+        assert_eq!(unfiltered_ways, 2);
+    }
+
+    #[test]
+    fn test_layer_filter() {
+        let bytes = common::small_fixture()
+            .poi(0.005, 0.005, 0, &[])
+            .poi(0.006, 0.006, 2, &[])
+            .way(&[(0.004, 0.004), (0.005, 0.005)], -1, &[])
+            .way(&[(0.006, 0.004), (0.007, 0.005)], 0, &[])
+            .way(&[(0.008, 0.004), (0.009, 0.005)], 3, &[])
+            .build();
+        let tile = common::tile_at(0.005, 0.005, 14);
+
+        let mut map_file = MapFile::builder()
+            .layer_filter(0..=0)
+            .from_bytes(bytes)
+            .unwrap();
+        let result = map_file.read_map_data(&tile).unwrap();
+        assert_eq!(result.pois().count(), 1);
+        assert_eq!(result.ways().count(), 1);
+        assert!(result.pois().all(|poi| poi.layer == Layer(0)));
+        assert!(result.ways().all(|way| way.layer == Layer(0)));
+
+        map_file.set_layer_filter(None);
+        let result = map_file.read_map_data(&tile).unwrap();
+        assert_eq!(result.pois().count(), 2);
+        assert_eq!(result.ways().count(), 3);
+    }
+
+    #[test]
+    fn test_feature_flags() {
+        assert_eq!(FeatureFlags::from_byte(0), FeatureFlags::default());
+
+        let way = FeatureFlags::from_byte(0xfc);
+        assert!(way.has_name && way.has_house_number && way.has_ref);
+        assert!(way.has_label_position && way.has_data_blocks_byte && way.double_delta);
+        assert!(!way.has_elevation);
+
+        let way = FeatureFlags::from_byte(0x84);
+        assert!(way.has_name && way.double_delta);
+        assert!(!way.has_ref && !way.has_label_position && !way.has_data_blocks_byte);
+
+        // The POI elevation shares its bit with the way ref
+        let poi = FeatureFlags::from_poi_byte(0xe0);
+        assert!(poi.has_name && poi.has_house_number && poi.has_elevation);
+        assert!(!poi.has_ref);
+        assert_eq!(FeatureFlags::from_poi_byte(0x1f), FeatureFlags::default());
+    }
+
+    #[test]
+    fn test_tile_outside_map_is_rejected_early() {
+        let bytes = common::MapFixture::new(0.001, 0.001, 0.01, 0.01)
+            .sub_file(14, 12, 16)
+            .poi(0.005, 0.005, 0, &[])
+            .build();
+        let mut map_file = MapFile::from_bytes(bytes).unwrap();
+
+        let inside = common::tile_at(0.005, 0.005, 14);
+        // Far away and below the sub-file's minimum zoom level
+        let outside = common::tile_at(-40.0, 120.0, 6);
+        assert!(map_file.covers(&inside));
+        assert!(!map_file.covers(&outside));
+
+        let (result, logs) = common::capture_logs(|| map_file.read_map_data(&outside));
+        let result = result.unwrap();
+
+        assert_eq!(result.pois().count(), 0);
+        assert_eq!(result.ways().count(), 0);
+        assert!(!logs.contains("WARN"), "unexpected warnings: {}", logs);
+    }
+
+    #[test]
+    fn test_sort_tags() {
+        let mut fixture = common::small_fixture().poi(0.005, 0.005, 0, &[0, 1]).way(
+            &[(0.005, 0.005), (0.006, 0.006)],
+            0,
+            &[0],
+        );
+        fixture.poi_tags = vec!["tourism=museum".to_string(), "amenity=cafe".to_string()];
+        fixture.way_tags = vec!["surface=paved".to_string()];
+        fixture.ways[0].name = Some("Main Street".to_string());
+        let bytes = fixture.build();
+        let tile = common::tile_at(0.005, 0.005, 14);
+        let keys = |result: &MapReadResult| {
+            let poi = result.pois().next().unwrap();
+            let way = result.ways().next().unwrap();
+            (
+                poi.tags
+                    .iter()
+                    .map(|tag| tag.key.to_string())
+                    .collect::<Vec<_>>(),
+                way.tags
+                    .iter()
+                    .map(|tag| tag.key.to_string())
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        let mut map_file = MapFile::from_bytes(bytes.clone()).unwrap();
+        let (poi_keys, way_keys) = keys(&map_file.read_map_data(&tile).unwrap());
+        assert_eq!(poi_keys, ["tourism", "amenity"]);
+        assert_eq!(way_keys, ["surface", "name"]);
+
+        let mut map_file = MapFile::builder()
+            .sort_tags(true)
+            .from_bytes(bytes)
+            .unwrap();
+        let (poi_keys, way_keys) = keys(&map_file.read_map_data(&tile).unwrap());
+        assert_eq!(poi_keys, ["amenity", "tourism"]);
+        assert_eq!(way_keys, ["name", "surface"]);
+
+        map_file.set_sort_tags(false);
+        let (poi_keys, _) = keys(&map_file.read_map_data(&tile).unwrap());
+        assert_eq!(poi_keys, ["tourism", "amenity"]);
+    }
+
+    #[test]
+    fn test_read_way_data() {
+        let bytes = common::small_fixture()
+            .poi(0.005, 0.005, 0, &[])
+            .poi(0.006, 0.006, 1, &[])
+            .way(&[(0.004, 0.004), (0.005, 0.005)], 0, &[])
+            .way(&[(0.006, 0.004), (0.007, 0.005)], 2, &[])
+            .build();
+        let tile = common::tile_at(0.005, 0.005, 14);
+        let mut map_file = MapFile::from_bytes(bytes).unwrap();
+
+        let all = map_file.read_map_data(&tile).unwrap();
+        let ways_only = map_file.read_way_data(&tile).unwrap();
+        assert_eq!(all.pois().count(), 2);
+        assert_eq!(ways_only.pois().count(), 0);
+
+        let nodes = |result: &MapReadResult| {
+            result
+                .ways()
+                .map(|way| (way.layer, way.way_nodes.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(nodes(&ways_only).len(), 2);
+        assert_eq!(nodes(&ways_only), nodes(&all));
+    }
+
+    #[test]
+    fn test_read_neighborhood() {
+        let bytes = common::MapFixture::new(0.001, 0.001, 0.03, 0.03)
+            .sub_file(14, 0, 16)
+            .poi(0.005, 0.005, 0, &[])
+            .poi(0.02, 0.02, 0, &[])
+            .way(&[(0.004, 0.004), (0.025, 0.025)], 0, &[])
+            .build();
+        let center = common::tile_at(0.015, 0.015, 14);
+        let mut map_file = MapFile::from_bytes(bytes).unwrap();
+
+        let neighborhood = map_file.read_neighborhood(&center).unwrap();
+        assert_eq!(neighborhood.len(), 9);
+        let direct = map_file.read_map_data(&center).unwrap();
+        let centered = &neighborhood[&(center.tile_x, center.tile_y)];
+        assert_eq!(centered.pois().count(), direct.pois().count());
+        assert_eq!(
+            centered
+                .ways()
+                .map(|way| way.way_nodes.clone())
+                .collect::<Vec<_>>(),
+            direct
+                .ways()
+                .map(|way| way.way_nodes.clone())
+                .collect::<Vec<_>>()
+        );
+
+        // The top row has no neighbors to the north, and the columns wrap
+        let mut map_file = MapFile::from_bytes(
+            common::MapFixture::new(0.001, 0.001, 0.03, 0.03)
+                .sub_file(14, 0, 16)
+                .build(),
+        )
+        .unwrap();
+        let neighborhood = map_file
+            .read_neighborhood(&Tile::new(0, 0, 2, 256))
+            .unwrap();
+        assert_eq!(neighborhood.len(), 6);
+        assert!(neighborhood.contains_key(&(3, 1)));
+    }
+
+    #[test]
+    fn test_strict_zoom() {
+        let bytes = common::MapFixture::new(0.001, 0.001, 0.01, 0.01)
+            .sub_file(14, 0, 18)
+            .poi(0.005, 0.005, 0, &[])
+            .build();
+        let tile = common::tile_at(0.005, 0.005, 25);
+
+        let mut map_file = MapFile::from_bytes(bytes.clone()).unwrap();
+        assert_eq!(map_file.read_map_data(&tile).unwrap().pois().count(), 1);
+
+        let mut map_file = MapFile::builder()
+            .strict_zoom(true)
+            .from_bytes(bytes)
+            .unwrap();
+        let error = map_file.read_map_data(&tile).unwrap_err();
+        assert_eq!(
+            error.kind(),
+            &MapFileErrorKind::NoCoverage {
+                zoom_level: 25,
+                max: 18
+            }
+        );
+        assert!(map_file.read_poi_data(&tile).is_err());
+
+        let covered = common::tile_at(0.005, 0.005, 18);
+        assert_eq!(map_file.read_map_data(&covered).unwrap().pois().count(), 1);
+    }
+
+    #[test]
+    fn test_normalize_tags() {
+        let mut fixture = common::small_fixture().poi(0.005, 0.005, 0, &[0, 1]);
+        fixture.poi_tags = vec!["Highway=bus_stop".to_string(), "operator= BVG ".to_string()];
+        let bytes = fixture.build();
+        let tile = common::tile_at(0.005, 0.005, 14);
+        let pairs = |map_file: &mut MapFile| {
+            let result = map_file.read_map_data(&tile).unwrap();
+            let poi = result.pois().next().unwrap();
+            poi.tags
+                .iter()
+                .map(|tag| (tag.key.to_string(), tag.value.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        let mut map_file = MapFile::from_bytes(bytes.clone()).unwrap();
+        assert_eq!(
+            pairs(&mut map_file),
+            [
+                ("Highway".to_string(), "bus_stop".to_string()),
+                ("operator".to_string(), " BVG ".to_string())
+            ]
+        );
+
+        let mut map_file = MapFile::builder()
+            .normalize_tags(true)
+            .from_bytes(bytes)
+            .unwrap();
+        assert_eq!(
+            pairs(&mut map_file),
+            [
+                ("highway".to_string(), "bus_stop".to_string()),
+                ("operator".to_string(), "BVG".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_estimate_cost() {
+        let mut fixture = common::MapFixture::new(0.001, 0.001, 0.03, 0.03).sub_file(14, 0, 16);
+        for step in 0..20 {
+            let offset = step as f64 * 0.0002;
+            fixture = fixture.poi(0.004 + offset, 0.004, 0, &[]).way(
+                &[(0.004, 0.004 + offset), (0.005, 0.005 + offset)],
+                0,
+                &[],
+            );
+        }
+        let mut map_file = MapFile::from_bytes(fixture.build()).unwrap();
+        let tile_at = |latitude: f64, longitude: f64| common::tile_at(latitude, longitude, 14);
+
+        let dense = map_file.estimate_cost(&tile_at(0.005, 0.005)).unwrap();
+        let empty = map_file.estimate_cost(&tile_at(0.025, 0.025)).unwrap();
+        assert!(dense > empty, "dense {} <= empty {}", dense, empty);
+        assert_eq!(map_file.estimate_cost(&tile_at(-40.0, 120.0)).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_clip_to_tile() {
+        let nodes: Vec<(f64, f64)> = (0..30)
+            .map(|step| (0.005, 0.001 + step as f64 * 0.001))
+            .collect();
+        let bytes = common::MapFixture::new(0.001, 0.001, 0.03, 0.03)
+            .sub_file(14, 0, 16)
+            .way(&nodes, 0, &[])
+            .build();
+        let tile = common::tile_at(0.005, 0.01, 16);
+        let tile_box = tile.get_bounding_box();
+
+        let mut map_file = MapFile::from_bytes(bytes.clone()).unwrap();
+        let full = map_file.read_map_data(&tile).unwrap();
+        let full_nodes = full.ways().next().unwrap().way_nodes[0].len();
+        assert_eq!(full_nodes, 30);
+
+        let mut map_file = MapFile::builder()
+            .clip_to_tile(true)
+            .from_bytes(bytes)
+            .unwrap();
+        let clipped = map_file.read_map_data(&tile).unwrap();
+        let way = clipped.ways().next().unwrap();
+        assert!(way.way_nodes[0].len() < full_nodes);
+        assert!(way.bounding_box().unwrap().intersects(&tile_box));
+        assert!(way.way_nodes.iter().flatten().all(|node| {
+            tile_box.contains(node.latitude, node.longitude)
+                || (node.longitude - tile_box.min_longitude).abs() < 1e-6
+                || (node.longitude - tile_box.max_longitude).abs() < 1e-6
+        }));
+    }
+
+    #[test]
+    fn test_dedup_nodes() {
+        let bytes = common::small_fixture()
+            .way(
+                &[
+                    (0.004, 0.004),
+                    (0.005, 0.005),
+                    (0.005, 0.005),
+                    (0.006, 0.004),
+                ],
+                0,
+                &[],
+            )
+            .build();
+        let tile = common::tile_at(0.005, 0.005, 14);
+
+        let mut map_file = MapFile::from_bytes(bytes).unwrap();
+        let result = map_file.read_map_data(&tile).unwrap();
+        assert_eq!(result.ways().next().unwrap().way_nodes[0].len(), 4);
+
+        map_file.set_dedup_nodes(true);
+        let result = map_file.read_map_data(&tile).unwrap();
+        let nodes = &result.ways().next().unwrap().way_nodes[0];
+        assert_eq!(nodes.len(), 3);
+        assert!(nodes.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn test_scan_pois() {
+        let mut fixture = common::MapFixture::new(0.001, 0.001, 0.03, 0.03)
+            .sub_file(14, 0, 16)
+            .poi(0.005, 0.005, 0, &[0])
+            .poi(0.025, 0.025, 0, &[1])
+            .poi(0.006, 0.006, 0, &[2]);
+        fixture.poi_tags = vec![
+            "place=city".to_string(),
+            "place=village".to_string(),
+            "amenity=cafe".to_string(),
+        ];
+        let observer = Arc::new(CountingObserver::default());
+        let mut map_file = MapFile::builder()
+            .observer(observer.clone())
+            .from_bytes(fixture.build())
+            .unwrap();
+
+        let places = map_file.scan_pois(&PoiFilter::key("place")).unwrap();
+        assert_eq!(places.len(), 2);
+        assert!(places.iter().any(|poi| poi.position.latitude > 0.02));
+        // The cafe is dropped while its block is decoded, it never reaches a bundle
+        let pois = observer.pois.load(std::sync::atomic::Ordering::Relaxed);
+        assert_eq!(pois, 2);
+
+        let cities = map_file
+            .scan_pois(&PoiFilter::tag("place", "city"))
+            .unwrap();
+        assert_eq!(cities.len(), 1);
+        assert!((cities[0].position.latitude - 0.005).abs() < 1e-6);
+
+        assert!(map_file
+            .scan_pois(&PoiFilter::key("shop"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_tag_histogram() {
+        let mut fixture = common::small_fixture()
+            .poi(0.005, 0.005, 0, &[0])
+            .poi(0.006, 0.006, 0, &[0, 1])
+            .way(&[(0.004, 0.004), (0.005, 0.005)], 0, &[0])
+            .way(&[(0.006, 0.004), (0.007, 0.005)], 0, &[0]);
+        fixture.poi_tags = vec!["amenity=cafe".to_string(), "cuisine=coffee".to_string()];
+        fixture.way_tags = vec!["highway=residential".to_string()];
+        fixture.ways[1].name = Some("Main Street".to_string());
+        let tile = common::tile_at(0.005, 0.005, 14);
+        let mut map_file = MapFile::from_bytes(fixture.build()).unwrap();
+
+        let histogram = map_file.tag_histogram(&tile).unwrap();
+        assert_eq!(histogram["amenity=cafe"], 2);
+        assert_eq!(histogram["cuisine=coffee"], 1);
+        assert_eq!(histogram["highway=residential"], 2);
+        assert_eq!(histogram["name=Main Street"], 1);
+
+        let result = map_file.read_map_data(&tile).unwrap();
+        let total = result.pois().map(|poi| poi.tags.len()).sum::<usize>()
+            + result.ways().map(|way| way.tags.len()).sum::<usize>();
+        assert_eq!(histogram.values().sum::<usize>(), total);
+    }
+
+    // Downstream code can name the crate's result type directly
+    fn count_pois(bytes: Vec<u8>, tile: &Tile) -> reader::Result<usize> {
+        let mut map_file = MapFile::from_bytes(bytes)?;
+        let result: reader::Result<MapReadResult> = map_file.read_map_data(tile);
+        Ok(result?.pois().count())
+    }
+
+    #[test]
+    fn test_result_alias() {
+        let bytes = common::small_fixture().poi(0.005, 0.005, 0, &[]).build();
+        let tile = common::tile_at(0.005, 0.005, 14);
+
+        assert_eq!(count_pois(bytes, &tile).unwrap(), 1);
+        let error: reader::MapFileException = count_pois(Vec::new(), &tile).unwrap_err();
+        assert!(!error.to_string().is_empty());
+    }
+
+    #[derive(Default)]
+    struct CountingObserver {
+        blocks: std::sync::atomic::AtomicUsize,
+        bundles: std::sync::atomic::AtomicUsize,
+        pois: std::sync::atomic::AtomicUsize,
+    }
+
+    impl ReadObserver for CountingObserver {
+        fn on_block(&self, _block_number: i64, _row: i64, _column: i64) {
+            self.blocks
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
+
+        fn on_bundle(&self, bundle: &PoiWayBundle) {
+            self.bundles
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            self.pois
+                .fetch_add(bundle.pois.len(), std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_read_observer() {
+        let bytes = common::MapFixture::new(0.001, 0.001, 0.03, 0.03)
+            .sub_file(14, 0, 16)
+            .poi(0.005, 0.005, 0, &[])
+            .poi(0.025, 0.025, 0, &[])
+            .build();
+        let tile = common::tile_at(0.015, 0.015, 12);
+        let observer = Arc::new(CountingObserver::default());
+        let mut map_file = MapFile::builder()
+            .observer(observer.clone())
+            .from_bytes(bytes)
+            .unwrap();
+        let query = map_file.explain_query(&tile).unwrap();
+        let expected_blocks = ((query.to_block_x - query.from_block_x + 1)
+            * (query.to_block_y - query.from_block_y + 1)) as usize;
+        assert!(expected_blocks > 1);
+
+        let (result, logs) = common::capture_logs(|| map_file.read_map_data(&tile));
+        let result = result.unwrap();
+
+        let blocks = observer.blocks.load(std::sync::atomic::Ordering::Relaxed);
+        let bundles = observer.bundles.load(std::sync::atomic::Ordering::Relaxed);
+        assert_eq!(blocks, expected_blocks);
+        assert_eq!(bundles, result.poi_way_bundles.len());
+        // The block events went to the observer, nothing is logged at the
+        // default level
+        assert!(logs.is_empty(), "unexpected logs: {}", logs);
+    }
+
+    #[test]
+    fn test_default_observer_is_silent() {
+        let bytes = common::MapFixture::new(0.001, 0.001, 0.03, 0.03)
+            .sub_file(14, 0, 16)
+            .poi(0.005, 0.005, 0, &[])
+            .poi(0.025, 0.025, 0, &[])
+            .build();
+        let tile = common::tile_at(0.015, 0.015, 12);
+        let mut map_file = MapFile::builder().from_bytes(bytes.clone()).unwrap();
+        let (result, logs) = common::capture_logs(|| map_file.read_map_data(&tile));
+        assert_eq!(result.unwrap().pois().count(), 2);
+        assert!(logs.is_empty(), "unexpected logs: {}", logs);
+
+        // The opt-in tracing observer keeps the block events below info
+        let mut map_file = MapFile::builder()
+            .observer(Arc::new(TracingObserver))
+            .from_bytes(bytes)
+            .unwrap();
+        let (result, logs) = common::capture_logs(|| map_file.read_map_data(&tile));
+        assert_eq!(result.unwrap().pois().count(), 2);
+        assert!(!logs.contains("INFO"), "unexpected logs: {}", logs);
+    }
+
+    #[test]
+    fn test_read_map_data_at_zoom() {
+        let bytes = common::MapFixture::new(0.001, 0.001, 0.02, 0.02)
+            .sub_file(14, 12, 18)
+            .poi(0.002, 0.002, 0, &[])
+            .poi(0.015, 0.015, 0, &[])
+            .way(&[(0.002, 0.002), (0.003, 0.003)], 0, &[])
+            .build();
+        let tile_at = |zoom: u8| common::tile_at(0.002, 0.002, zoom);
+        let mut map_file = MapFile::from_bytes(bytes).unwrap();
+
+        let base = map_file.read_map_data(&tile_at(14)).unwrap();
+        let overzoomed = map_file.read_map_data_at_zoom(&tile_at(16), 14).unwrap();
+        assert_eq!(base.pois().count(), 2);
+        assert_eq!(overzoomed.pois().count(), base.pois().count());
+        assert_eq!(
+            overzoomed
+                .ways()
+                .map(|way| way.way_nodes.clone())
+                .collect::<Vec<_>>(),
+            base.ways()
+                .map(|way| way.way_nodes.clone())
+                .collect::<Vec<_>>()
+        );
+
+        // A plain zoom-16 read drops the POI outside the tile
+        let plain = map_file.read_map_data(&tile_at(16)).unwrap();
+        assert_eq!(plain.pois().count(), 1);
+    }
+
+    #[test]
+    fn test_layer_filter_rejects_short_way() {
+        let mut bytes = common::small_fixture()
+            .way(&[(0.004, 0.004), (0.005, 0.005)], 3, &[])
+            .build();
+        // Shrink the way data size below the tile bitmask and special byte
+        let way_start = bytes
+            .windows(3)
+            .position(|window| window == [0xff, 0xff, 0x80])
+            .unwrap();
+        bytes[way_start - 1] = 2;
+        let tile = common::tile_at(0.005, 0.005, 14);
+
+        let mut map_file = MapFile::builder()
+            .layer_filter(0..=0)
+            .from_bytes(bytes)
+            .unwrap();
+        let result = map_file.read_map_data(&tile).unwrap();
+        assert_eq!(result.ways().count(), 0);
+    }
+
+    #[test]
+    fn test_zero_tag_features() {
+        let mut fixture = common::small_fixture()
+            .poi(0.005, 0.005, 0, &[])
+            .way(&[(0.005, 0.005), (0.006, 0.006)], 0, &[])
+            .way(&[(0.005, 0.006), (0.006, 0.007)], 0, &[]);
+        fixture.ways[1].name = Some("Hauptstraße".to_string());
+        let mut map_file = MapFile::from_bytes(fixture.build()).unwrap();
+        let tile = common::tile_at(0.005, 0.005, 14);
+
+        let result = map_file.read_map_data(&tile).unwrap();
+        assert_eq!(result.pois().count(), 1);
+        assert!(result.pois().all(|poi| poi.tags.is_empty()));
+        let untagged: Vec<_> = result.ways().filter(|way| way.tags.is_empty()).collect();
+        assert_eq!(untagged.len(), 1);
+        assert_eq!(untagged[0].way_nodes[0].len(), 2);
+
+        // Only the named way is a label candidate
+        let named = map_file.read_named_items(&tile).unwrap();
+        let ways: Vec<_> = named.ways().collect();
+        assert_eq!(ways.len(), 1);
+        assert!(ways[0]
+            .tags
+            .iter()
+            .any(|tag| tag.as_value() == "Hauptstraße"));
+    }
+
+    #[test]
+    fn test_prefetch_index() {
+        // 23 x 23 blocks at zoom 14, spread over five index blocks
+        let bytes = common::MapFixture::new(0.001, 0.001, 0.5, 0.5)
+            .sub_file(14, 0, 16)
+            .poi(0.005, 0.005, 0, &[])
+            .build();
+        let mut header = MapFileHeader::new();
+        header
+            .read_header(
+                &mut ReadBuffer::new(Cursor::new(bytes.clone())),
+                bytes.len() as i64,
+            )
+            .unwrap();
+        let sub_file_parameter = header.get_sub_file_parameter(14).unwrap().clone();
+        assert!(sub_file_parameter.number_of_blocks > 4 * 128);
+
+        let mut map_file = MapFile::from_bytes(bytes).unwrap();
+        map_file
+            .prefetch_index(
+                &sub_file_parameter,
+                0,
+                sub_file_parameter.number_of_blocks - 1,
+            )
+            .unwrap();
+        let prefetched = map_file.index_cache_stats();
+        assert_eq!(prefetched.len, 5);
+
+        // Every block of the sub-file is now served from the cache
+        let tile = common::tile_at(0.5, 0.001, 14);
+        let lower_right = common::tile_at(0.001, 0.5, 14);
+        let result = map_file.read_map_data_range(&tile, &lower_right).unwrap();
+        assert_eq!(result.into_pois().count(), 1);
+        assert_eq!(map_file.index_cache_stats().misses, prefetched.misses);
+    }
+
+    #[test]
+    fn test_way_label_position() {
+        let mut fixture = common::small_fixture().way(&[(0.002, 0.003), (0.008, 0.009)], 0, &[]);
+        // Stored as VBE-S latitude 1500, then longitude -2500 microdegrees
+        fixture.ways[0].label_position = Some((1500, -2500));
+        let mut map_file = MapFile::from_bytes(fixture.build()).unwrap();
+
+        let tile = common::tile_at(0.005, 0.005, 14);
+        let result = map_file.read_map_data(&tile).unwrap();
+        let way = result.ways().next().unwrap();
+        let label = way.label_position.as_ref().unwrap();
+
+        assert!(approx_equal(label.latitude, 0.0035, 1e-6));
+        assert!(approx_equal(label.longitude, 0.0005, 1e-6));
+    }
+
+    #[test]
+    fn test_read_sub_file() {
+        let bytes = common::MapFixture::new(0.001, 0.001, 0.06, 0.06)
+            .sub_file(8, 0, 9)
+            .add_sub_file(14, 10, 16)
+            .poi(0.005, 0.005, 0, &[])
+            .poi(0.05, 0.05, 0, &[])
+            .build();
+        let mut map_file = MapFile::from_bytes(bytes).unwrap();
+
+        // Each sub-file only holds the POIs of the blocks the tile maps to
+        for (base_zoom, expected_pois) in [(8u8, 2), (14, 1)] {
+            let tile = common::tile_at(0.005, 0.005, base_zoom);
+            let block_bbox = tile.get_bounding_box();
+
+            let result = map_file.read_sub_file(base_zoom, &tile).unwrap();
+            let pois: Vec<_> = result.pois().collect();
+            assert_eq!(pois.len(), expected_pois);
+            for poi in pois {
+                assert!(block_bbox.contains(poi.position.latitude, poi.position.longitude));
+            }
+        }
+
+        let tile = common::tile_at(0.005, 0.005, 12);
+        assert!(map_file.read_sub_file(12, &tile).is_err());
+    }
+
+    #[test]
+    fn test_decode_block_from_memory() {
+        let mut fixture = common::MapFixture::new(0.001, 0.001, 0.01, 0.01).sub_file(14, 14, 14);
+        fixture.poi_tags = vec!["amenity=cafe".to_string()];
+        let map_file = MapFile::from_bytes(fixture.build()).unwrap();
+
+        let mut source: Vec<u8> = vec![
+            0xAA, 0xAA, 0xAA, // unrelated bytes before the block
+            0x01, 0x00, // zoom table: 1 POI, 0 ways
+            0x07, // first way offset
+            0xF4, 0x43, // latitude offset -500
+            0xE8, 0x07, // longitude offset 1000
+            0x51, // layer 0 (stored as 5), 1 tag
+            0x00, // tag id 0
+            0x00, // no optional features
+        ];
+        let tile = Tile::new(8192, 8191, 14, 256);
+
+        let bundle = map_file.decode_block(&mut source, 3, 10, &tile).unwrap();
+        assert!(bundle.ways.is_empty());
+        assert_eq!(bundle.pois.len(), 1);
+
+        let poi = &bundle.pois[0];
+        assert_eq!(poi.layer, 0);
+        assert_eq!(poi.tags[0].as_key(), "amenity");
+        assert_eq!(poi.tags[0].as_value(), "cafe");
+        let tile_latitude = MercatorProjection::tile_y_to_latitude(8191, 14);
+        assert!(approx_equal(
+            poi.position.latitude,
+            tile_latitude - 0.0005,
+            1e-6
+        ));
+        assert!(approx_equal(poi.position.longitude, 0.001, 1e-9));
+
+        assert!(map_file.decode_block(&mut source, 10, 10, &tile).is_err());
+    }
+
+    #[test]
+    fn test_check_debug_signatures() {
+        let mut fixture = common::MapFixture::new(0.001, 0.001, 0.01, 0.01)
+            .sub_file(14, 12, 16)
+            .poi(0.005, 0.005, 0, &[])
+            .way(&[(0.004, 0.004), (0.006, 0.006)], 0, &[]);
+        let tile = common::tile_at(0.005, 0.005, 14);
+
+        let mut plain = MapFile::from_bytes(fixture.build()).unwrap();
+        assert!(!plain.check_debug_signatures(&tile).unwrap());
+
+        fixture.debug_file = true;
+        let mut bytes = fixture.build();
+        let mut map_file = MapFile::from_bytes(bytes.clone()).unwrap();
+        assert!(map_file.get_map_file_info().unwrap().debug_file);
+        assert!(map_file.check_debug_signatures(&tile).unwrap());
+
+        let way_start = bytes
+            .windows(11)
+            .position(|window| window == b"---WayStart")
+            .unwrap();
+        bytes[way_start] = b'+';
+        let mut corrupt = MapFile::from_bytes(bytes).unwrap();
+        assert!(corrupt.check_debug_signatures(&tile).is_err());
+    }
+
+    #[cfg(feature = "debug")]
+    #[test]
+    fn test_read_raw_block() {
+        let mut fixture = common::MapFixture::new(0.001, 0.001, 0.01, 0.01)
+            .sub_file(14, 12, 16)
+            .poi(0.005, 0.005, 0, &[])
+            .poi(0.006, 0.006, 0, &[])
+            .way(&[(0.002, 0.002), (0.003, 0.003)], 0, &[])
+            .way(&[(0.004, 0.004), (0.008, 0.008)], 0, &[]);
+        fixture.debug_file = true;
+        // A bitmask matching no quadrant of the query tile
+        fixture.ways[1].tile_bitmask = 0x0001;
+        let mut map_file = MapFile::from_bytes(fixture.build()).unwrap();
+
+        let tile = common::tile_at(0.005, 0.005, 14);
+        let raw = map_file.read_raw_block(&tile, 0).unwrap();
+        let [total_pois, total_ways] = *raw.zoom_table.last().unwrap();
+        assert_eq!(raw.pois.len(), total_pois as usize);
+        assert_eq!(raw.ways.len(), total_ways as usize);
+        assert_eq!(raw.pois.len(), 2);
+        assert_eq!(raw.ways.len(), 2);
+        assert_eq!(raw.ways[0].tile_bitmask, 0xffff);
+        assert_eq!(raw.ways[1].tile_bitmask, 0x0001);
+        assert!(raw.ways.iter().all(|way| way.way_data_size > 0));
+
+        assert!(map_file.read_raw_block(&tile, 1).is_err());
+    }
+
+    #[cfg(feature = "debug")]
+    #[test]
+    fn test_dump_zoom_table() {
+        let mut map_file = MapFile::from_bytes(
+            common::MapFixture::new(0.001, 0.001, 0.01, 0.01)
+                .sub_file(14, 12, 16)
+                .poi(0.005, 0.005, 0, &[])
+                .way(&[(0.002, 0.002), (0.003, 0.003)], 0, &[])
+                .build(),
+        )
+        .unwrap();
+        let tile = common::tile_at(0.005, 0.005, 14);
+
+        let table = map_file.dump_zoom_table(&tile).unwrap();
+        let zooms: Vec<u8> = table.iter().map(|row| row.zoom).collect();
+        assert_eq!(zooms, [12, 13, 14, 15, 16]);
+        assert!(table.windows(2).all(|pair| {
+            pair[0].cumulative_pois <= pair[1].cumulative_pois
+                && pair[0].cumulative_ways <= pair[1].cumulative_ways
+        }));
+        assert_eq!(table[4].cumulative_pois, 1);
+        assert_eq!(table[4].cumulative_ways, 1);
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let mut fixture = common::small_fixture().poi(0.005, 0.005, 0, &[0]).way(
+            &[(0.002, 0.002), (0.003, 0.003)],
+            0,
+            &[],
+        );
+        fixture.poi_tags = vec!["amenity=cafe".to_string()];
+        let mut map_file = MapFile::from_bytes(fixture.build()).unwrap();
+        let tile = common::tile_at(0.005, 0.005, 14);
+
+        let first = map_file.read_map_data(&tile).unwrap();
+        let second = map_file.read_map_data(&tile).unwrap();
+        assert_eq!(first.content_hash(), second.content_hash());
+
+        let mut changed = first.clone();
+        changed.poi_way_bundles[0].pois[0].tags[0] = Tag::new("amenity", "bar");
+        assert_ne!(first.content_hash(), changed.content_hash());
+    }
+
+    #[test]
+    fn test_read_start_tile() {
+        let mut fixture = common::small_fixture().poi(0.004, 0.006, 0, &[]);
+        fixture.start_position = Some((0.004, 0.006));
+        fixture.start_zoom_level = Some(15);
+        let mut map_file = MapFile::from_bytes(fixture.build()).unwrap();
+
+        let (tile, result) = map_file.read_start_tile().unwrap();
+        assert_eq!(tile.zoom_level, 15);
+        assert!(tile.get_bounding_box().contains(0.004, 0.006));
+        assert_eq!(result.pois().count(), 1);
+
+        // Without a start position the tile holds the bounding box center
+        let fixture = common::small_fixture();
+        let mut map_file = MapFile::from_bytes(fixture.build()).unwrap();
+        let (tile, _) = map_file.read_start_tile().unwrap();
+        assert!(tile.get_bounding_box().contains(0.0055, 0.0055));
+    }
+
+    #[test]
+    fn test_make_tile() {
+        let mut fixture = common::small_fixture();
+        let map_file = MapFile::from_bytes(fixture.build()).unwrap();
+        let tile = map_file.make_tile(0.005, 0.006, 14);
+        assert_eq!(tile.tile_size, 256);
+        assert_eq!(
+            tile.tile_x,
+            MercatorProjection::longitude_to_tile_x(0.006, 14)
+        );
+        assert_eq!(
+            tile.tile_y,
+            MercatorProjection::latitude_to_tile_y(0.005, 14)
+        );
+        assert_eq!(tile.zoom_level, 14);
+
+        fixture.tile_pixel_size = 512;
+        let map_file = MapFile::from_bytes(fixture.build()).unwrap();
+        assert_eq!(map_file.make_tile(0.005, 0.006, 14).tile_size, 512);
+    }
+
+    #[test]
+    fn test_read_viewport() {
+        let bytes = common::MapFixture::new(0.001, 0.001, 0.03, 0.03)
+            .sub_file(14, 0, 16)
+            .poi(0.005, 0.005, 0, &[])
+            .poi(0.028, 0.028, 0, &[])
+            .way(&[(0.004, 0.004), (0.006, 0.027)], 0, &[])
+            .build();
+        let mut map_file = MapFile::from_bytes(bytes).unwrap();
+
+        // Centered on a tile, 800 pixels reach 272 pixels into the second tile
+        // left and right, 600 pixels 172 pixels into the tiles above and below
+        let tile_x = MercatorProjection::longitude_to_tile_x(0.015, 14);
+        let tile_y = MercatorProjection::latitude_to_tile_y(0.015, 14);
+        let center = LatLong::new(
+            MercatorProjection::tile_y_to_latitude(2 * tile_y + 1, 15),
+            MercatorProjection::tile_x_to_longitude(2 * tile_x + 1, 15),
+        );
+        let (upper_left, lower_right) = map_file.viewport_tiles(&center, 14, 800, 600);
+        assert_eq!(
+            (upper_left.tile_x, upper_left.tile_y),
+            (tile_x - 2, tile_y - 1)
+        );
+        assert_eq!(
+            (lower_right.tile_x, lower_right.tile_y),
+            (tile_x + 2, tile_y + 1)
+        );
+        let covered = (lower_right.tile_x - upper_left.tile_x + 1)
+            * (lower_right.tile_y - upper_left.tile_y + 1);
+        assert_eq!(covered, 15);
+
+        let result = map_file.read_viewport(&center, 14, 800, 600).unwrap();
+        assert_eq!(result.pois().count(), 2);
+        assert_eq!(result.ways().count(), 1);
+    }
+
+    #[test]
+    fn test_nearest_pois() {
+        let mut fixture = common::small_fixture();
+        for &(latitude, longitude) in &[
+            (0.009, 0.002),
+            (0.005, 0.0055),
+            (0.002, 0.009),
+            (0.0051, 0.005),
+            (0.007, 0.007),
+        ] {
+            fixture = fixture.poi(latitude, longitude, 0, &[]);
+        }
+        let mut map_file = MapFile::from_bytes(fixture.build()).unwrap();
+
+        let point = LatLong::new(0.005, 0.005);
+        let nearest = map_file.nearest_pois(&point, 14, 3).unwrap();
+        assert_eq!(nearest.len(), 3);
+        assert!(nearest.windows(2).all(
+            |pair| pair[0].position.distance_to(&point) <= pair[1].position.distance_to(&point)
+        ));
+        assert!((nearest[0].position.latitude - 0.0051).abs() < 1e-6);
+
+        let all = map_file.nearest_pois(&point, 14, 10).unwrap();
+        assert_eq!(all.len(), 5);
+    }
+
+    #[test]
+    fn test_negative_elevation() {
+        let mut fixture =
+            common::small_fixture()
+                .poi(0.005, 0.005, 0, &[])
+                .poi(0.006, 0.006, 0, &[]);
+        fixture.pois[0].elevation = Some(-50);
+        fixture.pois[1].elevation = Some(8848);
+        let mut map_file = MapFile::from_bytes(fixture.build()).unwrap();
+
+        let tile = common::tile_at(0.005, 0.005, 14);
+        let pois: Vec<_> = map_file.read_poi_data(&tile).unwrap().into_pois().collect();
+        assert_eq!(pois[0].elevation(), Some(-50));
+        assert_eq!(pois[1].elevation(), Some(8848));
+    }
+
+    #[test]
+    fn test_read_map_data_with_progress() {
+        let bytes = common::MapFixture::new(0.001, 0.001, 0.1, 0.1)
+            .sub_file(14, 0, 16)
+            .poi(0.005, 0.005, 0, &[])
+            .build();
+        let mut map_file = MapFile::from_bytes(bytes).unwrap();
+
+        // A zoom 12 tile covers 4 x 4 blocks of the zoom 14 sub-file
+        let tile = common::tile_at(0.05, 0.05, 12);
+        let mut ticks = Vec::new();
+        map_file
+            .read_map_data_with_progress(&tile, &mut |done, total| ticks.push((done, total)))
+            .unwrap();
+
+        let total = ticks[0].1;
+        assert_eq!(total, 16);
+        assert!(ticks.iter().all(|&(_, t)| t == total));
+        assert!(ticks.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        assert_eq!(ticks.last(), Some(&(total, total)));
+    }
+
+    #[test]
+    fn test_layer_bias() {
+        let bytes = common::small_fixture()
+            .poi(0.005, 0.005, -2, &[])
+            .way(&[(0.002, 0.002), (0.003, 0.003)], -2, &[])
+            .way(&[(0.004, 0.004), (0.006, 0.006)], 10, &[])
+            .build();
+        let mut map_file = MapFile::from_bytes(bytes).unwrap();
+
+        let tile = common::tile_at(0.005, 0.005, 14);
+        let result = map_file.read_map_data(&tile).unwrap();
+        let bundle = &result.poi_way_bundles[0];
+        assert_eq!(bundle.pois[0].layer, Layer(-2));
+        assert_eq!(bundle.ways[0].layer, -2);
+        assert_eq!(bundle.ways[1].layer, 10);
+        assert_eq!(bundle.ways[0].layer.ordering(), 3);
+        assert_eq!(bundle.ways[1].layer.ordering(), 15);
+    }
+
+    #[test]
+    fn test_max_buffer_size() {
+        let bytes = common::small_fixture().poi(0.005, 0.005, 0, &[]).build();
+        let tile = common::tile_at(0.005, 0.005, 14);
+
+        let mut map_file = MapFile::builder()
+            .max_buffer_size(4)
+            .from_bytes(bytes.clone())
+            .unwrap();
+        let error = map_file.read_map_data(&tile).unwrap_err();
+        assert!(error.to_string().contains("exceeds maximum buffer size"));
+        assert!(matches!(
+            error.kind(),
+            MapFileErrorKind::BufferTooLarge { max: 4, .. }
+        ));
+
+        let mut map_file = MapFile::builder().from_bytes(bytes).unwrap();
+        assert_eq!(map_file.read_map_data(&tile).unwrap().pois().count(), 1);
+    }
+
+    #[test]
+    fn test_decoded_tags_share_strings() {
+        let mut fixture = common::small_fixture()
+            .way(&[(0.002, 0.002), (0.003, 0.003)], 0, &[0])
+            .way(&[(0.004, 0.004), (0.005, 0.005)], 0, &[0]);
+        fixture.way_tags = vec!["highway=primary".to_string()];
+        for way in &mut fixture.ways {
+            way.name = Some("Main Street".to_string());
+        }
+        let mut map_file = MapFile::from_bytes(fixture.build()).unwrap();
+
+        let tile = common::tile_at(0.005, 0.005, 14);
+        let result = map_file.read_map_data(&tile).unwrap();
+        let ways: Vec<_> = result.ways().collect();
+        assert_eq!(ways.len(), 2);
+
+        let (first, second) = (&ways[0].tags, &ways[1].tags);
+        assert_eq!(first[0].as_value(), "primary");
+        assert!(Arc::ptr_eq(&first[0].value, &second[0].value));
+        assert_eq!(first[1].as_key(), "name");
+        assert_eq!(first[1].as_value(), "Main Street");
+        assert!(Arc::ptr_eq(&first[1].value, &second[1].value));
+    }
+
+    #[test]
+    fn test_read_labels() {
+        let mut fixture = common::small_fixture()
+            .poi(0.002, 0.002, 0, &[])
+            .poi(0.003, 0.003, 0, &[])
+            .way(
+                &[
+                    (0.004, 0.004),
+                    (0.004, 0.006),
+                    (0.006, 0.006),
+                    (0.006, 0.004),
+                    (0.004, 0.004),
+                ],
+                0,
+                &[],
+            )
+            .way(&[(0.007, 0.007), (0.008, 0.008)], 1, &[]);
+        fixture.pois[0].name = Some("Cafe".to_string());
+        fixture.ways[0].name = Some("Park".to_string());
+        fixture.ways[1].name = Some("Main Street".to_string());
+        fixture.ways[1].label_position = Some((500, 0));
+        let mut map_file = MapFile::from_bytes(fixture.build()).unwrap();
+
+        let tile = common::tile_at(0.005, 0.005, 14);
+        let bbox = tile.get_bounding_box();
+        let labels = map_file.read_labels(&tile).unwrap();
+
+        let names: Vec<&str> = labels.iter().map(|label| label.name.as_str()).collect();
+        assert_eq!(names, ["Cafe", "Park", "Main Street"]);
+        for label in &labels {
+            assert!(!label.name.is_empty());
+            assert!(bbox.contains(label.anchor.latitude, label.anchor.longitude));
+        }
+        assert!(approx_equal(labels[1].anchor.latitude, 0.005, 1e-6));
+        assert!(approx_equal(labels[1].anchor.longitude, 0.005, 1e-6));
+        assert!(approx_equal(labels[2].anchor.latitude, 0.0075, 1e-6));
+        assert_eq!(labels[2].layer, 1);
+    }
+
+    #[test]
+    fn test_areas_containing() {
+        let mut fixture = common::small_fixture()
+            // Lake inside a park, and a road crossing both
+            .way(
+                &[
+                    (0.002, 0.002),
+                    (0.002, 0.009),
+                    (0.009, 0.009),
+                    (0.009, 0.002),
+                    (0.002, 0.002),
+                ],
+                0,
+                &[0],
+            )
+            .way(
+                &[
+                    (0.004, 0.004),
+                    (0.004, 0.006),
+                    (0.006, 0.006),
+                    (0.006, 0.004),
+                    (0.004, 0.004),
+                ],
+                0,
+                &[1],
+            )
+            .way(&[(0.002, 0.005), (0.009, 0.005)], 0, &[2]);
+        fixture.way_tags = vec![
+            "leisure=park".to_string(),
+            "natural=water".to_string(),
+            "highway=primary".to_string(),
+        ];
+        let mut map_file = MapFile::from_bytes(fixture.build()).unwrap();
+
+        let areas = map_file
+            .areas_containing(&LatLong::new(0.005, 0.005), 14)
+            .unwrap();
+        let values: Vec<&str> = areas.iter().map(|way| way.tags[0].as_value()).collect();
+        assert_eq!(values, ["water", "park"]);
+
+        let areas = map_file
+            .areas_containing(&LatLong::new(0.003, 0.003), 14)
+            .unwrap();
+        assert_eq!(areas.len(), 1);
+        assert_eq!(areas[0].tags[0].as_value(), "park");
+    }
+
+    #[test]
+    fn test_coordinate_precision() {
+        let bytes = common::small_fixture()
+            .way(
+                &[(0.002, 0.002), (0.004123, 0.005321), (0.008, 0.0075)],
+                0,
+                &[],
+            )
+            .build();
+        let tile = common::tile_at(0.005, 0.005, 14);
+        let on_grid = |degrees: f64| degrees == (degrees * 1e6).round() / 1e6;
+
+        let mut map_file = MapFile::from_bytes(bytes.clone()).unwrap();
+        let result = map_file.read_map_data(&tile).unwrap();
+        let nodes = &result.ways().next().unwrap().way_nodes[0];
+        for node in nodes {
+            assert!(on_grid(node.latitude) && on_grid(node.longitude));
+        }
+        assert!(approx_equal(nodes[1].latitude, 0.004123, 1.1e-6));
+        assert_eq!(nodes[1].longitude, 0.005321);
+
+        let mut map_file = MapFile::builder()
+            .coordinate_precision(CoordinatePrecision::Raw)
+            .from_bytes(bytes)
+            .unwrap();
+        let result = map_file.read_map_data(&tile).unwrap();
+        let nodes = &result.ways().next().unwrap().way_nodes[0];
+        assert!(nodes.iter().any(|node| !on_grid(node.latitude)));
+    }
+
+    #[test]
+    fn test_node_thinning() {
+        // A node every 0.00001 degrees, about 1.1 m, over 111 m
+        let nodes: Vec<(f64, f64)> = (0..=100)
+            .map(|i| (0.005, 0.005 + i as f64 * 0.00001))
+            .collect();
+        let bytes = common::small_fixture().way(&nodes, 0, &[]).build();
+        let tile = common::tile_at(0.005, 0.005, 16);
+
+        let mut map_file = MapFile::from_bytes(bytes.clone()).unwrap();
+        let result = map_file.read_map_data(&tile).unwrap();
+        assert_eq!(result.ways().next().unwrap().way_nodes[0].len(), 101);
+
+        // About 2.4 m per pixel at zoom 16
+        let mut map_file = MapFile::builder()
+            .node_thinning(true)
+            .from_bytes(bytes)
+            .unwrap();
+        let result = map_file.read_map_data(&tile).unwrap();
+        let thinned = &result.ways().next().unwrap().way_nodes[0];
+        assert!(thinned.len() < 60 && thinned.len() > 30);
+        assert_eq!(thinned[0], LatLong::new(0.005, 0.005));
+        assert_eq!(thinned[thinned.len() - 1], LatLong::new(0.005, 0.006));
+    }
+
+    // Opens `compressed` with `new_compressed` and compares it to `bytes`
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    fn assert_opens_like(name: &str, compressed: Vec<u8>, bytes: Vec<u8>) {
+        let path =
+            std::env::temp_dir().join(format!("gps-fixture-{}-{}.map", name, std::process::id()));
+        std::fs::write(&path, compressed).unwrap();
+        let mut compressed = MapFile::new_compressed(&path).unwrap();
+        let mut plain = MapFile::from_bytes(bytes).unwrap();
+
+        let (a, b) = (
+            compressed.get_map_file_info().unwrap(),
+            plain.get_map_file_info().unwrap(),
+        );
+        assert_eq!(a.map_date, b.map_date);
+        assert_eq!(a.file_size, b.file_size);
+        assert_eq!(a.number_of_sub_files, b.number_of_sub_files);
+        assert_eq!(a.poi_tags.len(), b.poi_tags.len());
+
+        let tile = common::tile_at(0.005, 0.005, 14);
+        let a: Vec<LatLong> = compressed
+            .read_map_data(&tile)
+            .unwrap()
+            .into_pois()
+            .map(|poi| poi.position)
+            .collect();
+        let b: Vec<LatLong> = plain
+            .read_map_data(&tile)
+            .unwrap()
+            .into_pois()
+            .map(|poi| poi.position)
+            .collect();
+        assert_eq!(a.len(), 2);
+        assert_eq!(a, b);
+    }
+
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    fn compressible_map() -> Vec<u8> {
+        let mut fixture =
+            common::small_fixture()
+                .poi(0.005, 0.005, 0, &[0])
+                .poi(0.006, 0.007, 0, &[0]);
+        fixture.poi_tags = vec!["amenity=cafe".to_string()];
+        fixture.build()
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_new_compressed_gzip() {
+        use std::io::Write;
+
+        let bytes = compressible_map();
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&bytes).unwrap();
+        assert_opens_like("gzip", encoder.finish().unwrap(), bytes);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_new_compressed_zstd() {
+        let bytes = compressible_map();
+        let compressed = zstd::stream::encode_all(bytes.as_slice(), 0).unwrap();
+        assert_opens_like("zstd", compressed, bytes);
+    }
+
     fn approx_equal(a: f64, b: f64, epsilon: f64) -> bool {
         (a - b).abs() < epsilon
     }
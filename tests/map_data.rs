@@ -0,0 +1,489 @@
+#[cfg(test)]
+mod tests {
+    use reader::{
+        centroid, clip_way_to_bbox, cluster_pois, densify, way_tiles, BoundingBox, DrawItem,
+        Geometry, HighwayKind, LanduseKind, LatLong, MapReadResult, MapStats, MercatorProjection,
+        NaturalKind, PoiWayBundle, PointOfInterest, Tag, WaterwayKind, Way, WayClass,
+    };
+
+    fn line(nodes: &[(f64, f64)]) -> Vec<LatLong> {
+        nodes
+            .iter()
+            .map(|&(latitude, longitude)| LatLong::new(latitude, longitude))
+            .collect()
+    }
+
+    fn sample_result() -> MapReadResult {
+        let mut result = MapReadResult::new();
+        result.add(PoiWayBundle::new(
+            vec![
+                PointOfInterest::new(0, Vec::new(), LatLong::new(0.01, 0.01)),
+                PointOfInterest::new(0, Vec::new(), LatLong::new(0.02, 0.02)),
+            ],
+            vec![Way::new(
+                0,
+                Vec::new(),
+                vec![line(&[(0.0, 0.0), (0.0, 0.1), (0.1, 0.1)])],
+                None,
+            )],
+        ));
+        result.add(PoiWayBundle::new(
+            Vec::new(),
+            vec![Way::new(
+                0,
+                Vec::new(),
+                vec![
+                    line(&[(0.0, 0.0), (0.1, 0.0)]),
+                    line(&[(0.2, 0.0), (0.3, 0.0), (0.4, 0.0), (0.5, 0.0)]),
+                ],
+                None,
+            )],
+        ));
+        result
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = sample_result().stats();
+
+        assert_eq!(
+            stats,
+            MapStats {
+                poi_count: 2,
+                way_count: 2,
+                total_way_nodes: 9,
+                bundle_count: 2,
+                has_water: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_drawing_order() {
+        let square = line(&[(0.0, 0.0), (0.0, 0.1), (0.1, 0.1), (0.1, 0.0), (0.0, 0.0)]);
+        let road = line(&[(0.0, 0.0), (0.1, 0.1)]);
+
+        let mut result = MapReadResult::new();
+        result.add(PoiWayBundle::new(
+            vec![PointOfInterest::new(
+                0,
+                Vec::new(),
+                LatLong::new(0.05, 0.05),
+            )],
+            vec![
+                Way::new(0, Vec::new(), vec![road.clone()], None),
+                Way::new(0, Vec::new(), vec![square.clone()], None),
+            ],
+        ));
+        result.add(PoiWayBundle::new(
+            Vec::new(),
+            vec![Way::new(-1, Vec::new(), vec![road], None)],
+        ));
+
+        let order: Vec<(i8, &str)> = result
+            .drawing_order()
+            .iter()
+            .map(|item| match item {
+                DrawItem::Area(way) => (way.layer.0, "area"),
+                DrawItem::Line(way) => (way.layer.0, "line"),
+                DrawItem::Poi(poi) => (poi.layer.0, "poi"),
+            })
+            .collect();
+
+        assert_eq!(
+            order,
+            vec![(-1, "line"), (0, "area"), (0, "line"), (0, "poi")]
+        );
+    }
+
+    #[test]
+    fn test_centroid() {
+        let square = Way::new(
+            0,
+            Vec::new(),
+            vec![line(&[
+                (0.0, 0.0),
+                (0.0, 2.0),
+                (2.0, 2.0),
+                (2.0, 0.0),
+                (0.0, 0.0),
+            ])],
+            None,
+        );
+        let center = centroid(&square).unwrap();
+        assert!((center.latitude - 1.0).abs() < 1e-9);
+        assert!((center.longitude - 1.0).abs() < 1e-9);
+
+        // Midpoint of the longest edge lies on the polyline
+        let polyline = Way::new(
+            0,
+            Vec::new(),
+            vec![line(&[(0.0, 0.0), (0.0, 1.0), (3.0, 1.0)])],
+            None,
+        );
+        assert_eq!(centroid(&polyline), Some(LatLong::new(1.5, 1.0)));
+
+        let empty = Way::new(0, Vec::new(), Vec::new(), None);
+        assert_eq!(centroid(&empty), None);
+    }
+
+    #[test]
+    fn test_rings() {
+        // Counter-clockwise outer square with a clockwise hole
+        let outer = line(&[(0.0, 0.0), (0.0, 4.0), (4.0, 4.0), (4.0, 0.0), (0.0, 0.0)]);
+        let inner = line(&[(1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0), (1.0, 1.0)]);
+        let way = Way::new(0, Vec::new(), vec![inner.clone(), outer.clone()], None);
+
+        let (outer_rings, inner_rings) = way.rings();
+        assert_eq!(outer_rings, vec![outer.as_slice()]);
+        assert_eq!(inner_rings, vec![inner.as_slice()]);
+    }
+
+    #[test]
+    fn test_segments() {
+        let short = line(&[(0.0, 0.0), (0.0, 1.0)]);
+        let long = line(&[(1.0, 0.0), (1.0, 1.0), (2.0, 1.0), (2.0, 0.0)]);
+        let tied = line(&[(3.0, 0.0), (3.0, 1.0), (4.0, 1.0), (4.0, 0.0)]);
+        let way = Way::new(
+            0,
+            Vec::new(),
+            vec![short.clone(), long.clone(), tied.clone()],
+            None,
+        );
+
+        assert_eq!(way.segments(), &[short.clone(), long.clone(), tied]);
+        assert_eq!(way.outer_segment(), Some(short.as_slice()));
+        assert_eq!(way.longest_segment(), Some(long.as_slice()));
+
+        let empty = Way::new(0, Vec::new(), Vec::new(), None);
+        assert_eq!(empty.outer_segment(), None);
+        assert_eq!(empty.longest_segment(), None);
+    }
+
+    #[test]
+    fn test_into_features() {
+        let outer = line(&[(0.0, 0.0), (0.0, 4.0), (4.0, 4.0), (4.0, 0.0), (0.0, 0.0)]);
+        let inner = line(&[(1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0), (1.0, 1.0)]);
+        let mut result = MapReadResult::new();
+        result.add(PoiWayBundle::new(
+            vec![PointOfInterest::new(
+                1,
+                vec![Tag::new("amenity", "cafe")],
+                LatLong::new(3.0, 3.0),
+            )],
+            vec![Way::new(
+                2,
+                vec![Tag::new("building", "yes")],
+                vec![inner.clone(), outer.clone()],
+                None,
+            )],
+        ));
+
+        let features = result.into_features();
+        assert_eq!(features.len(), 2);
+        match &features[0].geometry {
+            Geometry::Point(position) => assert_eq!(*position, LatLong::new(3.0, 3.0)),
+            other => panic!("expected a point, got {:?}", other),
+        }
+        assert_eq!(features[0].layer.0, 1);
+        assert_eq!(features[0].tags[0].as_value(), "cafe");
+        match &features[1].geometry {
+            Geometry::Polygon {
+                outer: outer_ring,
+                inners,
+            } => {
+                assert_eq!(outer_ring, &outer);
+                assert_eq!(inners, &vec![inner]);
+            }
+            other => panic!("expected a polygon, got {:?}", other),
+        }
+        assert_eq!(features[1].layer.0, 2);
+        assert_eq!(features[1].tags[0].as_key(), "building");
+    }
+
+    #[test]
+    fn test_clip_way_to_bbox() {
+        let bbox = BoundingBox::new(0.0, 0.0, 1.0, 1.0).unwrap();
+
+        // Endpoints of a crossing line move to the box edges
+        let crossing = Way::new(0, Vec::new(), vec![line(&[(0.5, -1.0), (0.5, 2.0)])], None);
+        let clipped = clip_way_to_bbox(&crossing, &bbox).unwrap();
+        assert_eq!(clipped.way_nodes, vec![line(&[(0.5, 0.0), (0.5, 1.0)])]);
+
+        // Leaving and re-entering the box splits the line
+        let zigzag = Way::new(
+            0,
+            Vec::new(),
+            vec![line(&[(0.5, 0.5), (2.0, 0.5), (0.5, 0.75)])],
+            None,
+        );
+        let clipped = clip_way_to_bbox(&zigzag, &bbox).unwrap();
+        assert_eq!(clipped.way_nodes.len(), 2);
+        assert_eq!(clipped.way_nodes[0], line(&[(0.5, 0.5), (1.0, 0.5)]));
+        assert_eq!(clipped.way_nodes[1][1], LatLong::new(0.5, 0.75));
+
+        let outside = Way::new(0, Vec::new(), vec![line(&[(2.0, 2.0), (3.0, 3.0)])], None);
+        assert!(clip_way_to_bbox(&outside, &bbox).is_none());
+
+        // A polygon partially inside keeps only its inner part, still closed
+        let square = Way::new(
+            0,
+            Vec::new(),
+            vec![line(&[
+                (0.5, 0.5),
+                (0.5, 1.5),
+                (1.5, 1.5),
+                (1.5, 0.5),
+                (0.5, 0.5),
+            ])],
+            None,
+        );
+        let clipped = clip_way_to_bbox(&square, &bbox).unwrap();
+        assert!(clipped.is_closed());
+        assert!((clipped.area() - 0.25).abs() < 1e-9);
+        assert!(clipped.way_nodes[0]
+            .iter()
+            .all(|node| bbox.contains(node.latitude, node.longitude)));
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test]
+    fn test_feature_builders() {
+        use reader::{PoiBuilder, WayBuilder};
+
+        let way = WayBuilder::new()
+            .tag("building", "yes")
+            .tag("name", "Court")
+            .layer(2)
+            .node(0.0, 0.0)
+            .node(0.0, 1.0)
+            .node(1.0, 1.0)
+            .node(0.0, 0.0)
+            .segment()
+            .node(0.2, 0.5)
+            .node(0.4, 0.5)
+            .build();
+        assert_eq!(way.layer, 2);
+        let tags: Vec<(&str, &str)> = way
+            .tags
+            .iter()
+            .map(|tag| (tag.as_key(), tag.as_value()))
+            .collect();
+        assert_eq!(tags, vec![("building", "yes"), ("name", "Court")]);
+        assert_eq!(
+            way.way_nodes,
+            vec![
+                line(&[(0.0, 0.0), (0.0, 1.0), (1.0, 1.0), (0.0, 0.0)]),
+                line(&[(0.2, 0.5), (0.4, 0.5)]),
+            ]
+        );
+        assert_eq!(way.classify(), WayClass::Building);
+
+        let poi = PoiBuilder::new(0.0, 0.0)
+            .tag("amenity", "cafe")
+            .layer(-1)
+            .node(52.5, 13.4)
+            .build();
+        assert_eq!(poi.layer, -1);
+        assert_eq!(poi.tags.len(), 1);
+        assert_eq!(poi.tags[0].as_value(), "cafe");
+        assert_eq!(poi.position, LatLong::new(52.5, 13.4));
+    }
+
+    #[cfg(feature = "geo")]
+    #[test]
+    fn test_to_geo_geometry() {
+        use geo_types::{Geometry, Point};
+
+        let closed = Way::new(
+            0,
+            Vec::new(),
+            vec![
+                line(&[(0.0, 0.0), (0.0, 2.0), (2.0, 2.0), (2.0, 0.0), (0.0, 0.0)]),
+                line(&[(0.5, 0.5), (1.0, 0.5), (1.0, 1.0), (0.5, 0.5)]),
+            ],
+            None,
+        );
+        let Geometry::Polygon(polygon) = closed.to_geo_geometry() else {
+            panic!("closed way is not a polygon");
+        };
+        assert_eq!(polygon.exterior().0.len(), 5);
+        assert_eq!(polygon.interiors().len(), 1);
+        assert_eq!(polygon.interiors()[0].0.len(), 4);
+
+        let open = Way::new(
+            0,
+            Vec::new(),
+            vec![line(&[(0.0, 0.0), (0.0, 1.0), (1.0, 2.0)])],
+            None,
+        );
+        let Geometry::LineString(line_string) = open.to_geo_geometry() else {
+            panic!("open way is not a line string");
+        };
+        assert_eq!(line_string.0.len(), 3);
+        assert_eq!((line_string.0[2].x, line_string.0[2].y), (2.0, 1.0));
+
+        let poi = PointOfInterest::new(0, Vec::new(), LatLong::new(52.5, 13.4));
+        assert_eq!(Point::from(&poi), Point::new(13.4, 52.5));
+    }
+
+    #[test]
+    fn test_cluster_pois() {
+        let poi = |latitude, longitude| {
+            PointOfInterest::new(0, Vec::new(), LatLong::new(latitude, longitude))
+        };
+        let pois = vec![
+            poi(52.5000, 13.4000),
+            poi(52.5002, 13.4002),
+            poi(52.5004, 13.4004),
+            poi(48.1000, 11.5000),
+        ];
+
+        let clusters = cluster_pois(&pois, 8, 64);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].count, 3);
+        assert!((clusters[0].position.latitude - 52.5002).abs() < 1e-9);
+        assert!((clusters[0].position.longitude - 13.4002).abs() < 1e-9);
+        assert_eq!(clusters[1].count, 1);
+        assert_eq!(clusters[1].position, LatLong::new(48.1, 11.5));
+
+        assert!(cluster_pois(&[], 8, 64).is_empty());
+    }
+
+    #[test]
+    fn test_densify() {
+        // One degree of latitude is about 111.2 km
+        let long = line(&[(0.0, 0.0), (1.0, 0.0)]);
+        let densified = densify(&long, 10_000.0);
+        assert_eq!(densified.len(), 13);
+        assert_eq!(densified[0], long[0]);
+        assert_eq!(densified[12], long[1]);
+        for node in &densified[1..12] {
+            assert!(node.longitude.abs() < 1e-9);
+            assert!(node.latitude > 0.0 && node.latitude < 1.0);
+        }
+        assert!((densified[6].latitude - 0.5).abs() < 1e-9);
+
+        let short = line(&[(0.0, 0.0), (0.0001, 0.0001)]);
+        assert_eq!(densify(&short, 100.0), short);
+
+        let way = Way::new(0, Vec::new(), vec![long], None).densified(10_000.0);
+        assert_eq!(way.way_nodes[0], densified);
+    }
+
+    #[test]
+    fn test_classify() {
+        let tagged = |key: &str, value: &str| {
+            Way::new(
+                0,
+                vec![Tag::new(key, value)],
+                vec![line(&[(0.0, 0.0), (0.1, 0.1)])],
+                None,
+            )
+        };
+
+        assert_eq!(
+            tagged("highway", "path").classify(),
+            WayClass::Highway(HighwayKind::Path)
+        );
+        assert_eq!(
+            tagged("waterway", "river").classify(),
+            WayClass::Waterway(WaterwayKind::River)
+        );
+        assert_eq!(
+            tagged("natural", "water").classify(),
+            WayClass::Natural(NaturalKind::Water)
+        );
+        // Each landuse value keeps its own kind, unknown ones are Other
+        assert_eq!(
+            tagged("landuse", "commercial").classify(),
+            WayClass::Landuse(LanduseKind::Commercial)
+        );
+        assert_eq!(
+            tagged("landuse", "industrial").classify(),
+            WayClass::Landuse(LanduseKind::Industrial)
+        );
+        assert_eq!(
+            tagged("landuse", "cemetery").classify(),
+            WayClass::Landuse(LanduseKind::Other)
+        );
+        let untagged = Way::new(0, Vec::new(), vec![line(&[(0.0, 0.0)])], None);
+        assert_eq!(untagged.classify(), WayClass::Other);
+    }
+
+    #[test]
+    fn test_stitch_ways() {
+        let road = |nodes: &[(f64, f64)]| {
+            Way::new(
+                0,
+                vec![Tag::new("highway", "path")],
+                vec![line(nodes)],
+                None,
+            )
+        };
+
+        let mut result = MapReadResult::new();
+        result.add(PoiWayBundle::new(
+            Vec::new(),
+            vec![road(&[(0.0, 0.0), (0.0, 0.1)])],
+        ));
+        result.add(PoiWayBundle::new(
+            Vec::new(),
+            vec![road(&[(0.0, 0.1), (0.0, 0.2), (0.0, 0.3)])],
+        ));
+        result.stitch_ways();
+
+        let ways: Vec<&Way> = result.ways().collect();
+        assert_eq!(ways.len(), 1);
+        assert_eq!(
+            ways[0].way_nodes,
+            vec![line(&[(0.0, 0.0), (0.0, 0.1), (0.0, 0.2), (0.0, 0.3)])]
+        );
+
+        // Joining these would close a ring
+        let mut result = MapReadResult::new();
+        result.add(PoiWayBundle::new(
+            Vec::new(),
+            vec![
+                road(&[(0.0, 0.0), (0.0, 0.1), (0.1, 0.1)]),
+                road(&[(0.1, 0.1), (0.0, 0.0)]),
+            ],
+        ));
+        result.stitch_ways();
+        assert_eq!(result.ways().count(), 2);
+    }
+
+    #[test]
+    fn test_way_tiles() {
+        // From the middle of tile (512, 511) to the middle of tile (513, 512)
+        let middle = |tile_x: i64, tile_y: i64| {
+            (
+                (MercatorProjection::tile_y_to_latitude(tile_y, 10)
+                    + MercatorProjection::tile_y_to_latitude(tile_y + 1, 10))
+                    / 2.0,
+                (MercatorProjection::tile_x_to_longitude(tile_x, 10)
+                    + MercatorProjection::tile_x_to_longitude(tile_x + 1, 10))
+                    / 2.0,
+            )
+        };
+        let way = Way::new(
+            0,
+            Vec::new(),
+            vec![line(&[
+                middle(512, 511),
+                middle(513, 511),
+                middle(513, 512),
+            ])],
+            None,
+        );
+
+        let tiles: Vec<(i64, i64)> = way_tiles(&way, 10, 256)
+            .iter()
+            .map(|tile| (tile.tile_x, tile.tile_y))
+            .collect();
+        assert_eq!(tiles, vec![(512, 511), (513, 511), (512, 512), (513, 512)]);
+
+        let empty = Way::new(0, Vec::new(), Vec::new(), None);
+        assert!(way_tiles(&empty, 10, 256).is_empty());
+    }
+}
@@ -0,0 +1,186 @@
+#[cfg(test)]
+mod tests {
+    use reader::{MercatorProjection, Tile};
+
+    #[test]
+    fn test_pixel_coordinates_scale_with_tile_size() {
+        for zoom_level in [0, 5, 12, 18] {
+            for (latitude, longitude) in [(0.0, 0.0), (26.74, 93.9), (-45.5, -120.25)] {
+                let x_256 =
+                    MercatorProjection::longitude_to_pixel_x_with_size(longitude, zoom_level, 256);
+                let x_512 =
+                    MercatorProjection::longitude_to_pixel_x_with_size(longitude, zoom_level, 512);
+                let y_256 =
+                    MercatorProjection::latitude_to_pixel_y_with_size(latitude, zoom_level, 256);
+                let y_512 =
+                    MercatorProjection::latitude_to_pixel_y_with_size(latitude, zoom_level, 512);
+
+                assert_eq!(x_512, x_256 * 2.0);
+                assert_eq!(y_512, y_256 * 2.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tile_pixel_helpers_use_tile_size() {
+        let tile_256 = Tile::new(2, 1, 2, 256);
+        let tile_512 = Tile::new(2, 1, 2, 512);
+
+        // The tile's own corner is its origin
+        assert_eq!(tile_256.longitude_to_pixel_x(0.0), 0.0);
+        assert_eq!(tile_512.longitude_to_pixel_x(0.0), 0.0);
+
+        let x_256 = tile_256.longitude_to_pixel_x(45.0);
+        let y_256 = tile_256.latitude_to_pixel_y(33.0);
+        assert_eq!(tile_512.longitude_to_pixel_x(45.0), x_256 * 2.0);
+        assert_eq!(tile_512.latitude_to_pixel_y(33.0), y_256 * 2.0);
+    }
+
+    #[test]
+    fn test_tile_round_trip() {
+        for zoom_level in 0..=18u8 {
+            let n = MercatorProjection::tile_count(zoom_level);
+            let step = (n / 4096).max(1);
+
+            let mut tile = 0;
+            while tile < n {
+                let latitude = MercatorProjection::tile_y_to_latitude(tile, zoom_level);
+                let longitude = MercatorProjection::tile_x_to_longitude(tile, zoom_level);
+
+                assert_eq!(
+                    MercatorProjection::latitude_to_tile_y(latitude, zoom_level),
+                    tile,
+                    "tile_y {} at zoom {}",
+                    tile,
+                    zoom_level
+                );
+                assert_eq!(
+                    MercatorProjection::longitude_to_tile_x(longitude, zoom_level),
+                    tile,
+                    "tile_x {} at zoom {}",
+                    tile,
+                    zoom_level
+                );
+                tile += step;
+            }
+
+            // The last tile also round-trips, and the map edges stay in range
+            let last = n - 1;
+            let latitude = MercatorProjection::tile_y_to_latitude(last, zoom_level);
+            assert_eq!(
+                MercatorProjection::latitude_to_tile_y(latitude, zoom_level),
+                last
+            );
+            assert_eq!(
+                MercatorProjection::longitude_to_tile_x(180.0, zoom_level),
+                last
+            );
+            assert_eq!(
+                MercatorProjection::latitude_to_tile_y(-90.0, zoom_level),
+                last
+            );
+        }
+    }
+
+    #[test]
+    fn test_web_mercator_meters() {
+        let (x, y) = MercatorProjection::lat_long_to_meters(0.0, 0.0);
+        assert!(x.abs() < 1e-6);
+        assert!(y.abs() < 1e-6);
+
+        let (x, y) = MercatorProjection::lat_long_to_meters(85.05112877980659, 180.0);
+        assert!((x - 20037508.34).abs() < 0.01);
+        assert!((y - 20037508.34).abs() < 0.01);
+        let (x, y) = MercatorProjection::lat_long_to_meters(-85.05112877980659, -180.0);
+        assert!((x + 20037508.34).abs() < 0.01);
+        assert!((y + 20037508.34).abs() < 0.01);
+
+        for (latitude, longitude) in [(52.52, 13.405), (-33.8688, 151.2093), (0.0, -179.9)] {
+            let (x, y) = MercatorProjection::lat_long_to_meters(latitude, longitude);
+            let lat_long = MercatorProjection::meters_to_lat_long(x, y);
+            let (x2, y2) =
+                MercatorProjection::lat_long_to_meters(lat_long.latitude, lat_long.longitude);
+            assert!((x - x2).abs() < 1e-3);
+            assert!((y - y2).abs() < 1e-3);
+            assert!((lat_long.latitude - latitude).abs() < 1e-9);
+            assert!((lat_long.longitude - longitude).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_tile_pixel_bounds_are_contiguous() {
+        for tile_size in [256, 512] {
+            let tile = Tile::new(8580, 5556, 14, tile_size);
+            let right_neighbor = Tile::new(8581, 5556, 14, tile_size);
+            let lower_neighbor = Tile::new(8580, 5557, 14, tile_size);
+
+            let (left, top, right, bottom) = tile.pixel_bounds();
+            assert_eq!(left, 8580 * tile_size as i64);
+            assert_eq!(top, 5556 * tile_size as i64);
+            assert_eq!(right - left, tile_size as i64);
+            assert_eq!(bottom - top, tile_size as i64);
+            assert_eq!(right, right_neighbor.pixel_bounds().0);
+            assert_eq!(bottom, lower_neighbor.pixel_bounds().1);
+
+            let origin = (left - 10, top - 20);
+            assert_eq!(
+                tile.screen_rect_in(origin),
+                (10, 20, 10 + tile_size as i64, 20 + tile_size as i64)
+            );
+        }
+    }
+
+    #[test]
+    fn test_quadkey_round_trip() {
+        let tile = Tile::new(3, 5, 3, 256);
+        assert_eq!(tile.to_quadkey(), "213");
+
+        let parsed = Tile::from_quadkey("213", 256).unwrap();
+        assert_eq!(
+            (
+                parsed.tile_x,
+                parsed.tile_y,
+                parsed.zoom_level,
+                parsed.tile_size
+            ),
+            (3, 5, 3, 256)
+        );
+
+        let root = Tile::from_quadkey("", 512).unwrap();
+        assert_eq!((root.tile_x, root.tile_y, root.zoom_level), (0, 0, 0));
+        assert_eq!(root.to_quadkey(), "");
+
+        assert!(Tile::from_quadkey("2140", 256).is_err());
+        assert!(Tile::from_quadkey("21a", 256).is_err());
+    }
+
+    #[test]
+    fn test_tile_neighbor() {
+        let tile = Tile::new(0, 3, 2, 256);
+        let left = tile.neighbor(-1, 0).unwrap();
+        assert_eq!((left.tile_x, left.tile_y, left.zoom_level), (3, 3, 2));
+        let right = Tile::new(3, 0, 2, 256).neighbor(1, 0).unwrap();
+        assert_eq!((right.tile_x, right.tile_y), (0, 0));
+
+        assert!(tile.neighbor(0, 1).is_none());
+        assert!(Tile::new(1, 0, 2, 256).neighbor(0, -1).is_none());
+    }
+
+    #[test]
+    fn test_ground_resolution() {
+        let world = Tile::new(0, 0, 0, 256);
+        assert!((world.ground_resolution() - 156_543.034).abs() < 0.01);
+        assert!((Tile::new(0, 0, 0, 512).ground_resolution() - 78_271.517).abs() < 0.01);
+
+        // Tiles just south of the equator, halving with each zoom level
+        for zoom in 8..16u8 {
+            let tile = Tile::new(1 << (zoom - 1), 1 << (zoom - 1), zoom, 256);
+            let next = Tile::new(1 << zoom, 1 << zoom, zoom + 1, 256);
+            let ratio = tile.ground_resolution() / next.ground_resolution();
+            assert!((ratio - 2.0).abs() < 1e-3, "zoom {}: {}", zoom, ratio);
+        }
+
+        let scale = world.scale_denominator(96.0);
+        assert!((scale - 156_543.034 * 96.0 / 0.0254).abs() < 100.0);
+    }
+}
@@ -0,0 +1,65 @@
+#[cfg(test)]
+mod tests {
+    use reader::raster::{draw_thick_line, fill_polygon};
+
+    const WIDTH: usize = 10;
+    const HEIGHT: usize = 10;
+    const COLOR: u32 = 0x00FF0000;
+
+    fn pixel(buffer: &[u32], x: usize, y: usize) -> u32 {
+        buffer[y * WIDTH + x]
+    }
+
+    #[test]
+    fn test_fill_triangle() {
+        let mut buffer = vec![0u32; WIDTH * HEIGHT];
+        let triangle = [(1, 1), (8, 1), (1, 8)];
+
+        fill_polygon(&triangle, COLOR, &mut buffer, WIDTH, HEIGHT);
+
+        // Inside the triangle
+        assert_eq!(pixel(&buffer, 2, 2), COLOR);
+        assert_eq!(pixel(&buffer, 1, 5), COLOR);
+        assert_eq!(pixel(&buffer, 5, 2), COLOR);
+
+        // Outside the triangle
+        assert_eq!(pixel(&buffer, 0, 0), 0);
+        assert_eq!(pixel(&buffer, 8, 8), 0);
+        assert_eq!(pixel(&buffer, 7, 6), 0);
+        assert_eq!(pixel(&buffer, 9, 9), 0);
+    }
+
+    #[test]
+    fn test_fill_polygon_clips_to_buffer() {
+        let mut buffer = vec![0u32; WIDTH * HEIGHT];
+        let square = [(-5, -5), (20, -5), (20, 20), (-5, 20)];
+
+        fill_polygon(&square, COLOR, &mut buffer, WIDTH, HEIGHT);
+
+        assert!(buffer.iter().all(|&p| p == COLOR));
+    }
+
+    #[test]
+    fn test_fill_polygon_needs_three_points() {
+        let mut buffer = vec![0u32; WIDTH * HEIGHT];
+
+        fill_polygon(&[(0, 0), (9, 9)], COLOR, &mut buffer, WIDTH, HEIGHT);
+
+        assert!(buffer.iter().all(|&p| p == 0));
+    }
+
+    #[test]
+    fn test_draw_thick_line() {
+        let mut buffer = vec![0u32; WIDTH * HEIGHT];
+
+        draw_thick_line((1, 5), (8, 5), COLOR, 3, &mut buffer, WIDTH);
+
+        for x in 1..=8 {
+            assert_eq!(pixel(&buffer, x, 4), COLOR);
+            assert_eq!(pixel(&buffer, x, 5), COLOR);
+            assert_eq!(pixel(&buffer, x, 6), COLOR);
+        }
+        assert_eq!(pixel(&buffer, 5, 3), 0);
+        assert_eq!(pixel(&buffer, 5, 7), 0);
+    }
+}
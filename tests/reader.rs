@@ -0,0 +1,141 @@
+#[cfg(test)]
+mod tests {
+    use reader::{ByteOrder, MapFileErrorKind, ReadBuffer, Serializer, Tag};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_read_past_end_is_eof() {
+        let mut read_buffer = ReadBuffer::new(Cursor::new(vec![1u8, 2, 3, 4]));
+
+        read_buffer.read_from_file(2).unwrap();
+        assert_eq!(read_buffer.read_byte().unwrap(), 1);
+
+        let error = read_buffer.read_from_file(4).unwrap_err();
+        assert_eq!(error.kind(), &MapFileErrorKind::UnexpectedEof);
+
+        let error = read_buffer.read_from_file_at_offset(3, 2).unwrap_err();
+        assert_eq!(error.kind(), &MapFileErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_read_too_large_is_distinct_from_eof() {
+        let mut read_buffer = ReadBuffer::new(Cursor::new(Vec::<u8>::new()));
+
+        let error = read_buffer.read_from_file(usize::MAX).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            MapFileErrorKind::BufferTooLarge { length, .. } if *length == usize::MAX
+        ));
+    }
+
+    #[test]
+    fn test_read_negative_signed_int() {
+        // VBE-S: -50 in one byte (sign bit 0x40), -200 in two bytes
+        let mut read_buffer = ReadBuffer::from_bytes(vec![0x72, 0xC8, 0x41, 0x32]);
+
+        assert_eq!(read_buffer.read_signed_int().unwrap(), -50);
+        assert_eq!(read_buffer.read_signed_int().unwrap(), -200);
+        assert_eq!(read_buffer.read_signed_int().unwrap(), 50);
+    }
+
+    #[test]
+    fn test_byte_order() {
+        let bytes = vec![
+            0x00, 0x00, 0x00, 0x01, // int
+            0x00, 0x02, // short
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, // long
+            0x3f, 0x80, 0x00, 0x00, // float
+        ];
+
+        let mut big = ReadBuffer::from_bytes(bytes.clone());
+        assert_eq!(big.byte_order(), ByteOrder::BigEndian);
+        assert_eq!(big.read_int().unwrap(), 1);
+        assert_eq!(big.read_short().unwrap(), 2);
+        assert_eq!(big.read_long().unwrap(), 3);
+        assert_eq!(big.read_float().unwrap(), 1.0);
+
+        let mut little = ReadBuffer::from_bytes(bytes);
+        little.set_byte_order(ByteOrder::LittleEndian);
+        assert_eq!(little.read_int().unwrap(), 1 << 24);
+        assert_eq!(little.read_short().unwrap(), 2 << 8);
+        assert_eq!(little.read_long().unwrap(), 3 << 56);
+        assert_eq!(little.read_float().unwrap(), f32::from_bits(0x803f));
+
+        // Variable-length numbers read the same either way
+        let mut little = ReadBuffer::from_bytes(vec![0x96, 0x01]);
+        little.set_byte_order(ByteOrder::LittleEndian);
+        assert_eq!(little.read_unsigned_int().unwrap(), 150);
+    }
+
+    #[test]
+    fn test_read_tags_rejects_truncated_ids() {
+        let tags: Vec<Tag> = ["amenity=cafe", "shop=bakery", "name=%s"]
+            .into_iter()
+            .map(|tag| Tag::from_string(tag).unwrap())
+            .collect();
+
+        // Fifteen tags declared, three ids present
+        let mut read_buffer = ReadBuffer::from_bytes(vec![0, 1, 0]);
+        let error = read_buffer.read_tags(&tags, 15).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("feature declares 15 tags, but only 3 bytes remain"));
+
+        // Enough bytes, but the last id never terminates
+        let mut read_buffer = ReadBuffer::from_bytes(vec![1, 0x80, 0x80]);
+        let error = read_buffer.read_tags(&tags, 3).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("truncated tag id 2 of 3 declared"));
+    }
+
+    #[test]
+    fn test_read_tags_with_dynamic_values() {
+        let tags: Vec<Tag> = [
+            "amenity=cafe",
+            "ele=%i",
+            "roof:colour=%i",
+            "width=%f",
+            "ref=%s",
+            "lanes=%b",
+            "maxspeed=%h",
+        ]
+        .into_iter()
+        .map(|tag| Tag::from_string(tag).unwrap())
+        .collect();
+
+        let mut buffer = Vec::new();
+        for id in [0, 1, 2, 3, 4, 5, 6] {
+            Serializer::write_unsigned_int(&mut buffer, id);
+        }
+        Serializer::write_int(&mut buffer, -12);
+        Serializer::write_int(&mut buffer, 0xff8800);
+        Serializer::write_int(&mut buffer, 2.5f32.to_bits() as i32);
+        Serializer::write_utf8_encoded_string(&mut buffer, "B 42");
+        buffer.push(3);
+        Serializer::write_short(&mut buffer, 120);
+
+        let mut read_buffer = ReadBuffer::from_bytes(buffer);
+        let resolved = read_buffer.read_tags(&tags, 7).unwrap();
+        let pairs: Vec<(&str, &str)> = resolved
+            .iter()
+            .map(|tag| (tag.as_key(), tag.as_value()))
+            .collect();
+        assert_eq!(
+            pairs,
+            [
+                ("amenity", "cafe"),
+                ("ele", "-12"),
+                ("roof:colour", "#ff8800"),
+                ("width", "2.5"),
+                ("ref", "B 42"),
+                ("lanes", "3"),
+                ("maxspeed", "120"),
+            ]
+        );
+        assert_eq!(
+            read_buffer.get_buffer_position(),
+            read_buffer.get_buffer_size()
+        );
+    }
+}
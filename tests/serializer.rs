@@ -0,0 +1,58 @@
+#[cfg(test)]
+mod tests {
+    use reader::{ReadBuffer, Serializer};
+
+    #[test]
+    fn test_variable_byte_round_trip() {
+        let signed = [
+            0,
+            1,
+            -1,
+            63,
+            -63,
+            64,
+            -64,
+            -50,
+            8191,
+            -8192,
+            i32::MAX,
+            -i32::MAX,
+        ];
+        let unsigned = [0, 1, 127, 128, 16_383, 16_384, u32::MAX];
+
+        let mut buffer = Vec::new();
+        for &value in &signed {
+            Serializer::write_signed_int(&mut buffer, value);
+        }
+        for &value in &unsigned {
+            Serializer::write_unsigned_int(&mut buffer, value);
+        }
+
+        let mut read_buffer = ReadBuffer::from_bytes(buffer);
+        for &value in &signed {
+            assert_eq!(read_buffer.read_signed_int().unwrap(), value);
+        }
+        for &value in &unsigned {
+            assert_eq!(read_buffer.read_unsigned_int().unwrap(), value);
+        }
+        assert_eq!(
+            read_buffer.get_buffer_position(),
+            read_buffer.get_buffer_size()
+        );
+    }
+
+    #[test]
+    fn test_fixed_width_round_trip() {
+        let mut buffer = Vec::new();
+        Serializer::write_int(&mut buffer, -123_456);
+        Serializer::write_long(&mut buffer, i64::MIN + 1);
+        Serializer::write_short(&mut buffer, -2);
+        Serializer::write_utf8_encoded_string(&mut buffer, "Straße");
+
+        let mut read_buffer = ReadBuffer::from_bytes(buffer);
+        assert_eq!(read_buffer.read_int().unwrap(), -123_456);
+        assert_eq!(read_buffer.read_long().unwrap(), i64::MIN + 1);
+        assert_eq!(read_buffer.read_short().unwrap(), -2);
+        assert_eq!(read_buffer.read_utf8_encoded_string().unwrap(), "Straße");
+    }
+}
@@ -0,0 +1,168 @@
+#[cfg(test)]
+mod tests {
+    use reader::{BoundingBox, LabelPosition, LatLong, LatLongE6, Tag, Way};
+
+    #[test]
+    fn test_tag_from_string() {
+        let tag = Tag::from_string("highway=primary").unwrap();
+        assert_eq!(tag.as_key(), "highway");
+        assert_eq!(tag.as_value(), "primary");
+
+        // Only the first separator splits, values may contain '='
+        let tag = Tag::from_string("note=a=b").unwrap();
+        assert_eq!(tag.as_key(), "note");
+        assert_eq!(tag.as_value(), "a=b");
+
+        let tag = Tag::from_string("key=").unwrap();
+        assert_eq!(tag.as_key(), "key");
+        assert_eq!(tag.as_value(), "");
+
+        assert!(Tag::from_string("=value").is_err());
+        assert!(Tag::from_string("novalue").is_err());
+    }
+
+    #[test]
+    fn test_extend_meters_scales_longitude() {
+        let bbox = BoundingBox::new(59.9, 10.0, 60.1, 10.2).unwrap();
+        let extended = bbox.extend_meters(1000);
+
+        let latitude_delta = bbox.min_latitude - extended.min_latitude;
+        let longitude_delta = bbox.min_longitude - extended.min_longitude;
+        assert!((longitude_delta / latitude_delta - 2.0).abs() < 0.01);
+        assert!((extended.max_longitude - bbox.max_longitude - longitude_delta).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_extend_meters_clamps_longitude() {
+        let bbox = BoundingBox::new(0.0, -179.999, 1.0, 179.999).unwrap();
+        let extended = bbox.extend_meters(10_000);
+
+        assert_eq!(extended.min_longitude, -180.0);
+        assert_eq!(extended.max_longitude, 180.0);
+    }
+
+    #[test]
+    fn test_label_position_offsets_first_node() {
+        let label = LabelPosition::from_microdegrees(1_000_000, -250_000);
+        assert_eq!(label.lat, 1.0);
+        assert_eq!(label.lon, -0.25);
+
+        let position = label.apply_to(&LatLong::new(52.5, 13.25));
+        assert_eq!(position, LatLong::new(53.5, 13.0));
+    }
+
+    #[test]
+    fn test_lat_long_try_new() {
+        assert!(LatLong::try_new(91.0, 0.0).is_err());
+        assert!(LatLong::try_new(-90.5, 0.0).is_err());
+        assert!(LatLong::try_new(0.0, 180.1).is_err());
+        assert!(LatLong::try_new(f64::NAN, 0.0).is_err());
+
+        for (latitude, longitude) in [(0.0, 0.0), (90.0, 180.0), (-90.0, -180.0), (52.5, 13.4)] {
+            assert_eq!(
+                LatLong::try_new(latitude, longitude).unwrap(),
+                LatLong::new(latitude, longitude)
+            );
+        }
+    }
+
+    #[test]
+    fn test_microdegrees() {
+        let position = LatLong::new(0.08, -0.08);
+        assert_eq!(position.to_microdegrees(), (80_000, -80_000));
+
+        let e6 = LatLongE6::from(&position);
+        assert_eq!(
+            e6,
+            LatLongE6 {
+                lat_e6: 80_000,
+                lon_e6: -80_000
+            }
+        );
+        assert_eq!(LatLong::from(e6), position);
+        assert_eq!(LatLongE6::from(LatLong::from(e6)), e6);
+
+        let way = Way::new(
+            0,
+            Vec::new(),
+            vec![vec![
+                LatLong::new(52.5, 13.4),
+                LatLong::new(52.500001, 13.400002),
+            ]],
+            None,
+        );
+        assert_eq!(
+            way.nodes_e6(),
+            vec![vec![
+                LatLongE6 {
+                    lat_e6: 52_500_000,
+                    lon_e6: 13_400_000
+                },
+                LatLongE6 {
+                    lat_e6: 52_500_001,
+                    lon_e6: 13_400_002
+                },
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_bounding_box_from_lat_longs() {
+        let bounding_box = BoundingBox::from_lat_longs(&[
+            LatLong::new(1.0, 5.0),
+            LatLong::new(-2.0, 3.0),
+            LatLong::new(0.5, 7.0),
+        ])
+        .unwrap();
+        assert_eq!(bounding_box.min_latitude, -2.0);
+        assert_eq!(bounding_box.min_longitude, 3.0);
+        assert_eq!(bounding_box.max_latitude, 1.0);
+        assert_eq!(bounding_box.max_longitude, 7.0);
+
+        assert!(BoundingBox::from_lat_longs(&[]).is_err());
+    }
+
+    #[test]
+    fn test_center_point_wrapped() {
+        let wrapped = |min_longitude, max_longitude| BoundingBox {
+            min_latitude: -10.0,
+            min_longitude,
+            max_latitude: 10.0,
+            max_longitude,
+        };
+
+        let center = wrapped(170.0, -170.0).center_point_wrapped();
+        assert_eq!(center.latitude, 0.0);
+        assert_eq!(center.longitude.abs(), 180.0);
+        assert_eq!(wrapped(170.0, -170.0).get_center_point().longitude, 0.0);
+
+        assert_eq!(
+            wrapped(160.0, -170.0).center_point_wrapped().longitude,
+            175.0
+        );
+        assert_eq!(
+            wrapped(170.0, -160.0).center_point_wrapped().longitude,
+            -175.0
+        );
+        assert_eq!(wrapped(10.0, 20.0).center_point_wrapped().longitude, 15.0);
+    }
+
+    #[test]
+    fn test_expand_to_aspect() {
+        let square = BoundingBox::new(10.0, 20.0, 12.0, 22.0).unwrap();
+        assert_eq!(square.aspect_ratio(), 1.0);
+
+        let wide = square.expand_to_aspect(2.0);
+        assert_eq!(wide.aspect_ratio(), 2.0);
+        assert_eq!(wide.min_longitude, 19.0);
+        assert_eq!(wide.max_longitude, 23.0);
+        assert_eq!(wide.min_latitude, 10.0);
+        assert_eq!(wide.max_latitude, 12.0);
+
+        let wider = BoundingBox::new(0.0, 0.0, 1.0, 3.0).unwrap();
+        let unchanged = wider.expand_to_aspect(2.0);
+        assert_eq!(unchanged.min_longitude, 0.0);
+        assert_eq!(unchanged.max_longitude, 3.0);
+        assert_eq!(unchanged.aspect_ratio(), 3.0);
+    }
+}